@@ -0,0 +1,166 @@
+//cargo bench --bench broadphase
+//compares queries against every collider with queries through the Broadphase, on cubes laid out like the rotating_cubes example
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use bevy_ecs::{prelude::*, system::SystemState};
+use glam::{Quat, Vec2, Vec3};
+use vertix::{
+    collision::{
+        broadphase::{update_broadphase, Broadphase, UniformGrid2D},
+        spatial_query::{QueryFilter, SpatialQuery},
+        structs_3d::{Collider3D, Ray, OBB},
+    },
+    instance::Instance,
+};
+
+const SPACE_BETWEEN: f32 = 3.0;
+const RAYS: usize = 1000;
+//checking every pair is quadratic, past this it takes too long to be worth waiting for
+const MAX_BRUTE_FORCE_PAIRS: usize = 2_500;
+
+fn time<T>(mut run: impl FnMut() -> T) -> (Duration, T) {
+    //warm up, then take the best of a few runs
+    let mut result = run();
+    let mut best = Duration::MAX;
+    for _ in 0..5 {
+        let start = Instant::now();
+        result = black_box(run());
+        best = best.min(start.elapsed());
+    }
+    (best, result)
+}
+
+fn cube_world(count: usize) -> World {
+    let mut world = World::new();
+    let per_row = (count as f32).sqrt().ceil() as usize;
+    for i in 0..count {
+        let x = SPACE_BETWEEN * ((i % per_row) as f32 - per_row as f32 / 2.0);
+        let z = SPACE_BETWEEN * ((i / per_row) as f32 - per_row as f32 / 2.0);
+        //every other cube is turned so it reaches its neighbours, giving the pairs something to find
+        let rotation = if i % 2 == 0 { Quat::IDENTITY } else { Quat::from_rotation_y(0.6) };
+        world.spawn((
+            Instance { position: Vec3::new(x, 0.0, z), rotation, scale: Vec3::splat(1.3), ..Default::default() },
+            Collider3D::OBB(OBB::new(2.0, 2.0, 2.0)),
+        ));
+    }
+    world
+}
+
+fn rays(count: usize) -> Vec<Ray> {
+    let half_width = SPACE_BETWEEN * (count as f32).sqrt() / 2.0;
+    (0..RAYS)
+        .map(|i| {
+            let t = i as f32 / RAYS as f32;
+            let target = Vec3::new((t * 7.0).sin() * half_width, 0.0, (t * 13.0).cos() * half_width);
+            let origin = Vec3::new(0.0, 20.0, 0.0);
+            Ray { origin, direction: (target - origin).normalize() }
+        })
+        .collect()
+}
+
+fn bench_3d(count: usize) {
+    let rays = rays(count);
+    let mut world = cube_world(count);
+    let mut linear = SystemState::<SpatialQuery>::new(&mut world);
+    let (linear_rays, linear_hits) = time(|| {
+        let query = linear.get(&world);
+        rays.iter().filter(|ray| query.cast_ray(**ray, 1000.0, &QueryFilter::default()).is_some()).count()
+    });
+    let linear_pairs = (count <= MAX_BRUTE_FORCE_PAIRS).then(|| time(|| linear.get(&world).contact_pairs().len()));
+
+    let mut schedule = Schedule::default();
+    schedule.add_systems(update_broadphase);
+    //a fresh broadphase takes in every collider on its first update
+    let (build, _) = time(|| {
+        world.insert_resource(Broadphase::default());
+        schedule.run(&mut world);
+    });
+    let mut accelerated = SystemState::<SpatialQuery>::new(&mut world);
+    let (tree_rays, tree_hits) = time(|| {
+        let query = accelerated.get(&world);
+        rays.iter().filter(|ray| query.cast_ray(**ray, 1000.0, &QueryFilter::default()).is_some()).count()
+    });
+    let (tree_pairs, tree_pair_count) = time(|| accelerated.get(&world).contact_pairs().len());
+    assert_eq!(linear_hits, tree_hits);
+    //every cube moves a little, most stay inside their fattened boxes like they would frame to frame
+    let mut frame = 0;
+    let (update, _) = time(|| {
+        frame += 1;
+        for mut instance in world.query::<&mut Instance>().iter_mut(&mut world) {
+            instance.position.y = (frame as f32 * 0.05).sin() * 0.2;
+        }
+        schedule.run(&mut world);
+    });
+
+    let pairs_column = match linear_pairs {
+        Some((duration, pair_count)) => {
+            assert_eq!(pair_count, tree_pair_count);
+            format!("{:>12.2?}", duration)
+        }
+        None => format!("{:>12}", "skipped"),
+    };
+    println!(
+        "{:>7} | {:>12.2?} {:>12.2?} | {} {:>12.2?} | {:>12.2?} {:>12.2?} | {:>6}",
+        count, linear_rays, tree_rays, pairs_column, tree_pairs, build, update, tree_pair_count
+    );
+}
+
+fn bench_2d(count: usize) {
+    let per_row = (count as f32).sqrt().ceil() as usize;
+    let boxes: Vec<(Vec2, Vec2)> = (0..count)
+        .map(|i| {
+            let center = Vec2::new((i % per_row) as f32, (i / per_row) as f32) * 1.5;
+            (center - 0.8, center + 0.8)
+        })
+        .collect();
+    let brute_force = (count <= MAX_BRUTE_FORCE_PAIRS).then(|| {
+        time(|| {
+            let mut pairs = 0;
+            let boxes = black_box(&boxes);
+            for (i, (a_min, a_max)) in boxes.iter().enumerate() {
+                for (b_min, b_max) in &boxes[i + 1..] {
+                    if a_min.cmple(*b_max).all() && b_min.cmple(*a_max).all() {
+                        pairs += 1;
+                    }
+                }
+            }
+            pairs
+        })
+    });
+    let (build, grid) = time(|| {
+        let mut grid = UniformGrid2D::new(2.0);
+        for (i, (min, max)) in boxes.iter().enumerate() {
+            grid.insert(i, *min, *max);
+        }
+        grid
+    });
+    let (grid_pairs, pair_count) = time(|| grid.pairs().len());
+    let brute_force_column = match brute_force {
+        Some((duration, brute_force_count)) => {
+            assert_eq!(brute_force_count, pair_count);
+            format!("{:>12.2?}", duration)
+        }
+        None => format!("{:>12}", "skipped"),
+    };
+    println!("{:>7} | {} {:>12.2?} | {:>12.2?} | {:>6}", count, brute_force_column, grid_pairs, build, pair_count);
+}
+
+fn main() {
+    println!("3d, {} rays per run", RAYS);
+    println!(
+        "{:>7} | {:>12} {:>12} | {:>12} {:>12} | {:>12} {:>12} | {:>6}",
+        "cubes", "rays linear", "rays tree", "pairs linear", "pairs tree", "tree build", "tree update", "pairs"
+    );
+    for count in [100, 1_000, 10_000] {
+        bench_3d(count);
+    }
+    println!();
+    println!("2d");
+    println!("{:>7} | {:>12} {:>12} | {:>12} | {:>6}", "boxes", "pairs linear", "pairs grid", "grid build", "pairs");
+    for count in [100, 1_000, 10_000] {
+        bench_2d(count);
+    }
+}
@@ -125,9 +125,14 @@ fn main() {
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
 pub async fn run() {
     let camera = Camera::new(Vec3::new(0.0, 5.0, 10.0), f32::to_radians(-90.0), f32::to_radians(-20.0));
-    // State::new uses async code, so we're going to wait for it to finish
-    let (mut state, _event_loop) = State::new(true, env!("OUT_DIR"), camera, 5.0, 2.0).await;
-    let asset_server = &state.world.get_resource_mut::<App>().unwrap().asset_server;
+    // building the State uses async code, so we're going to wait for it to finish
+    let (state, _event_loop) = AppBuilder::new(env!("OUT_DIR"))
+        .with_camera(camera)
+        .with_mouse_lock(true)
+        .build()
+        .await
+        .unwrap();
+    let asset_server = &state.world.get_resource::<App>().unwrap().asset_server;
     let steps = compute_shader(&asset_server.device, &asset_server.queue, &[1, 2, 3, 4]).await.unwrap();
     for num in steps{
         println!("{},", num);
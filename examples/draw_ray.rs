@@ -1,8 +1,9 @@
-use bevy_ecs::system::{ResMut, Resource, Res, Query};
+use bevy_ecs::system::{Res, ResMut};
 use glam::Vec3;
 use vertix::{
-    camera::{default_3d_cam, Camera},
-    prelude::*, app_resource::App, collision::structs_3d::{Collider3D, ColliderResult, Ray, OBB}, shapes::cube,
+    camera::Camera,
+    prelude::*, app_resource::App, collision::structs_3d::{Collider3D, OBB}, shapes::cube,
+    plugins::DebugDraw,
 };
 
 fn main() {
@@ -15,50 +16,49 @@ pub async fn run() {
         f32::to_radians(-90.0),
         f32::to_radians(-20.0),
     );
-    // State::new uses async code, so we're going to wait for it to finish
-    let (mut state, event_loop) = State::new(false, env!("OUT_DIR"), camera, 5.0, 2.0).await;
+    // building the State uses async code, so we're going to wait for it to finish
+    let (mut state, event_loop) = AppBuilder::new(env!("OUT_DIR"))
+        .with_camera(camera)
+        .add_plugin(FlyCameraPlugin::default())
+        .add_plugin(DebugDrawPlugin)
+        .add_plugin(CollisionPlugin)
+        .build()
+        .await
+        .unwrap();
     //add models
     let mut instance = Instance {
         ..Default::default()
     };
     let collider = Collider3D::OBB(OBB::new(2., 2., 2.));
     let asset_server = &mut state.world.get_resource_mut::<App>().unwrap().asset_server;
-    let mat_idx = asset_server
+    let material = asset_server
         .compile_material(
             "cube-diffuse.jpg",
             wgpu::FilterMode::Linear
         )
-        .await;
+        .await
+        .unwrap();
     asset_server
         .build_mesh(
             cube(2.,2.,2.),
             vec![&mut instance],
-            mat_idx,
+            &material,
             true,
         );
-    state.world.insert_resource(Mat {idx: mat_idx});
     state.world.spawn((instance,collider));
     state.schedule.add_systems(movement);
     //render loop
-    run_event_loop(state, event_loop, Some(default_3d_cam));
-}
-#[derive(Resource)]
-struct Mat {
-    pub idx: usize
+    run_event_loop(state, event_loop);
 }
 fn movement(
-    mut app: ResMut<App>,
-    mat: Res<Mat>,
-    query: Query<(&Instance, &Collider3D)>
+    app: Res<App>,
+    mut debug_draw: ResMut<DebugDraw>,
+    spatial_query: SpatialQuery,
 ) {
-    for (instance, collider) in &query {
-        if app.window_events.left_clicked() {
-            let ray = Ray {origin: app.camera.camera_transform.position, direction: app.window_events.mouse_ray_direction};
-            
-            let collision = collider.check_collision(Some(instance), &Collider3D::Ray(ray), None);
-            if let ColliderResult::Collision(_dist) = collision {
-                app.draw_ray(ray, 100., mat.idx);
-            }
+    if app.window_events.left_clicked() {
+        let ray = app.mouse_ray();
+        if let Some(hit) = spatial_query.cast_ray(ray, 100., &QueryFilter::default()) {
+            debug_draw.ray(ray, hit.distance);
         }
     }
-}
\ No newline at end of file
+}
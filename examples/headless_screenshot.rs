@@ -0,0 +1,24 @@
+use glam::Vec3;
+use instant::Duration;
+use vertix::{prelude::*, camera::Camera, app_resource::App, shapes::cube};
+fn main() {
+    pollster::block_on(run());
+}
+pub async fn run() {
+    let camera = Camera::new(Vec3::new(0.0, 2.0, 6.0), f32::to_radians(-90.0), f32::to_radians(-20.0));
+    // no window gets opened, so this also works in CI with a software adapter
+    let mut state = AppBuilder::new(env!("OUT_DIR"))
+        .with_camera(camera)
+        .build_headless(512, 512, true)
+        .await
+        .unwrap();
+    let mut instance = Instance {..Default::default()};
+    let asset_server = &mut state.world.get_resource_mut::<App>().unwrap().asset_server;
+    let material = asset_server.compile_material("cube-diffuse.jpg", wgpu::FilterMode::Linear).await.unwrap();
+    asset_server.build_mesh(cube(2., 2., 2.), vec![&mut instance], &material, true);
+    state.world.spawn((instance,));
+    state.run_frame(Duration::from_millis(16));
+    let frame = state.render_to_image();
+    frame.save("headless_screenshot.png").unwrap();
+    println!("saved {}x{} frame to headless_screenshot.png", frame.width(), frame.height());
+}
@@ -1,6 +1,6 @@
 use glam::Vec3;
 use noise::{NoiseFn, Perlin};
-use vertix::{prelude::*, camera::{Camera, default_3d_cam}, app_resource::App};
+use vertix::{prelude::*, camera::Camera, app_resource::App, model::Material};
 #[derive(Copy, Clone, Default, Debug)]
 pub struct Block {
     block_type: BlockType,
@@ -34,17 +34,32 @@ fn main() {
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
 pub async fn run() {
     let camera = Camera::new(Vec3::new(0.0, 50.0, 10.0), f32::to_radians(90.0), f32::to_radians(-20.0));
-    // State::new uses async code, so we're going to wait for it to finish
-    let (mut state, event_loop) = State::new(true, env!("OUT_DIR"), camera, 5.0, 2.0).await;
+    // building the State uses async code, so we're going to wait for it to finish
+    let (mut state, event_loop) = AppBuilder::new(env!("OUT_DIR"))
+        .with_camera(camera)
+        .with_perspective(PerspectiveSettings {
+            zfar: f32::INFINITY,
+            reverse_z: true,
+            ..Default::default()
+        })
+        .with_mouse_lock(true)
+        .with_window_config(WindowConfig {
+            title: "WGPUCraft".to_string(),
+            ..Default::default()
+        })
+        .add_plugin(FlyCameraPlugin::default())
+        .build()
+        .await
+        .unwrap();
     let asset_server = &mut state.world.get_resource_mut::<App>().unwrap().asset_server;
-    let atlas_idx = asset_server.compile_material("texture_atlas.png", wgpu::FilterMode::Nearest).await;
+    let atlas = asset_server.compile_material("texture_atlas.png", wgpu::FilterMode::Nearest).await.unwrap();
     //add models
-    create_terrain(&mut state, atlas_idx);
+    create_terrain(&mut state, &atlas);
     //render loop
-    run_event_loop(state, event_loop, Some(default_3d_cam));
+    run_event_loop(state, event_loop);
 }
 
-fn create_terrain(state: &mut State, atlas_idx: usize) {
+fn create_terrain(state: &mut State, atlas: &Handle<Material>) {
     let mut chunk_blocks_vec = vec![];
     //gen chunks
     for i in 0..256 {
@@ -60,7 +75,7 @@ fn create_terrain(state: &mut State, atlas_idx: usize) {
         build_chunk(
             state,
             blocks,
-            atlas_idx,
+            atlas,
             row as f32,
             col as f32,
             match i.checked_sub(16) {
@@ -142,11 +157,11 @@ fn flip_2d_vector(input: Vec<Vec<Block>>) -> Vec<Vec<Block>> {
     flipped
 }
 
-
+#[allow(clippy::too_many_arguments)]
 pub fn build_chunk(
     state: &mut State,
-    blocks: &Vec<Vec<Vec<Block>>>,
-    material_idx: usize,
+    blocks: &[Vec<Vec<Block>>],
+    material: &Handle<Material>,
     x_offset: f32,
     z_offset: f32,
     left_chunk: Option<&Vec<Vec<Vec<Block>>>>,
@@ -221,7 +236,7 @@ pub fn build_chunk(
                     None
                 };
                 neighbor_chunk_block_option =
-                    left_chunk.map_or(None, |chunk| Some(&chunk[15][y][z]));
+                    left_chunk.map(|chunk| &chunk[15][y][z]);
                 get_block_face(
                     base_index,
                     face,
@@ -242,7 +257,7 @@ pub fn build_chunk(
                     None
                 };
                 neighbor_chunk_block_option =
-                    right_chunk.map_or(None, |chunk| Some(&chunk[0][y][z]));
+                    right_chunk.map(|chunk| &chunk[0][y][z]);
                 get_block_face(
                     base_index,
                     face,
@@ -263,7 +278,7 @@ pub fn build_chunk(
                     None
                 };
                 neighbor_chunk_block_option =
-                    front_chunk.map_or(None, |chunk| Some(&chunk[x][y][0]));
+                    front_chunk.map(|chunk| &chunk[x][y][0]);
                 get_block_face(
                     base_index,
                     face,
@@ -284,7 +299,7 @@ pub fn build_chunk(
                     None
                 };
                 neighbor_chunk_block_option =
-                    back_chunk.map_or(None, |chunk| Some(&chunk[x][y][15]));
+                    back_chunk.map(|chunk| &chunk[x][y][15]);
                 get_block_face(
                     base_index,
                     face,
@@ -305,11 +320,12 @@ pub fn build_chunk(
             (vertices,
             indices),
             vec![&mut Instance{..Default::default()}], //since we are just discarding the instance afterward and not doing anything to it we don't need to add to world
-            material_idx,false
+            material,false
         );
     
 }
 
+#[allow(clippy::too_many_arguments)]
 fn get_block_face(
     base_index: u32,
     face: Face,
@@ -336,20 +352,17 @@ fn get_block_face(
             //otherwise the neighboring block is a solid block so you don't need to render
         }
         None => {
-            match neighbor_chunk_block_option {
-                Some(neighbor_chunk_block) => {
-                    if let BlockType::Air = neighbor_chunk_block.block_type {
-                        vertices.extend_from_slice(&get_mesh_texture_and_pos(
-                            face,
-                            &block.block_type,
-                            pos,
-                            grass_above,
-                        ));
-                        render = true;
-                    }
-                    //otherwise the neighboring chunk's block is a solid block so you don't need to render
+            if let Some(neighbor_chunk_block) = neighbor_chunk_block_option {
+                if let BlockType::Air = neighbor_chunk_block.block_type {
+                    vertices.extend_from_slice(&get_mesh_texture_and_pos(
+                        face,
+                        &block.block_type,
+                        pos,
+                        grass_above,
+                    ));
+                    render = true;
                 }
-                None => {}
+                //otherwise the neighboring chunk's block is a solid block so you don't need to render
             }
         }
     }
@@ -421,10 +434,18 @@ fn get_mesh_texture_and_pos(
         _ => todo!(),
     };
 
+    let normal = match face {
+        Face::Top => [0.0, 1.0, 0.0],
+        Face::Bottom => [0.0, -1.0, 0.0],
+        Face::Left => [-1.0, 0.0, 0.0],
+        Face::Right => [1.0, 0.0, 0.0],
+        Face::Front => [0.0, 0.0, 1.0],
+        Face::Back => [0.0, 0.0, -1.0],
+    };
     let texture_coords = get_texture_coords(index);
     let mut vertices_array = vec![];
     for i in 0..4 {
-        vertices_array.push(Vertex::new(vertices[i], texture_coords[i]))
+        vertices_array.push(Vertex::with_normal(vertices[i], texture_coords[i], normal))
     }
 
     vertices_array
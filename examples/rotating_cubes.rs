@@ -1,7 +1,7 @@
-use bevy_ecs::system::{Query, ResMut};
+use bevy_ecs::system::{Query, Res};
 use glam::{Quat, Vec3};
 use vertix::{
-    camera::{default_3d_cam, Camera},
+    camera::Camera,
     prelude::*, app_resource::App,
 };
 
@@ -15,8 +15,13 @@ pub async fn run() {
         f32::to_radians(-90.0),
         f32::to_radians(-20.0),
     );
-    // State::new uses async code, so we're going to wait for it to finish
-    let (mut state, event_loop) = State::new(false, env!("OUT_DIR"), camera, 5.0, 2.0).await;
+    // building the State uses async code, so we're going to wait for it to finish
+    let (mut state, event_loop) = AppBuilder::new(env!("OUT_DIR"))
+        .with_camera(camera)
+        .add_plugin(FlyCameraPlugin::default())
+        .build()
+        .await
+        .unwrap();
     //add models
     const SPACE_BETWEEN: f32 = 3.0;
     const NUM_INSTANCES_PER_ROW: usize = 100;
@@ -46,47 +51,30 @@ pub async fn run() {
             instances.iter_mut().map(|(instance,)| instance).collect(),
             true,
         )
-        .await;
+        .await
+        .unwrap();
     state.world.spawn_batch(instances);
-    state.schedule.add_systems((movement, movement_with_key));
+    state
+        .add_systems(Stage::FixedUpdate, movement)
+        .add_systems(Stage::Update, movement_with_key);
     //render loop
-    run_event_loop(state, event_loop, Some(default_3d_cam));
+    run_event_loop(state, event_loop);
 }
 fn movement(
     mut query: Query<(&mut Instance,)>,
-    mut app: ResMut<App>,
+    app: Res<App>,
 ) {
-    let mut instances = vec![];
-    let mut temp_instance = Instance {
-        ..Default::default()
-    };
     for (mut instance,) in &mut query {
         instance.position[0] += 10. * delta_time_to_seconds(app.dt);
-        let instance_raw = instance.to_raw();
-        if instance_raw.is_some() {
-            instances.push(instance_raw.unwrap());
-        }
-        temp_instance = *instance;
     }
-    temp_instance.update(instances, &mut app.asset_server);
 }
 fn movement_with_key(
     mut query: Query<(&mut Instance,)>,
-    mut app: ResMut<App>
+    app: Res<App>
 ) {
     if app.window_events.is_key_pressed(VirtualKeyCode::D, None) {
-        let mut instances = vec![];
-        let mut temp_instance = Instance {
-            ..Default::default()
-        };
         for (mut instance,) in &mut query {
             instance.position[1] += 50. * delta_time_to_seconds(app.dt);
-            let instance_raw = instance.to_raw();
-            if instance_raw.is_some() {
-                instances.push(instance_raw.unwrap());
-            }
-            temp_instance = *instance;
         }
-        temp_instance.update(instances, &mut app.asset_server);
     }
 }
@@ -1,9 +1,9 @@
 use glam::{Vec2, Vec3};
 use vertix::{
-    camera::{default_3d_cam, Camera},
+    camera::Camera,
     prelude::*,
     collision::structs_2d::Box2D,
-    shapes::rect, app_resource::App,
+    shapes::rect, app_resource::App, plugins::Interaction,
 };
 use bevy_ecs::prelude::*;
 fn main() {
@@ -16,35 +16,36 @@ pub async fn run() {
         f32::to_radians(-90.0),
         f32::to_radians(0.0),
     );
-    // State::new uses async code, so we're going to wait for it to finish
-    let (mut state, event_loop) = vertix::prelude::State::new(false, env!("OUT_DIR"), camera, 5.0, 2.0).await;
+    // building the State uses async code, so we're going to wait for it to finish
+    let (mut state, event_loop) = AppBuilder::new(env!("OUT_DIR"))
+        .with_camera(camera)
+        .add_plugin(UiPlugin)
+        .build()
+        .await
+        .unwrap();
     //custom mesh
     let p1 = Vec2::new(-0.5, -0.5);
     let p2 = Vec2::new(0.5, 0.5);
     let collider = Box2D::new(p1,p2);
     let mut instance = Instance {is_world_space: false, ..Default::default()};
-    let mut instances = vec![];
-    instances.push(&mut instance);
+    let instances = vec![&mut instance];
     let asset_server = &mut state.world.get_resource_mut::<App>().unwrap().asset_server;
-    let material_idx = asset_server.compile_material("rounded_rect.png", wgpu::FilterMode::Linear).await;
+    let material = asset_server.compile_material("rounded_rect.png", wgpu::FilterMode::Linear).await.unwrap();
     asset_server.build_mesh(
         rect(1.,1.),
         instances,
-        material_idx,
+        &material,
         false,
     );
-    state.world.spawn((instance, collider));
+    state.world.spawn((instance, collider, Interaction::default()));
     state.schedule.add_systems(movement);
     //render loop
-    run_event_loop(state, event_loop, Some(default_3d_cam));
+    run_event_loop(state, event_loop);
 }
-fn movement(query: Query<(&Instance, &Box2D)>, app: Res<App>) {
-    for (instance, collider) in &query {
-        if collider.check_collision(instance, &app.window_events) {
-            //println!("collision");
-            if app.window_events.left_clicked() {
-                println!("click")
-            }
+fn movement(query: Query<&Interaction, Changed<Interaction>>) {
+    for interaction in &query {
+        if let Interaction::Clicked = interaction {
+            println!("click")
         }
     }
 }
\ No newline at end of file
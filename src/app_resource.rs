@@ -2,26 +2,45 @@ use bevy_ecs::system::Resource;
 use glam::Vec3;
 use winit::dpi::PhysicalPosition;
 
-use crate::{assets::AssetServer, camera::CameraStruct, resources::WindowEvents, shapes::line_3d, collision::structs_3d::Ray, prelude::Instance};
+use crate::{assets::AssetServer, camera::CameraStruct, resources::WindowEvents, shapes::line_3d, collision::structs_3d::Ray, prelude::Instance, handle::Handle, model::Material};
 
 #[derive(Resource)]
 pub struct App {
     pub asset_server: AssetServer,
     pub camera: CameraStruct,
     pub window_events: WindowEvents,
-    pub dt: instant::Duration,
+    pub dt: instant::Duration, //frame time, or fixed_timestep inside FixedUpdate
+    pub fixed_timestep: instant::Duration,
+    pub fixed_alpha: f32, //0-1, progress towards the next fixed step
 }
 impl App {
     pub fn cursor_move(&mut self, normalized_position: PhysicalPosition<f32>) {
-        self.window_events.update_mouse_pos(normalized_position, &mut self.camera.camera_transform);
-        self.window_events.calculate_mouse_dir(&self.camera.projection, &self.camera.camera_uniform.view_proj);
+        self.window_events.update_mouse_pos(normalized_position, &self.camera);
+        self.window_events.calculate_mouse_dir(&self.camera);
     }
-    pub fn draw_ray(&mut self, normalized_ray: Ray, length: f32, material_idx: usize) {
+    //projection changes are picked up by the camera uniform on the next frame
+    pub fn set_fov(&mut self, fovy: f32) {
+        self.camera.projection.set_fovy(fovy);
+    }
+    pub fn set_clip_planes(&mut self, znear: f32, zfar: f32) {
+        self.camera.projection.set_clip_planes(znear, zfar);
+    }
+    pub fn set_reverse_z(&mut self, reverse_z: bool) {
+        self.camera.projection.set_reverse_z(reverse_z);
+    }
+    //from the camera through the cursor, for picking with SpatialQuery
+    pub fn mouse_ray(&self) -> Ray {
+        Ray {
+            origin: self.window_events.mouse_ray_origin,
+            direction: self.window_events.mouse_ray_direction,
+        }
+    }
+    pub fn draw_ray(&mut self, normalized_ray: Ray, length: f32, material: &Handle<Material>) {
         let line_segment_start = normalized_ray.origin;
         let line_segment_end = normalized_ray.origin + normalized_ray.direction * length;
-        self.draw_line_segment(line_segment_start, line_segment_end, material_idx);
+        self.draw_line_segment(line_segment_start, line_segment_end, material);
     }
-    pub fn draw_line_segment(&mut self, line_start: Vec3, line_end: Vec3, material_idx: usize) {
-        self.asset_server.build_mesh(line_3d(line_start, line_end), vec![&mut Instance {..Default::default()}], material_idx, false)
+    pub fn draw_line_segment(&mut self, line_start: Vec3, line_end: Vec3, material: &Handle<Material>) {
+        self.asset_server.build_mesh(line_3d(line_start, line_end), vec![&mut Instance {..Default::default()}], material, false)
     }
 }
\ No newline at end of file
@@ -1,19 +1,26 @@
 use slab::Slab;
 use wgpu::{Device, Queue, util::DeviceExt, BindGroupLayout};
 
-use crate::{prelude::{Vertex, Instance}, shapes::rect, prefabs::Prefab, structs::{MeshType, Mesh}, loader::{load_texture, load_model}, model::Material};
+use crate::{prelude::{Vertex, Instance}, shapes::rect, prefabs::Prefab, structs::{MeshType, Mesh, compute_tangents}, loader, model::{AlphaMode, Material, Model}, texture::Texture, error::Result, handle::{Assets, Handle}};
 
 pub struct AssetServer {
-    pub material_assets: Vec<Material>,
+    pub textures: Assets<Texture>,
+    pub materials: Assets<Material>,
+    pub models: Assets<Model>,
     pub device: Device,
     pub queue: Queue,
     pub prefab_slab: Slab<Prefab>,
     pub build_path: String,
     pub texture_bind_group_layout: BindGroupLayout,
     pub sprite_mesh: Mesh,
+    pub flat_normal_texture: Texture, //bound in place of a normal map for materials that don't have one
 }
 impl AssetServer {
     pub fn new(device: Device, queue: Queue, build_path: String, texture_bind_group_layout: BindGroupLayout) -> Self {
+        let mut textures = Assets::default();
+        let mut materials = Assets::default();
+        let flat_normal_texture = loader::flat_normal_texture(&device, &queue);
+        let white = color_material(&device, &queue, &texture_bind_group_layout, &flat_normal_texture, &mut textures, &mut materials, [255, 255, 255, 255]);
         //make sprite mesh
         let (vertices, indices) = rect(1.,1.);
         let vertex_buffer = device
@@ -31,16 +38,19 @@ impl AssetServer {
             
         let mesh = Mesh {
             vertex_buffer,index_buffer, num_elements: indices.len() as u32,
-            material_idx: 0,
+            material: white,
         };
         Self {
-            material_assets: vec![],
+            textures,
+            materials,
+            models: Assets::default(),
             device,
             queue,
             prefab_slab: Slab::new(),
             build_path,
             texture_bind_group_layout,
-            sprite_mesh: mesh
+            sprite_mesh: mesh,
+            flat_normal_texture,
         }
     }
     pub fn remove_prefab(&mut self, prefab_idx: usize) {
@@ -49,48 +59,99 @@ impl AssetServer {
     pub fn clear_all_prefabs(&mut self) {
         self.prefab_slab.clear();
     }
-    pub async fn compile_materials(&mut self, material_paths: Vec<&str>, filter_type: wgpu::FilterMode) -> Vec<usize> {
-        let mut material_idxs = vec![];
+    //true if the prefab has to be drawn in the transparent pass, because of its materials or its instances' colors
+    pub fn is_transparent(&self, prefab: &Prefab) -> bool {
+        if prefab.has_transparent_instances() {
+            return true;
+        }
+        let is_blended = |material: &Material| material.alpha_mode().is_blended();
+        match &prefab.mesh_type {
+            MeshType::Model(model) => self
+                .models
+                .get(model)
+                .is_some_and(|model| model.materials.iter().any(is_blended)),
+            MeshType::Mesh(mesh) => self.materials.get(&mesh.material).is_some_and(is_blended),
+            MeshType::Sprite(material) => self.materials.get(material).is_some_and(is_blended),
+        }
+    }
+    //drops every texture, material and model that no handle points at anymore, State calls this once per frame
+    pub fn free_unused_assets(&mut self) {
+        //models and materials hold handles to what they use, so they go first
+        self.models.free_unused();
+        self.materials.free_unused();
+        self.textures.free_unused();
+    }
+    //loading the same path with the same filter twice gives back the first texture
+    pub async fn load_texture(&mut self, texture_path: &str, filter_type: wgpu::FilterMode) -> Result<Handle<Texture>> {
+        let key = format!("{}:{:?}", texture_path, filter_type);
+        if let Some(handle) = self.textures.get_keyed(&key) {
+            return Ok(handle);
+        }
+        let texture = loader::load_texture(texture_path, &self.build_path, &self.device, &self.queue, filter_type).await?;
+        Ok(self.textures.add_keyed(&key, texture))
+    }
+    pub async fn compile_materials(&mut self, material_paths: Vec<&str>, filter_type: wgpu::FilterMode) -> Result<Vec<Handle<Material>>> {
+        let mut materials = vec![];
         for material_path in material_paths {
-            self.material_assets.push(self.compile_material_internal(&material_path, filter_type).await);
-            material_idxs.push(self.material_assets.len() - 1);
-        }
-        material_idxs
-    }
-    pub async fn compile_material(&mut self, material_path: &str, filter_type: wgpu::FilterMode) -> usize {
-        self.material_assets.push(self.compile_material_internal(&material_path, filter_type).await);
-        self.material_assets.len() - 1
-    }
-    async fn compile_material_internal(&self, texture_name: &str, filter_type: wgpu::FilterMode) -> Material {
-        let diffuse_texture =
-            load_texture(texture_name, &self.build_path, &self.device, &self.queue, filter_type)
-                .await
-                .unwrap();
-        let texture_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &self.texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
-                },
-            ],
-            label: None,
-        });
-        Material {
-            bind_group: texture_bind_group,
+            materials.push(self.compile_material(material_path, filter_type).await?);
         }
+        Ok(materials)
+    }
+    pub async fn compile_material(&mut self, material_path: &str, filter_type: wgpu::FilterMode) -> Result<Handle<Material>> {
+        let key = format!("{}:{:?}", material_path, filter_type);
+        if let Some(handle) = self.materials.get_keyed(&key) {
+            return Ok(handle);
+        }
+        let texture = self.load_texture(material_path, filter_type).await?;
+        let material = material_from_texture(&self.device, &self.texture_bind_group_layout, &self.textures, texture, None, &self.flat_normal_texture);
+        Ok(self.materials.add_keyed(&key, material))
+    }
+    pub async fn compile_material_with_normal_map(&mut self, material_path: &str, normal_map_path: &str, filter_type: wgpu::FilterMode) -> Result<Handle<Material>> {
+        let key = format!("{}+{}:{:?}", material_path, normal_map_path, filter_type);
+        if let Some(handle) = self.materials.get_keyed(&key) {
+            return Ok(handle);
+        }
+        let texture = self.load_texture(material_path, filter_type).await?;
+        let normal_texture = loader::load_normal_texture(normal_map_path, &self.build_path, &self.device, &self.queue, filter_type).await?;
+        let material = material_from_texture(&self.device, &self.texture_bind_group_layout, &self.textures, texture, Some(normal_texture), &self.flat_normal_texture);
+        Ok(self.materials.add_keyed(&key, material))
+    }
+    //a 1x1 texture of a single color, for meshes that only need the instance color
+    pub fn compile_color_material(&mut self, color: [u8; 4]) -> Handle<Material> {
+        color_material(&self.device, &self.queue, &self.texture_bind_group_layout, &self.flat_normal_texture, &mut self.textures, &mut self.materials, color)
+    }
+    //overrides the mode picked from the texture, like Premultiplied for textures exported that way or Mask for foliage
+    //materials are shared between everything that loaded the same path, so this changes it for all of them
+    pub fn set_alpha_mode(&mut self, material: &Handle<Material>, alpha_mode: AlphaMode) {
+        if let Some(material) = self.materials.get_mut(material) {
+            material.set_alpha_mode(&self.queue, alpha_mode);
+        }
+    }
+    pub async fn load_model(&mut self, model_path: &str) -> Result<Handle<Model>> {
+        if let Some(handle) = self.models.get_keyed(model_path) {
+            return Ok(handle);
+        }
+        let model = loader::load_model(
+            model_path,
+            &self.build_path,
+            &self.device,
+            &self.queue,
+            &self.texture_bind_group_layout,
+        )
+        .await?;
+        Ok(self.models.add_keyed(model_path, model))
     }
     pub fn build_mesh(
         &mut self,
-        (vertices,indices): (Vec<Vertex>,Vec<u32>),
+        (mut vertices,indices): (Vec<Vertex>,Vec<u32>),
         instances: Vec<&mut Instance>,
-        material_idx: usize,
+        material: &Handle<Material>,
         is_updating: bool
     ) {
+        //meshes that bring their own tangents keep them
+        if vertices.iter().all(|vertex| vertex.tangent == [0.0; 3]) {
+            compute_tangents(&mut vertices, &indices);
+        }
         let vertex_buffer = self
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -109,15 +170,12 @@ impl AssetServer {
             vertex_buffer,
             index_buffer,
             num_elements: indices.len() as u32,
-            material_idx,
+            material: material.clone(),
         };
         let mut instance_data = vec![];
-        let mut length = 0;
         for instance in &instances {
-            let instance_raw = instance.to_raw();
-            if instance_raw.is_some() {
-                instance_data.push(instance_raw.unwrap());
-                length += 1;
+            if let Some(instance_raw) = instance.to_raw() {
+                instance_data.push(instance_raw);
             }
         }
         let instance_buffer = self
@@ -134,7 +192,7 @@ impl AssetServer {
         let container = Prefab::new(
             instance_buffer,
             MeshType::Mesh(mesh),
-            length,
+            instance_data,
         );
         let entry = self.prefab_slab.vacant_entry();
         let key = entry.key();
@@ -148,23 +206,12 @@ impl AssetServer {
         model: &str,
         instances: Vec<&mut Instance>,
         is_updating: bool,
-    ) {
-        let loaded_model = load_model(
-            model,
-            &self.build_path,
-            &self.device,
-            &self.queue,
-            &self.texture_bind_group_layout,
-        )
-        .await
-        .unwrap();
+    ) -> Result<()> {
+        let loaded_model = self.load_model(model).await?;
         let mut instance_data = vec![];
-        let mut length = 0;
         for instance in &instances {
-            let instance_raw = instance.to_raw();
-            if instance_raw.is_some() {
-                instance_data.push(instance_raw.unwrap());
-                length += 1;
+            if let Some(instance_raw) = instance.to_raw() {
+                instance_data.push(instance_raw);
             }
         }
         let instance_buffer = self
@@ -181,7 +228,7 @@ impl AssetServer {
         let container = Prefab::new(
             instance_buffer,
             MeshType::Model(loaded_model),
-            length,
+            instance_data,
         );
         let entry = self.prefab_slab.vacant_entry();
         let key = entry.key();
@@ -189,20 +236,18 @@ impl AssetServer {
             instance.prefab_index = key;
         }
         entry.insert(container);
+        Ok(())
     }
     pub fn make_sprites(
         &mut self,
         instances: Vec<&mut Instance>,
-        material_idx: usize,
+        material: &Handle<Material>,
         is_updating: bool
     ) {
         let mut instance_data = vec![];
-        let mut length = 0;
         for instance in &instances {
-            let instance_raw = instance.to_raw();
-            if instance_raw.is_some() {
-                instance_data.push(instance_raw.unwrap());
-                length += 1;
+            if let Some(instance_raw) = instance.to_raw() {
+                instance_data.push(instance_raw);
             }
         }
         let instance_buffer = self
@@ -218,8 +263,8 @@ impl AssetServer {
             });
         let container = Prefab::new(
             instance_buffer,
-            MeshType::Sprite(material_idx),
-            length,
+            MeshType::Sprite(material.clone()),
+            instance_data,
         );
         let entry = self.prefab_slab.vacant_entry();
         let key = entry.key();
@@ -228,4 +273,37 @@ impl AssetServer {
         }
         entry.insert(container);
     }
-}
\ No newline at end of file
+}
+fn color_material(
+    device: &Device,
+    queue: &Queue,
+    texture_bind_group_layout: &BindGroupLayout,
+    flat_normal_texture: &Texture,
+    textures: &mut Assets<Texture>,
+    materials: &mut Assets<Material>,
+    color: [u8; 4],
+) -> Handle<Material> {
+    let key = format!("color:{:?}", color);
+    if let Some(handle) = materials.get_keyed(&key) {
+        return handle;
+    }
+    let image = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(1, 1, image::Rgba(color)));
+    let texture = textures.add(Texture::from_image(device, queue, &image, Some("color material"), wgpu::FilterMode::Nearest));
+    let material = material_from_texture(device, texture_bind_group_layout, textures, texture, None, flat_normal_texture);
+    materials.add_keyed(&key, material)
+}
+fn material_from_texture(
+    device: &Device,
+    texture_bind_group_layout: &BindGroupLayout,
+    textures: &Assets<Texture>,
+    texture: Handle<Texture>,
+    normal_texture: Option<Texture>,
+    flat_normal_texture: &Texture,
+) -> Material {
+    let diffuse_texture = textures.get(&texture).expect("texture handles always point at a loaded texture");
+    let normal = normal_texture.as_ref().unwrap_or(flat_normal_texture);
+    let alpha_mode = AlphaMode::from_texture(diffuse_texture);
+    let mut material = Material::new(device, texture_bind_group_layout, diffuse_texture, normal, Some(texture), alpha_mode);
+    material.normal_texture = normal_texture;
+    material
+}
@@ -0,0 +1,83 @@
+use winit::event_loop::EventLoop;
+
+use crate::{app_resource::App, camera::{Camera, Camera2D, OrthographicScaling, PerspectiveSettings}, error::Result, state::State, window::WindowConfig};
+
+pub trait Plugin {
+    //called once after the State is created, register resources into state.world and systems with state.add_systems
+    fn build(&self, state: &mut State);
+}
+
+pub struct AppBuilder {
+    build_path: String,
+    camera: Camera,
+    camera_2d: Option<(Camera2D, OrthographicScaling)>,
+    perspective: PerspectiveSettings,
+    mouse_lock: bool,
+    window_config: WindowConfig,
+    plugins: Vec<Box<dyn Plugin>>,
+}
+impl AppBuilder {
+    //build_path is the folder that holds res/, usually env!("OUT_DIR")
+    pub fn new(build_path: &str) -> Self {
+        Self {
+            build_path: build_path.to_string(),
+            camera: Camera::default(),
+            camera_2d: None,
+            perspective: PerspectiveSettings::default(),
+            mouse_lock: false,
+            window_config: WindowConfig::default(),
+            plugins: vec![],
+        }
+    }
+    pub fn with_camera(mut self, camera: Camera) -> Self {
+        self.camera = camera;
+        self
+    }
+    //field of view and clip planes of the 3d camera
+    pub fn with_perspective(mut self, perspective: PerspectiveSettings) -> Self {
+        self.perspective = perspective;
+        self
+    }
+    //starts with an orthographic 2d camera instead of the 3d one
+    pub fn with_camera_2d(mut self, camera_2d: Camera2D, scaling: OrthographicScaling) -> Self {
+        self.camera_2d = Some((camera_2d, scaling));
+        self
+    }
+    pub fn with_mouse_lock(mut self, mouse_lock: bool) -> Self {
+        self.mouse_lock = mouse_lock;
+        self
+    }
+    pub fn with_window_config(mut self, window_config: WindowConfig) -> Self {
+        self.window_config = window_config;
+        self
+    }
+    pub fn add_plugin(mut self, plugin: impl Plugin + 'static) -> Self {
+        self.plugins.push(Box::new(plugin));
+        self
+    }
+    pub async fn build(self) -> Result<(State, EventLoop<()>)> {
+        let (mut state, event_loop) = State::new(self.mouse_lock, &self.build_path, self.camera, self.window_config).await?;
+        finish(&mut state, self.perspective, self.camera_2d, &self.plugins);
+        Ok((state, event_loop))
+    }
+    pub async fn build_headless(self, width: u32, height: u32, force_fallback_adapter: bool) -> Result<State> {
+        let mut state = State::new_headless(width, height, force_fallback_adapter, &self.build_path, self.camera).await?;
+        finish(&mut state, self.perspective, self.camera_2d, &self.plugins);
+        Ok(state)
+    }
+}
+fn finish(
+    state: &mut State,
+    perspective: PerspectiveSettings,
+    camera_2d: Option<(Camera2D, OrthographicScaling)>,
+    plugins: &[Box<dyn Plugin>],
+) {
+    let camera = &mut state.world.resource_mut::<App>().into_inner().camera;
+    camera.set_3d(perspective);
+    if let Some((camera_2d, scaling)) = camera_2d {
+        camera.set_2d(camera_2d, scaling);
+    }
+    for plugin in plugins {
+        plugin.build(state);
+    }
+}
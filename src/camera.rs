@@ -1,43 +1,66 @@
-use glam::{Mat4, Vec3};
-use instant::Duration;
+use glam::{Mat4, Vec2, Vec3};
 use wgpu::util::DeviceExt;
 use wgpu::{Device, SurfaceConfiguration, Buffer, BindGroupLayout, BindGroup};
 
+use bevy_ecs::system::{Res, ResMut};
+
 use crate::app_resource::App;
+use crate::controllers::{CameraControllerKind, CAMERA_FORWARD, CAMERA_RIGHT, CAMERA_UP};
+use crate::input::InputMap;
 use crate::structs::CameraController;
-use crate::state::State;
 use std::f32::consts::FRAC_PI_2;
 const SAFE_FRAC_PI_2: f32 = FRAC_PI_2 - 0.0001;
+//the 2d camera sees everything from this far in front of it to this far behind it
+pub const ORTHOGRAPHIC_DEPTH: f32 = 1000.0;
+//remaps opengl's -1 to 1 depth to wgpu's 0 to 1, z' = 0.5z + 0.5w
 #[rustfmt::skip]
 pub const OPENGL_TO_WGPU_MATRIX: Mat4 = Mat4::from_cols_array(
     &[1.0, 0.0, 0.0, 0.0,
     0.0, 1.0, 0.0, 0.0,
-    0.0, 0.0, 0.5, 0.5,
-    0.0, 0.0, 0.0, 1.0]
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0]
 );
 
 
 
+//screen space runs from -1 to 1 vertically and -aspect to aspect horizontally, so shapes keep their proportions
+//whatever the window size. depth passes straight through
+pub fn screen_projection(width: u32, height: u32) -> Mat4 {
+    let aspect = width as f32 / height as f32;
+    Mat4::from_scale(Vec3::new(1.0 / aspect, 1.0, 1.0))
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct CameraUniform {
     view_position: [f32; 4],
     pub view_proj: [[f32; 4]; 4],
+    pub screen_proj: [[f32; 4]; 4], //what instances that aren't in world space are drawn through
+}
+impl Default for CameraUniform {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl CameraUniform {
     pub fn new() -> Self {
         Self {
-            view_position: [0.0; 4],
+            view_position: [0.0, 0.0, 0.0, 1.0],
             view_proj: Mat4::IDENTITY.to_cols_array_2d(), //maybe here
+            screen_proj: Mat4::IDENTITY.to_cols_array_2d(),
         }
     }
     pub fn update_screen_size(&mut self, width: u32, height: u32) {
-        self.view_position[3] = (width as f32) /(height as f32);
+        self.screen_proj = screen_projection(width, height).to_cols_array_2d();
     }
     pub fn update_view_proj(&mut self, camera: &Camera, projection: &Projection) {
-        self.view_position = camera.position.extend(self.view_position[3]).into();
+        self.view_position = camera.position.extend(1.0).into();
         self.view_proj = (projection.calc_matrix() * camera.calc_matrix()).to_cols_array_2d();
     }
+    pub fn update_view_proj_2d(&mut self, camera: &Camera2D, projection: &Projection) {
+        self.view_position = camera.position.extend(ORTHOGRAPHIC_DEPTH).extend(1.0).into();
+        self.view_proj = (projection.calc_matrix_zoomed(camera.zoom) * camera.calc_matrix(projection)).to_cols_array_2d();
+    }
 }
 pub struct CameraStruct{
     pub projection: Projection,
@@ -46,11 +69,12 @@ pub struct CameraStruct{
     pub bind_group_layout: BindGroupLayout,
     pub bind_group: BindGroup,
     pub camera_transform: Camera,
+    pub camera_2d: Option<Camera2D>, //when set the view comes from it instead of camera_transform
     pub camera_controller: CameraController
 }
 impl CameraStruct{
     pub fn new(device: &Device, config: &SurfaceConfiguration, camera: Camera, camera_controller: CameraController) -> Self{
-        let projection = Projection::new(config.width, config.height, f32::to_radians(45.0), 0.1, 100.0);
+        let projection = Projection::perspective(config.width, config.height, PerspectiveSettings::default());
     
         let mut camera_uniform = CameraUniform::new();
         camera_uniform.update_view_proj(&camera, &projection);
@@ -66,7 +90,7 @@ impl CameraStruct{
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -85,12 +109,45 @@ impl CameraStruct{
             }],
             label: Some("camera_bind_group"),
         });
-        Self {projection,camera_uniform, buffer, bind_group_layout, bind_group, camera_transform: camera, camera_controller }
+        Self {projection,camera_uniform, buffer, bind_group_layout, bind_group, camera_transform: camera, camera_2d: None, camera_controller }
     }
     pub fn update_view_proj(&mut self){
-        self
-            .camera_uniform
-            .update_view_proj(&self.camera_transform, &self.projection);
+        let (width, height) = self.projection.size();
+        self.camera_uniform.update_screen_size(width, height);
+        match &self.camera_2d {
+            Some(camera_2d) => {
+                //keeps everything that reads the 3d camera, like transparency sorting, working in 2d
+                self.camera_transform.position = camera_2d.position.extend(ORTHOGRAPHIC_DEPTH);
+                self.camera_transform.yaw = f32::to_radians(-90.0);
+                self.camera_transform.pitch = 0.0;
+                self.camera_uniform.update_view_proj_2d(camera_2d, &self.projection);
+            }
+            None => self
+                .camera_uniform
+                .update_view_proj(&self.camera_transform, &self.projection),
+        }
+    }
+    //switches to a 2d camera with an orthographic projection
+    pub fn set_2d(&mut self, camera_2d: Camera2D, scaling: OrthographicScaling) {
+        let (width, height) = self.projection.size();
+        let reverse_z = self.projection.reverse_z();
+        self.projection = Projection::orthographic(width, height, scaling);
+        self.projection.set_reverse_z(reverse_z);
+        self.camera_2d = Some(camera_2d);
+    }
+    //back to a 3d camera with a perspective projection
+    pub fn set_3d(&mut self, settings: PerspectiveSettings) {
+        let (width, height) = self.projection.size();
+        self.projection = Projection::perspective(width, height, settings);
+        self.camera_2d = None;
+    }
+    pub fn view_proj(&self) -> Mat4 {
+        Mat4::from_cols_array_2d(&self.camera_uniform.view_proj)
+    }
+    //half the width and height of what the camera sees at a distance in front of it
+    pub fn half_extents(&self, distance: f32) -> Vec2 {
+        let zoom = self.camera_2d.map_or(1.0, |camera_2d| camera_2d.zoom);
+        self.projection.half_extents(distance, zoom)
     }
 }
 
@@ -102,6 +159,13 @@ pub struct Camera {
     pub pitch: f32, //angle in radians
 }
 
+impl Default for Camera {
+    fn default() -> Self {
+        //at the origin looking down -z
+        Self::new(Vec3::ZERO, f32::to_radians(-90.0), 0.0)
+    }
+}
+
 impl Camera {
     pub fn new(
         position: Vec3,
@@ -109,61 +173,281 @@ impl Camera {
         pitch: f32,
     ) -> Self {
         Self {
-            position: position.into(),
+            position,
             yaw,
             pitch,
         }
     }
 
-    pub fn calc_matrix(&self) -> Mat4 {
+    pub fn forward(&self) -> Vec3 {
         let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
         let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        Vec3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw).normalize()
+    }
 
+    pub fn calc_matrix(&self) -> Mat4 {
         Mat4::look_to_rh(
             self.position,
-            Vec3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw).normalize(),
+            self.forward(),
             Vec3::Y,
         )
     }
 }
 
-pub struct Projection {
-    aspect: f32,
-    fovy: f32,
-    znear: f32,
-    zfar: f32,
+//looks down -z at the xy plane, y is up on screen
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera2D {
+    pub position: Vec2,
+    pub zoom: f32, //2 shows everything twice as big
+    pub rotation: f32, //radians counter clockwise
+}
+
+impl Default for Camera2D {
+    fn default() -> Self {
+        Self::new(Vec2::ZERO, 1.0, 0.0)
+    }
+}
+
+impl Camera2D {
+    pub fn new(position: Vec2, zoom: f32, rotation: f32) -> Self {
+        Self {
+            position,
+            zoom,
+            rotation,
+        }
+    }
+
+    pub fn calc_matrix(&self, projection: &Projection) -> Mat4 {
+        let mut position = self.position;
+        //moving in whole pixels stops pixel art from shimmering
+        if let Some(pixels_per_unit) = projection.pixels_per_unit(self.zoom) {
+            position = (position * pixels_per_unit).round() / pixels_per_unit;
+        }
+        Mat4::from_rotation_z(-self.rotation) * Mat4::from_translation(-position.extend(0.0))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OrthographicScaling {
+    PixelPerfect { pixels_per_unit: f32 }, //zoom gets rounded to a whole number so every texel covers the same amount of pixels
+    FixedHeight(f32), //this many world units fit vertically whatever the window size, the width follows the aspect ratio
+}
+
+//everything about a perspective projection that doesn't come from the window
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PerspectiveSettings {
+    pub fovy: f32, //vertical field of view in radians
+    pub znear: f32,
+    pub zfar: f32, //f32::INFINITY for no far plane
+    pub reverse_z: bool, //near is 1 and far is 0 in the depth buffer, which spreads precision out far better
+}
+impl Default for PerspectiveSettings {
+    fn default() -> Self {
+        Self {
+            fovy: f32::to_radians(45.0),
+            znear: 0.1,
+            zfar: 100.0,
+            reverse_z: false,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Projection {
+    Perspective {
+        width: u32,
+        height: u32,
+        fovy: f32,
+        znear: f32,
+        zfar: f32,
+        reverse_z: bool,
+    },
+    Orthographic {
+        width: u32,
+        height: u32,
+        scaling: OrthographicScaling,
+        znear: f32,
+        zfar: f32,
+        reverse_z: bool,
+    },
 }
 
 impl Projection {
     pub fn new<F: Into<f32>>(width: u32, height: u32, fovy: F, znear: f32, zfar: f32) -> Self {
-        Self {
-            aspect: width as f32 / height as f32,
-            fovy: fovy.into(),
-            znear,
-            zfar,
+        Self::perspective(width, height, PerspectiveSettings { fovy: fovy.into(), znear, zfar, reverse_z: false })
+    }
+    pub fn perspective(width: u32, height: u32, settings: PerspectiveSettings) -> Self {
+        Projection::Perspective {
+            width,
+            height,
+            fovy: settings.fovy,
+            znear: settings.znear,
+            zfar: settings.zfar,
+            reverse_z: settings.reverse_z,
+        }
+    }
+    pub fn orthographic(width: u32, height: u32, scaling: OrthographicScaling) -> Self {
+        Projection::Orthographic {
+            width,
+            height,
+            scaling,
+            znear: -ORTHOGRAPHIC_DEPTH,
+            zfar: ORTHOGRAPHIC_DEPTH,
+            reverse_z: false,
         }
     }
 
-    pub fn resize(&mut self, width: u32, height: u32) {
-        self.aspect = width as f32 / height as f32;
+    pub fn resize(&mut self, new_width: u32, new_height: u32) {
+        match self {
+            Projection::Perspective { width, height, .. } | Projection::Orthographic { width, height, .. } => {
+                *width = new_width;
+                *height = new_height;
+            }
+        }
+    }
+    pub fn size(&self) -> (u32, u32) {
+        match *self {
+            Projection::Perspective { width, height, .. } | Projection::Orthographic { width, height, .. } => (width, height),
+        }
+    }
+    pub fn is_orthographic(&self) -> bool {
+        matches!(self, Projection::Orthographic { .. })
+    }
+    //0 for orthographic projections
+    pub fn fovy(&self) -> f32 {
+        match *self {
+            Projection::Perspective { fovy, .. } => fovy,
+            Projection::Orthographic { .. } => 0.0,
+        }
+    }
+    pub fn aspect(&self) -> f32 {
+        let (width, height) = self.size();
+        width as f32 / height as f32
+    }
+    pub fn znear(&self) -> f32 {
+        match *self {
+            Projection::Perspective { znear, .. } | Projection::Orthographic { znear, .. } => znear,
+        }
+    }
+    pub fn zfar(&self) -> f32 {
+        match *self {
+            Projection::Perspective { zfar, .. } | Projection::Orthographic { zfar, .. } => zfar,
+        }
+    }
+    pub fn reverse_z(&self) -> bool {
+        match *self {
+            Projection::Perspective { reverse_z, .. } | Projection::Orthographic { reverse_z, .. } => reverse_z,
+        }
+    }
+    //does nothing for orthographic projections
+    pub fn set_fovy(&mut self, new_fovy: f32) {
+        if let Projection::Perspective { fovy, .. } = self {
+            *fovy = new_fovy;
+        }
+    }
+    pub fn set_clip_planes(&mut self, new_znear: f32, new_zfar: f32) {
+        match self {
+            Projection::Perspective { znear, zfar, .. } | Projection::Orthographic { znear, zfar, .. } => {
+                *znear = new_znear;
+                *zfar = new_zfar;
+            }
+        }
+    }
+    pub fn set_reverse_z(&mut self, new_reverse_z: bool) {
+        match self {
+            Projection::Perspective { reverse_z, .. } | Projection::Orthographic { reverse_z, .. } => {
+                *reverse_z = new_reverse_z;
+            }
+        }
+    }
+    //what the depth buffer gets cleared to, the furthest possible depth
+    pub fn depth_clear_value(&self) -> f32 {
+        if self.reverse_z() {
+            0.0
+        } else {
+            1.0
+        }
+    }
+    //the depth the near and far planes end up at after projecting, reverse z swaps them
+    pub fn ndc_depth_range(&self) -> (f32, f32) {
+        if self.reverse_z() {
+            (1.0, 0.0)
+        } else {
+            (0.0, 1.0)
+        }
+    }
+    //the point on the near plane under a screen position and the direction from there into the screen
+    //screen_pos is -1 to 1 with y up, view_proj has to be made with this projection
+    pub fn unproject(&self, view_proj: Mat4, screen_pos: Vec2) -> (Vec3, Vec3) {
+        let inverse_view_proj = view_proj.inverse();
+        let (near, far) = self.ndc_depth_range();
+        let near_point = inverse_view_proj.project_point3(screen_pos.extend(near));
+        //halfway instead of the far plane, which is at infinity for infinite projections
+        let mid_point = inverse_view_proj.project_point3(screen_pos.extend((near + far) / 2.0));
+        (near_point, (mid_point - near_point).normalize())
+    }
+    //how many pixels a world unit covers, only for pixel perfect projections
+    pub fn pixels_per_unit(&self, zoom: f32) -> Option<f32> {
+        match *self {
+            Projection::Orthographic { scaling: OrthographicScaling::PixelPerfect { pixels_per_unit }, .. } => {
+                Some(pixels_per_unit * zoom.round().max(1.0))
+            }
+            _ => None,
+        }
+    }
+    //half the width and height of the view at a distance in front of the camera
+    pub fn half_extents(&self, distance: f32, zoom: f32) -> Vec2 {
+        let half_height = match *self {
+            Projection::Perspective { fovy, .. } => distance * (fovy / 2.0).tan(),
+            Projection::Orthographic { height, scaling, .. } => match scaling {
+                OrthographicScaling::PixelPerfect { .. } => {
+                    height as f32 / self.pixels_per_unit(zoom).unwrap_or(1.0) / 2.0
+                }
+                OrthographicScaling::FixedHeight(units) => units / zoom / 2.0,
+            },
+        };
+        Vec2::new(half_height * self.aspect(), half_height)
     }
 
     pub fn calc_matrix(&self) -> Mat4 {
-        OPENGL_TO_WGPU_MATRIX * Mat4::perspective_rh_gl(self.fovy, self.aspect, self.znear, self.zfar)
+        self.calc_matrix_zoomed(1.0)
+    }
+    pub fn calc_matrix_zoomed(&self, zoom: f32) -> Mat4 {
+        match *self {
+            Projection::Perspective { fovy, znear, zfar, reverse_z, .. } => {
+                let aspect = self.aspect();
+                match (zfar.is_infinite(), reverse_z) {
+                    (false, false) => OPENGL_TO_WGPU_MATRIX * Mat4::perspective_rh_gl(fovy, aspect, znear, zfar),
+                    (false, true) => Mat4::perspective_rh(fovy, aspect, zfar, znear),
+                    (true, false) => Mat4::perspective_infinite_rh(fovy, aspect, znear),
+                    (true, true) => Mat4::perspective_infinite_reverse_rh(fovy, aspect, znear),
+                }
+            }
+            Projection::Orthographic { znear, zfar, reverse_z, .. } => {
+                let half = self.half_extents(0.0, zoom);
+                if reverse_z {
+                    Mat4::orthographic_rh(-half.x, half.x, -half.y, half.y, zfar, znear)
+                } else {
+                    Mat4::orthographic_rh(-half.x, half.x, -half.y, half.y, znear, zfar)
+                }
+            }
+        }
     }
 }
 
-pub fn default_3d_cam(state: &mut State, dt: Duration) {
-    let dt = dt.as_secs_f32();
-    let cam = &mut state.world
-                .get_resource_mut::<App>()
-                .unwrap().camera;
+//the free fly controller, only moves the camera when the CameraControllerKind resource is FreeFly or missing
+pub fn default_3d_cam(mut app: ResMut<App>, kind: Option<Res<CameraControllerKind>>, input: Res<InputMap>) {
+    if kind.is_some_and(|kind| *kind != CameraControllerKind::FreeFly) {
+        return;
+    }
+    let dt = app.dt.as_secs_f32();
+    let cam = &mut app.camera;
     // Move forward/backward and left/right
     let (yaw_sin, yaw_cos) = cam.camera_transform.yaw.sin_cos();
     let forward = Vec3::new(yaw_cos, 0.0, yaw_sin).normalize();
     let right = Vec3::new(-yaw_sin, 0.0, yaw_cos).normalize();
-    cam.camera_transform.position += forward * (cam.camera_controller.amount_forward - cam.camera_controller.amount_backward) * cam.camera_controller.speed * dt;
-    cam.camera_transform.position += right * (cam.camera_controller.amount_right - cam.camera_controller.amount_left) * cam.camera_controller.speed * dt;
+    cam.camera_transform.position += forward * input.value(CAMERA_FORWARD) * cam.camera_controller.speed * dt;
+    cam.camera_transform.position += right * input.value(CAMERA_RIGHT) * cam.camera_controller.speed * dt;
 
     // Move in/out (aka. "zoom")
     // Note: this isn't an actual zoom. The camera's position
@@ -177,7 +461,7 @@ pub fn default_3d_cam(state: &mut State, dt: Duration) {
 
     // Move up/down. Since we don't use roll, we can just
     // modify the y coordinate directly.
-    cam.camera_transform.position.y += (cam.camera_controller.amount_up - cam.camera_controller.amount_down) * cam.camera_controller.speed * dt;
+    cam.camera_transform.position.y += input.value(CAMERA_UP) * cam.camera_controller.speed * dt;
 
     // Rotate
     cam.camera_transform.yaw += cam.camera_controller.rotate_horizontal * cam.camera_controller.sensitivity * dt;
@@ -190,9 +474,5 @@ pub fn default_3d_cam(state: &mut State, dt: Duration) {
     cam.camera_controller.rotate_vertical = 0.0;
 
     // Keep the camera's angle from going too high/low.
-    if cam.camera_transform.pitch < -SAFE_FRAC_PI_2 {
-        cam.camera_transform.pitch = -SAFE_FRAC_PI_2;
-    } else if cam.camera_transform.pitch > SAFE_FRAC_PI_2 {
-        cam.camera_transform.pitch = SAFE_FRAC_PI_2;
-    }
+    cam.camera_transform.pitch = cam.camera_transform.pitch.clamp(-SAFE_FRAC_PI_2, SAFE_FRAC_PI_2);
 }
@@ -0,0 +1,573 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+use bevy_ecs::prelude::*;
+use glam::{IVec2, Mat4, Vec2, Vec3};
+use slab::Slab;
+
+use crate::{hierarchy::GlobalTransform, instance::Instance};
+
+use super::{
+    spatial_query::collider_transform,
+    structs_2d::{Box2D, Circle},
+    structs_3d::Collider3D,
+};
+
+//how far a tree leaf's box reaches past its collider, so small moves don't touch the tree
+const AABB_MARGIN: f32 = 0.1;
+//most cells a box can be bucketed into, past this it's kept in a list every query checks
+const MAX_GRID_CELLS: f32 = 1024.0;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+    //the box around a transformed box, scale and rotation included
+    pub fn from_transformed(aabb_min: Vec3, aabb_max: Vec3, matrix: &Mat4) -> Self {
+        let center = matrix.transform_point3((aabb_min + aabb_max) * 0.5);
+        let half_extents = (aabb_max - aabb_min) * 0.5;
+        let extents = matrix.x_axis.truncate().abs() * half_extents.x
+            + matrix.y_axis.truncate().abs() * half_extents.y
+            + matrix.z_axis.truncate().abs() * half_extents.z;
+        Self::new(center - extents, center + extents)
+    }
+    pub fn union(&self, other: &Self) -> Self {
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.min.cmple(other.max).all() && other.min.cmple(self.max).all()
+    }
+    pub fn contains(&self, other: &Self) -> bool {
+        self.min.cmple(other.min).all() && other.max.cmple(self.max).all()
+    }
+    pub fn expanded(&self, margin: f32) -> Self {
+        Self::new(self.min - margin, self.max + margin)
+    }
+    //what the tree tries to keep small, bigger boxes get visited by more queries
+    pub fn surface_area(&self) -> f32 {
+        let size = self.max - self.min;
+        2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+    //where the ray enters, 0 if it starts inside, None if it misses within max_distance
+    pub fn ray_entry(&self, origin: Vec3, inverse_direction: Vec3, max_distance: f32) -> Option<f32> {
+        let t1 = (self.min - origin) * inverse_direction;
+        let t2 = (self.max - origin) * inverse_direction;
+        //nan comes from 0 * inf when the ray runs along a plane, max/min_element skip it
+        let t_min = t1.min(t2).max_element().max(0.0);
+        let t_max = t1.max(t2).min_element().min(max_distance);
+        if t_min <= t_max {
+            Some(t_min)
+        } else {
+            None
+        }
+    }
+}
+
+//world space box around a 3d collider, rays have no box and stay out of the tree
+pub fn collider_aabb(collider: &Collider3D, transform: &GlobalTransform) -> Option<Aabb> {
+    match collider {
+        Collider3D::OBB(obb) => Some(Aabb::from_transformed(obb.aabb_min, obb.aabb_max, &transform.0)),
+        Collider3D::Sphere(sphere) => {
+            let center = sphere.world_center(Some(transform));
+            let radius = sphere.world_radius(Some(transform));
+            Some(Aabb::new(center - radius, center + radius))
+        }
+        Collider3D::Ray(_) => None,
+    }
+}
+
+//index of a leaf in a DynamicAabbTree, stays the same while the leaf moves
+pub type ProxyId = usize;
+
+#[derive(Clone, Debug)]
+enum NodeKind<T> {
+    Leaf(T),
+    Branch(usize, usize),
+}
+#[derive(Clone, Debug)]
+struct Node<T> {
+    aabb: Aabb, //fattened by AABB_MARGIN for leaves
+    parent: Option<usize>,
+    height: u32, //0 for leaves
+    kind: NodeKind<T>,
+}
+
+//bounding volume hierarchy that's updated as things move instead of being rebuilt, kept balanced with tree rotations
+#[derive(Clone, Debug)]
+pub struct DynamicAabbTree<T> {
+    nodes: Slab<Node<T>>,
+    root: Option<usize>,
+}
+impl<T> Default for DynamicAabbTree<T> {
+    fn default() -> Self {
+        Self {
+            nodes: Slab::new(),
+            root: None,
+        }
+    }
+}
+impl<T: Copy> DynamicAabbTree<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn len(&self) -> usize {
+        //a tree of n leaves has n - 1 branches
+        self.nodes.len().div_ceil(2)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+    pub fn get(&self, proxy: ProxyId) -> Option<T> {
+        match self.nodes.get(proxy)?.kind {
+            NodeKind::Leaf(data) => Some(data),
+            NodeKind::Branch(..) => None,
+        }
+    }
+    //the fattened box the tree holds for the leaf
+    pub fn fat_aabb(&self, proxy: ProxyId) -> Aabb {
+        self.nodes[proxy].aabb
+    }
+    pub fn insert(&mut self, aabb: Aabb, data: T) -> ProxyId {
+        let leaf = self.nodes.insert(Node {
+            aabb: aabb.expanded(AABB_MARGIN),
+            parent: None,
+            height: 0,
+            kind: NodeKind::Leaf(data),
+        });
+        self.insert_leaf(leaf);
+        leaf
+    }
+    pub fn remove(&mut self, proxy: ProxyId) -> T {
+        self.remove_leaf(proxy);
+        match self.nodes.remove(proxy).kind {
+            NodeKind::Leaf(data) => data,
+            NodeKind::Branch(..) => panic!("proxy {} is not a leaf", proxy),
+        }
+    }
+    //moves the leaf, returns true if it had to be reinserted because it left its fat box
+    pub fn update(&mut self, proxy: ProxyId, aabb: Aabb) -> bool {
+        if self.nodes[proxy].aabb.contains(&aabb) {
+            return false;
+        }
+        self.remove_leaf(proxy);
+        self.nodes[proxy].aabb = aabb.expanded(AABB_MARGIN);
+        self.insert_leaf(proxy);
+        true
+    }
+    fn leaves(&self) -> impl Iterator<Item = (ProxyId, &Node<T>)> {
+        self.nodes.iter().filter(|(_, node)| matches!(node.kind, NodeKind::Leaf(_)))
+    }
+
+    fn insert_leaf(&mut self, leaf: usize) {
+        let Some(root) = self.root else {
+            self.root = Some(leaf);
+            self.nodes[leaf].parent = None;
+            return;
+        };
+        //walk down to the cheapest sibling, the cost being how much surface area the tree gains
+        let leaf_aabb = self.nodes[leaf].aabb;
+        let mut index = root;
+        while let NodeKind::Branch(child_1, child_2) = self.nodes[index].kind {
+            let area = self.nodes[index].aabb.surface_area();
+            let combined_area = self.nodes[index].aabb.union(&leaf_aabb).surface_area();
+            //a new parent here costs this much
+            let cost = 2.0 * combined_area;
+            //going further down grows this node anyway
+            let inheritance_cost = 2.0 * (combined_area - area);
+            let child_cost = |child: usize| {
+                let node = &self.nodes[child];
+                let grown_area = leaf_aabb.union(&node.aabb).surface_area();
+                match node.kind {
+                    NodeKind::Leaf(_) => grown_area + inheritance_cost,
+                    NodeKind::Branch(..) => grown_area - node.aabb.surface_area() + inheritance_cost,
+                }
+            };
+            let (cost_1, cost_2) = (child_cost(child_1), child_cost(child_2));
+            if cost < cost_1 && cost < cost_2 {
+                break;
+            }
+            index = if cost_1 < cost_2 { child_1 } else { child_2 };
+        }
+
+        let sibling = index;
+        let old_parent = self.nodes[sibling].parent;
+        let new_parent = self.nodes.insert(Node {
+            aabb: leaf_aabb.union(&self.nodes[sibling].aabb),
+            parent: old_parent,
+            height: self.nodes[sibling].height + 1,
+            kind: NodeKind::Branch(sibling, leaf),
+        });
+        match old_parent {
+            Some(old_parent) => self.replace_child(old_parent, sibling, new_parent),
+            None => self.root = Some(new_parent),
+        }
+        self.nodes[sibling].parent = Some(new_parent);
+        self.nodes[leaf].parent = Some(new_parent);
+        self.refit(Some(new_parent));
+    }
+    fn remove_leaf(&mut self, leaf: usize) {
+        if self.root == Some(leaf) {
+            self.root = None;
+            return;
+        }
+        let parent = self.nodes[leaf].parent.expect("a leaf that isn't the root has a parent");
+        let NodeKind::Branch(child_1, child_2) = self.nodes[parent].kind else {
+            unreachable!("parents are branches")
+        };
+        let sibling = if child_1 == leaf { child_2 } else { child_1 };
+        let grandparent = self.nodes[parent].parent;
+        self.nodes.remove(parent);
+        self.nodes[sibling].parent = grandparent;
+        self.nodes[leaf].parent = None;
+        match grandparent {
+            Some(grandparent) => {
+                self.replace_child(grandparent, parent, sibling);
+                self.refit(Some(grandparent));
+            }
+            None => self.root = Some(sibling),
+        }
+    }
+    fn replace_child(&mut self, parent: usize, old_child: usize, new_child: usize) {
+        if let NodeKind::Branch(child_1, child_2) = &mut self.nodes[parent].kind {
+            if *child_1 == old_child {
+                *child_1 = new_child;
+            } else {
+                *child_2 = new_child;
+            }
+        }
+    }
+    //fixes boxes and heights from index up to the root, rotating where one side got too deep
+    fn refit(&mut self, mut index: Option<usize>) {
+        while let Some(node) = index {
+            let node = self.balance(node);
+            if let NodeKind::Branch(child_1, child_2) = self.nodes[node].kind {
+                self.nodes[node].height = 1 + self.nodes[child_1].height.max(self.nodes[child_2].height);
+                self.nodes[node].aabb = self.nodes[child_1].aabb.union(&self.nodes[child_2].aabb);
+            }
+            index = self.nodes[node].parent;
+        }
+    }
+    //if one child is more than a level deeper, it's pulled up to take a's place, returns the node now where a was
+    fn balance(&mut self, a: usize) -> usize {
+        let NodeKind::Branch(b, c) = self.nodes[a].kind else {
+            return a;
+        };
+        if self.nodes[a].height < 2 {
+            return a;
+        }
+        let balance = self.nodes[c].height as i32 - self.nodes[b].height as i32;
+        if balance > 1 {
+            self.rotate_up(a, c, b)
+        } else if balance < -1 {
+            self.rotate_up(a, b, c)
+        } else {
+            a
+        }
+    }
+    //deep takes a's place with a as a child, a keeps shallow and the shallower of deep's children
+    fn rotate_up(&mut self, a: usize, deep: usize, shallow: usize) -> usize {
+        let NodeKind::Branch(f, g) = self.nodes[deep].kind else {
+            return a;
+        };
+        let a_parent = self.nodes[a].parent;
+        self.nodes[deep].parent = a_parent;
+        self.nodes[a].parent = Some(deep);
+        match a_parent {
+            Some(a_parent) => self.replace_child(a_parent, a, deep),
+            None => self.root = Some(deep),
+        }
+        let (kept, moved) = if self.nodes[f].height > self.nodes[g].height { (f, g) } else { (g, f) };
+        self.nodes[deep].kind = NodeKind::Branch(a, kept);
+        self.nodes[a].kind = NodeKind::Branch(shallow, moved);
+        self.nodes[moved].parent = Some(a);
+        self.nodes[a].aabb = self.nodes[shallow].aabb.union(&self.nodes[moved].aabb);
+        self.nodes[a].height = 1 + self.nodes[shallow].height.max(self.nodes[moved].height);
+        self.nodes[deep].aabb = self.nodes[a].aabb.union(&self.nodes[kept].aabb);
+        self.nodes[deep].height = 1 + self.nodes[a].height.max(self.nodes[kept].height);
+        deep
+    }
+
+    //every leaf whose fat box overlaps the box
+    pub fn query_aabb(&self, aabb: &Aabb, mut found: impl FnMut(ProxyId, T)) {
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.aabb.intersects(aabb) {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf(data) => found(index, data),
+                NodeKind::Branch(child_1, child_2) => stack.extend([child_1, child_2]),
+            }
+        }
+    }
+    //every leaf whose fat box the ray passes through within max_distance, direction doesn't have to be normalized
+    pub fn query_ray(&self, origin: Vec3, direction: Vec3, max_distance: f32, mut found: impl FnMut(ProxyId, T)) {
+        let inverse_direction = direction.recip();
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.aabb.ray_entry(origin, inverse_direction, max_distance).is_none() {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf(data) => found(index, data),
+                NodeKind::Branch(child_1, child_2) => stack.extend([child_1, child_2]),
+            }
+        }
+    }
+    //every pair of leaves whose fat boxes overlap, each pair once
+    pub fn pairs(&self) -> Vec<(T, T)> {
+        let mut pairs = vec![];
+        for (proxy, node) in self.leaves() {
+            let NodeKind::Leaf(data) = node.kind else {
+                continue;
+            };
+            self.query_aabb(&node.aabb, |other_proxy, other| {
+                if other_proxy > proxy {
+                    pairs.push((data, other));
+                }
+            });
+        }
+        pairs
+    }
+    //longest path from the root to a leaf, a balanced tree of n leaves is around log2(n)
+    pub fn height(&self) -> u32 {
+        self.root.map_or(0, |root| self.nodes[root].height)
+    }
+}
+
+//buckets 2d boxes into square cells, cheap to update when lots of similarly sized things move every frame
+#[derive(Clone, Debug)]
+pub struct UniformGrid2D<T> {
+    pub cell_size: f32,
+    cells: HashMap<IVec2, Vec<T>>,
+    bounds: HashMap<T, (Vec2, Vec2)>,
+    oversized: Vec<T>, //too big or not finite to bucket, checked against everything instead
+}
+impl<T: Copy + Eq + Hash> UniformGrid2D<T> {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            bounds: HashMap::new(),
+            oversized: vec![],
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.bounds.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+    //None when the box covers more than MAX_GRID_CELLS cells or isn't finite, walking its cells would take forever
+    fn cell_range(&self, min: Vec2, max: Vec2) -> Option<(IVec2, IVec2)> {
+        let min_cell = (min / self.cell_size).floor();
+        let max_cell = (max / self.cell_size).floor();
+        if !min_cell.is_finite() || !max_cell.is_finite() {
+            return None;
+        }
+        let cell_count = (max_cell - min_cell + 1.0).max(Vec2::ZERO);
+        if cell_count.x * cell_count.y > MAX_GRID_CELLS {
+            return None;
+        }
+        Some((min_cell.as_ivec2(), max_cell.as_ivec2()))
+    }
+    fn cells_in(min_cell: IVec2, max_cell: IVec2) -> impl Iterator<Item = IVec2> {
+        (min_cell.x..=max_cell.x).flat_map(move |x| (min_cell.y..=max_cell.y).map(move |y| IVec2::new(x, y)))
+    }
+    fn overlaps(&self, item: &T, min: Vec2, max: Vec2) -> bool {
+        let (item_min, item_max) = self.bounds[item];
+        item_min.cmple(max).all() && min.cmple(item_max).all()
+    }
+    //adds the item, or moves it if it's already in the grid
+    pub fn insert(&mut self, item: T, min: Vec2, max: Vec2) {
+        if let Some((old_min, old_max)) = self.bounds.insert(item, (min, max)) {
+            if self.cell_range(old_min, old_max) == self.cell_range(min, max) {
+                return;
+            }
+            self.remove_from_cells(item, old_min, old_max);
+        }
+        match self.cell_range(min, max) {
+            Some((min_cell, max_cell)) => {
+                for cell in Self::cells_in(min_cell, max_cell) {
+                    self.cells.entry(cell).or_default().push(item);
+                }
+            }
+            None => self.oversized.push(item),
+        }
+    }
+    pub fn remove(&mut self, item: T) {
+        if let Some((min, max)) = self.bounds.remove(&item) {
+            self.remove_from_cells(item, min, max);
+        }
+    }
+    fn remove_from_cells(&mut self, item: T, min: Vec2, max: Vec2) {
+        let Some((min_cell, max_cell)) = self.cell_range(min, max) else {
+            self.oversized.retain(|other| *other != item);
+            return;
+        };
+        for cell in Self::cells_in(min_cell, max_cell) {
+            if let Some(items) = self.cells.get_mut(&cell) {
+                items.retain(|other| *other != item);
+                if items.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+    //every item whose box overlaps the box
+    pub fn query_aabb(&self, min: Vec2, max: Vec2) -> Vec<T> {
+        let mut found = HashSet::new();
+        match self.cell_range(min, max) {
+            Some((min_cell, max_cell)) => {
+                for cell in Self::cells_in(min_cell, max_cell) {
+                    found.extend(self.cells.get(&cell).into_iter().flatten().filter(|item| self.overlaps(item, min, max)));
+                }
+                found.extend(self.oversized.iter().filter(|item| self.overlaps(item, min, max)));
+            }
+            //a query box this big is cheaper to check against everything
+            None => found.extend(self.bounds.keys().filter(|item| self.overlaps(item, min, max))),
+        }
+        found.into_iter().collect()
+    }
+    pub fn query_point(&self, point: Vec2) -> Vec<T> {
+        self.query_aabb(point, point)
+    }
+    //every pair of items whose boxes overlap, each pair once
+    pub fn pairs(&self) -> Vec<(T, T)> {
+        let mut pairs = vec![];
+        for (cell, items) in &self.cells {
+            for (i, a) in items.iter().enumerate() {
+                let (a_min, a_max) = self.bounds[a];
+                for b in &items[i + 1..] {
+                    if !self.overlaps(b, a_min, a_max) {
+                        continue;
+                    }
+                    //two items can share several cells, only the first cell of their overlap reports them
+                    let (b_min, b_max) = self.bounds[b];
+                    let first_shared_cell = self.cell_range(a_min.max(b_min), a_max.min(b_max)).map(|range| range.0);
+                    if first_shared_cell == Some(*cell) {
+                        pairs.push((*a, *b));
+                    }
+                }
+            }
+        }
+        for (i, a) in self.oversized.iter().enumerate() {
+            let (a_min, a_max) = self.bounds[a];
+            for b in self.bounds.keys() {
+                //pairs with the oversized items before this one were reported by them
+                if !self.oversized[..=i].contains(b) && self.overlaps(b, a_min, a_max) {
+                    pairs.push((*a, *b));
+                }
+            }
+        }
+        pairs
+    }
+}
+
+//the engine's broadphase, kept up to date from the collider components by update_broadphase at the start of every frame
+#[derive(Resource, Clone, Debug)]
+pub struct Broadphase {
+    pub tree: DynamicAabbTree<Entity>, //Collider3D
+    pub grid: UniformGrid2D<Entity>, //Box2D and Circle
+    proxies: HashMap<Entity, ProxyId>,
+    filled: bool, //false until the first update adds every collider, not just the changed ones
+}
+impl Default for Broadphase {
+    fn default() -> Self {
+        Self::with_grid_cell_size(1.0)
+    }
+}
+impl Broadphase {
+    //cells around the size of the typical 2d collider work best
+    pub fn with_grid_cell_size(cell_size: f32) -> Self {
+        Self {
+            tree: DynamicAabbTree::new(),
+            grid: UniformGrid2D::new(cell_size),
+            proxies: HashMap::new(),
+            filled: false,
+        }
+    }
+    //adds or moves the entity's collider in the tree, None takes it out
+    pub fn set_3d(&mut self, entity: Entity, aabb: Option<Aabb>) {
+        match (self.proxies.get(&entity), aabb) {
+            (Some(&proxy), Some(aabb)) => {
+                self.tree.update(proxy, aabb);
+            }
+            (None, Some(aabb)) => {
+                let proxy = self.tree.insert(aabb, entity);
+                self.proxies.insert(entity, proxy);
+            }
+            (Some(_), None) => self.remove_3d(entity),
+            (None, None) => {}
+        }
+    }
+    pub fn remove_3d(&mut self, entity: Entity) {
+        if let Some(proxy) = self.proxies.remove(&entity) {
+            self.tree.remove(proxy);
+        }
+    }
+    pub fn contains_3d(&self, entity: Entity) -> bool {
+        self.proxies.contains_key(&entity)
+    }
+    //entities whose 3d colliders might overlap, still have to be checked with Collider3D::check_collision
+    pub fn pairs_3d(&self) -> Vec<(Entity, Entity)> {
+        self.tree.pairs()
+    }
+    //entities whose 2d colliders' boxes overlap
+    pub fn pairs_2d(&self) -> Vec<(Entity, Entity)> {
+        self.grid.pairs()
+    }
+}
+
+type ColliderData3D = (Entity, &'static Collider3D, Option<&'static GlobalTransform>, Option<&'static Instance>);
+type ColliderData2D<T> = (Entity, &'static T, &'static Instance);
+type Moved3D = Or<(Changed<Collider3D>, Changed<GlobalTransform>, Changed<Instance>)>;
+type Moved2D<T> = Or<(Changed<T>, Changed<Instance>)>;
+
+//only colliders that were added, changed or moved since last frame are touched, except the first time when all of them are added
+#[allow(clippy::too_many_arguments)]
+pub fn update_broadphase(
+    mut broadphase: ResMut<Broadphase>,
+    changed_colliders: Query<ColliderData3D, Moved3D>,
+    changed_boxes: Query<ColliderData2D<Box2D>, Moved2D<Box2D>>,
+    changed_circles: Query<ColliderData2D<Circle>, Moved2D<Circle>>,
+    all_colliders: Query<ColliderData3D>,
+    all_boxes: Query<ColliderData2D<Box2D>>,
+    all_circles: Query<ColliderData2D<Circle>>,
+    mut removed: (RemovedComponents<Collider3D>, RemovedComponents<Box2D>, RemovedComponents<Circle>),
+) {
+    for entity in removed.0.iter() {
+        broadphase.remove_3d(entity);
+    }
+    for entity in removed.1.iter().chain(removed.2.iter()) {
+        broadphase.grid.remove(entity);
+    }
+    type Boxed<'a, T> = Box<dyn Iterator<Item = T> + 'a>;
+    let (colliders, boxes, circles): (Boxed<_>, Boxed<_>, Boxed<_>) = if broadphase.filled {
+        (Box::new(changed_colliders.iter()), Box::new(changed_boxes.iter()), Box::new(changed_circles.iter()))
+    } else {
+        broadphase.filled = true;
+        (Box::new(all_colliders.iter()), Box::new(all_boxes.iter()), Box::new(all_circles.iter()))
+    };
+    for (entity, collider, global_transform, instance) in colliders {
+        let transform = collider_transform(global_transform, instance);
+        broadphase.set_3d(entity, collider_aabb(collider, &transform));
+    }
+    for (entity, collider, instance) in boxes {
+        let (min, max) = collider.bounds(instance);
+        broadphase.grid.insert(entity, min, max);
+    }
+    for (entity, collider, instance) in circles {
+        let (min, max) = collider.bounds(instance);
+        broadphase.grid.insert(entity, min, max);
+    }
+}
@@ -1,126 +1,307 @@
-use std::f32::INFINITY;
+use glam::{Mat4, Vec3};
 
-use glam::Vec3;
+use super::structs_3d::{Contact, RayHit};
 
+//slab test in the box's own space, a ray starting inside hits at distance 0
 pub fn oriented_bounding_box_with_ray(
     ray_origin: Vec3,    // Ray origin, in world space
     ray_direction: Vec3, // Ray direction (NOT target position!), in world space. Must be normalize()'d.
     aabb_min: Vec3,      // Minimum X,Y,Z coords of the mesh when not transformed at all.
     aabb_max: Vec3, // Maximum X,Y,Z coords. Often aabb_min*-1 if your mesh is centered, but it's not always the case.
     model_matrix: [[f32; 4]; 4],
-) -> Option<f32> /*intersection distance */ {
-    let mut t_min = 0.0; //largest near intersection found
-    let mut t_max = INFINITY; //smallest far interaction found
+) -> Option<RayHit> {
     const THRESHOLD: f32 = 0.0000000001;
-    let obb_worldspace = Vec3::new(model_matrix[3][0], model_matrix[3][1], model_matrix[3][2]); //3rd x,y, and z
-    let delta = obb_worldspace - ray_origin;
-
-    // Test intersection with the 2 planes perpendicular to the OBB's X axis
-    let x_axis = Vec3::new(model_matrix[0][0], model_matrix[0][1], model_matrix[0][2]);
-    let e = x_axis.dot(delta);
-    let f = ray_direction.dot(x_axis);
-    if f.abs() > THRESHOLD {
-        //standard case
-        let mut t1 = (e + aabb_min.x) / f;
-        let mut t2 = (e + aabb_max.x) / f;
-        // t1 and t2 now contain distances betwen ray origin and ray-plane intersections
-        if t1 > t2 {
-            // if wrong order
-            let w = t1;
-            t1 = t2;
-            t2 = w; // swap t1 and t2
+    let obb = WorldBox::new(aabb_min, aabb_max, model_matrix);
+    let offset = ray_origin - obb.center;
+    let mut t_min = 0.0; //largest near intersection found, the ray doesn't go backwards
+    let mut t_max = f32::INFINITY; //smallest far intersection found
+    let mut hit_normal = -ray_direction; //stays this when the ray starts inside
+    for i in 0..3 {
+        let axis = obb.axes[i];
+        let origin = axis.dot(offset);
+        let direction = axis.dot(ray_direction);
+        let half_extent = obb.half_extents[i];
+        if direction.abs() < THRESHOLD {
+            //parallel to both planes, it either runs between them the whole way or never gets between them
+            if origin.abs() > half_extent {
+                return None;
+            }
+            continue;
         }
-        // tMax is the nearest "far" intersection (amongst the X,Y and Z planes pairs)
-        if t2 < t_max {
-            t_max = t2;
+        let mut t1 = (-half_extent - origin) / direction;
+        let mut t2 = (half_extent - origin) / direction;
+        //the ray enters through the plane facing it
+        let mut entry_normal = -axis;
+        if t1 > t2 {
+            std::mem::swap(&mut t1, &mut t2);
+            entry_normal = axis;
         }
-        // tMin is the farthest "near" intersection (amongst the X,Y and Z planes pairs)
         if t1 > t_min {
             t_min = t1;
+            hit_normal = entry_normal;
         }
+        t_max = t_max.min(t2);
         // If "far" is closer than "near", then there is NO intersection.
         if t_max < t_min {
             return None;
         }
-    } else {
-        // Rare case : the ray is almost parallel to the planes, so they don't have any "intersection"
-        if -e + aabb_min.x > 0.0 || -e + aabb_max.x < 0.0 {
-            return None;
-        }
     }
-    //Test intersection with the 2 planes perpendicular to the OBB's Y axis
-    let y_axis = Vec3::new(model_matrix[1][0], model_matrix[1][1], model_matrix[1][2]);
-    let e = y_axis.dot(delta);
-    let f = ray_direction.dot(y_axis);
-    if f.abs() > THRESHOLD {
-        let mut t1 = (e + aabb_min.y) / f;
-        let mut t2 = (e + aabb_max.y) / f;
-        if t1 > t2 {
-            // if wrong order
-            let w = t1;
-            t1 = t2;
-            t2 = w; // swap t1 and t2
-        }
-        // tMax is the nearest "far" intersection (amongst the X,Y and Z planes pairs)
-        if t2 < t_max {
-            t_max = t2;
-        }
-        // tMin is the farthest "near" intersection (amongst the X,Y and Z planes pairs)
-        if t1 > t_min {
-            t_min = t1;
+    Some(RayHit {
+        distance: t_min,
+        point: ray_origin + ray_direction * t_min,
+        normal: hit_normal,
+    })
+}
+//a ray starting inside hits at distance 0, facing back along the ray
+pub fn sphere_with_ray_collision(
+    ray_origin: Vec3,
+    ray_direction: Vec3, // Must be normalize()'d.
+    sphere_radius: f32,
+    sphere_center: Vec3,
+) -> Option<RayHit> {
+    let delta = ray_origin - sphere_center;
+    let b = delta.dot(ray_direction);
+    let c = delta.dot(delta) - sphere_radius * sphere_radius;
+    if c <= 0.0 {
+        return Some(RayHit {
+            distance: 0.0,
+            point: ray_origin,
+            normal: -ray_direction,
+        });
+    }
+    // outside and pointing away
+    if b > 0.0 {
+        return None;
+    }
+    let h = b * b - c;
+    if h < 0.0 {
+        return None;
+    } // no intersection
+    let distance = -b - h.sqrt();
+    let point = ray_origin + ray_direction * distance;
+    Some(RayHit {
+        distance,
+        point,
+        normal: (point - sphere_center) / sphere_radius,
+    })
+}
+
+//a box in world space, scale is folded into the half extents so the axes stay unit length
+#[derive(Copy, Clone, Debug)]
+struct WorldBox {
+    center: Vec3,
+    axes: [Vec3; 3],
+    half_extents: Vec3,
+}
+impl WorldBox {
+    fn new(aabb_min: Vec3, aabb_max: Vec3, model_matrix: [[f32; 4]; 4]) -> Self {
+        let matrix = Mat4::from_cols_array_2d(&model_matrix);
+        let axes = [matrix.x_axis.truncate(), matrix.y_axis.truncate(), matrix.z_axis.truncate()];
+        let scale = Vec3::new(axes[0].length(), axes[1].length(), axes[2].length());
+        //a zero scale axis has no direction, the unit one stands in and the box is flat along it
+        let unit_axes = [Vec3::X, Vec3::Y, Vec3::Z];
+        Self {
+            //the mesh isn't always centered on its origin
+            center: matrix.transform_point3((aabb_min + aabb_max) * 0.5),
+            axes: [0, 1, 2].map(|i| axes[i].try_normalize().unwrap_or(unit_axes[i])),
+            half_extents: (aabb_max - aabb_min) * 0.5 * scale,
         }
-        if t_max < t_min {
+    }
+    //half the length of the box's shadow on the axis
+    fn projected_radius(&self, axis: Vec3) -> f32 {
+        (0..3).map(|i| self.half_extents[i] * self.axes[i].dot(axis).abs()).sum()
+    }
+    //the corner furthest along the direction
+    fn support(&self, direction: Vec3) -> Vec3 {
+        (0..3).fold(self.center, |point, i| {
+            point + self.axes[i] * self.half_extents[i] * self.axes[i].dot(direction).signum()
+        })
+    }
+    //corners of the face whose outward normal points most along the direction, in winding order
+    fn face(&self, direction: Vec3) -> [Vec3; 4] {
+        let i = (0..3)
+            .max_by(|&a, &b| self.axes[a].dot(direction).abs().total_cmp(&self.axes[b].dot(direction).abs()))
+            .unwrap_or(0);
+        let normal = self.axes[i] * self.axes[i].dot(direction).signum();
+        let face_center = self.center + normal * self.half_extents[i];
+        let u = self.axes[(i + 1) % 3] * self.half_extents[(i + 1) % 3];
+        let v = self.axes[(i + 2) % 3] * self.half_extents[(i + 2) % 3];
+        [face_center + u + v, face_center - u + v, face_center - u - v, face_center + u - v]
+    }
+}
+
+//separating axis test between two oriented boxes, the normal points from a to b
+pub fn oriented_bounding_box_with_oriented_bounding_box(
+    a_aabb_min: Vec3,
+    a_aabb_max: Vec3,
+    a_model_matrix: [[f32; 4]; 4],
+    b_aabb_min: Vec3,
+    b_aabb_max: Vec3,
+    b_model_matrix: [[f32; 4]; 4],
+) -> Option<Contact> {
+    //edge axes have to beat a face axis by this much to be picked, parallel faces otherwise flicker between the two
+    const EDGE_BIAS: f32 = 1.05;
+    const PARALLEL_THRESHOLD: f32 = 1e-6;
+    let a = WorldBox::new(a_aabb_min, a_aabb_max, a_model_matrix);
+    let b = WorldBox::new(b_aabb_min, b_aabb_max, b_model_matrix);
+    let delta = b.center - a.center;
+
+    //0-2 are a's faces, 3-5 b's faces, 6-14 an edge of a crossed with an edge of b
+    let mut best: Option<(usize, Vec3, f32)> = None;
+    for index in 0..15 {
+        let axis = match index {
+            0..=2 => a.axes[index],
+            3..=5 => b.axes[index - 3],
+            _ => {
+                let cross = a.axes[(index - 6) / 3].cross(b.axes[(index - 6) % 3]);
+                //edges that are parallel don't give a new axis, the face axes already cover them
+                if cross.length_squared() < PARALLEL_THRESHOLD {
+                    continue;
+                }
+                cross.normalize()
+            }
+        };
+        let distance = delta.dot(axis);
+        let overlap = a.projected_radius(axis) + b.projected_radius(axis) - distance.abs();
+        if overlap < 0.0 {
             return None;
         }
-    } else {
-        if -e + aabb_min.y > 0.0 || -e + aabb_max.y < 0.0 {
-            return None;
+        let biased_overlap = if index >= 6 { overlap * EDGE_BIAS } else { overlap };
+        let is_better = match best {
+            Some((best_index, _, best_overlap)) => {
+                let best_biased = if best_index >= 6 { best_overlap * EDGE_BIAS } else { best_overlap };
+                biased_overlap < best_biased
+            }
+            None => true,
+        };
+        if is_better {
+            let normal = if distance < 0.0 { -axis } else { axis };
+            best = Some((index, normal, overlap));
         }
     }
-    // Test intersection with the 2 planes perpendicular to the OBB's Z axis
-    let z_axis = Vec3::new(model_matrix[2][0], model_matrix[2][1], model_matrix[2][2]);
-    let e = z_axis.dot(delta);
-    let f = ray_direction.dot(z_axis);
-    if f.abs() > THRESHOLD {
-        let mut t1 = (e + aabb_min.y) / f;
-        let mut t2 = (e + aabb_max.y) / f;
-        if t1 > t2 {
-            // if wrong order
-            let w = t1;
-            t1 = t2;
-            t2 = w; // swap t1 and t2
-        }
-        // tMax is the nearest "far" intersection (amongst the X,Y and Z planes pairs)
-        if t2 < t_max {
-            t_max = t2;
-        }
-        // tMin is the farthest "near" intersection (amongst the X,Y and Z planes pairs)
-        if t1 > t_min {
-            t_min = t1;
+    let (index, normal, penetration) = best?;
+
+    let points = if index < 6 {
+        //face contact, the incident face gets clipped to the sides of the reference face
+        let (reference, incident, reference_normal) = if index < 3 { (a, b, normal) } else { (b, a, -normal) };
+        let reference_axis = index % 3;
+        let mut polygon = incident.face(-reference_normal).to_vec();
+        for side in [(reference_axis + 1) % 3, (reference_axis + 2) % 3] {
+            for sign in [1.0, -1.0] {
+                let plane_normal = reference.axes[side] * sign;
+                let plane_offset = plane_normal.dot(reference.center) + reference.half_extents[side];
+                polygon = clip_polygon(&polygon, plane_normal, plane_offset);
+            }
         }
-        if t_max < t_min {
-            return None;
+        let face_offset = reference_normal.dot(reference.center) + reference.half_extents[reference_axis];
+        let points: Vec<Vec3> = polygon
+            .into_iter()
+            .filter(|point| reference_normal.dot(*point) <= face_offset + PARALLEL_THRESHOLD)
+            .collect();
+        if points.is_empty() {
+            vec![incident.support(-reference_normal)]
+        } else {
+            points
         }
     } else {
-        if -e + aabb_min.y > 0.0 || -e + aabb_max.y < 0.0 {
-            return None;
+        //edge contact, the touching point is between the closest points of the two edges
+        let a_axis = (index - 6) / 3;
+        let b_axis = (index - 6) % 3;
+        let a_point = a.support(normal);
+        let b_point = b.support(-normal);
+        //the support corner is at one end of the edge, step back to its middle
+        let a_mid = a_point - a.axes[a_axis] * a.axes[a_axis].dot(a_point - a.center);
+        let b_mid = b_point - b.axes[b_axis] * b.axes[b_axis].dot(b_point - b.center);
+        let (on_a, on_b) = closest_points_between_lines(a_mid, a.axes[a_axis], b_mid, b.axes[b_axis]);
+        vec![(on_a + on_b) * 0.5]
+    };
+    Some(Contact { normal, penetration, points })
+}
+
+//keeps the part of the polygon behind the plane (normal.dot(point) <= offset)
+fn clip_polygon(polygon: &[Vec3], plane_normal: Vec3, plane_offset: f32) -> Vec<Vec3> {
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for (i, &start) in polygon.iter().enumerate() {
+        let end = polygon[(i + 1) % polygon.len()];
+        let start_distance = plane_normal.dot(start) - plane_offset;
+        let end_distance = plane_normal.dot(end) - plane_offset;
+        if start_distance <= 0.0 {
+            clipped.push(start);
+        }
+        if (start_distance <= 0.0) != (end_distance <= 0.0) {
+            clipped.push(start + (end - start) * (start_distance / (start_distance - end_distance)));
         }
     }
-    Some(t_min)
+    clipped
 }
-pub fn sphere_with_ray_collision(
-    ray_origin: Vec3,
-    ray_direction: Vec3,
-    sphere_radius: f32,
-    sphere_center: Vec3,
-) -> Option<f32> {
-    let delta = ray_origin - sphere_center;
 
-    let b = delta.dot(ray_direction);
-    let c = delta.dot(delta) - sphere_radius * sphere_radius;
-    let h = b * b - c;
-    if h < 0.0 {
+fn closest_points_between_lines(a_point: Vec3, a_direction: Vec3, b_point: Vec3, b_direction: Vec3) -> (Vec3, Vec3) {
+    let offset = a_point - b_point;
+    let along = a_direction.dot(b_direction);
+    let a_offset = a_direction.dot(offset);
+    let b_offset = b_direction.dot(offset);
+    let denominator = 1.0 - along * along; //both directions are unit length
+    if denominator.abs() < 1e-6 {
+        return (a_point, b_point + b_direction * b_offset);
+    }
+    let a_t = (along * b_offset - a_offset) / denominator;
+    let b_t = (b_offset - along * a_offset) / denominator;
+    (a_point + a_direction * a_t, b_point + b_direction * b_t)
+}
+
+//the normal points from a to b, centers on top of each other get pushed apart along +y
+pub fn sphere_with_sphere(a_center: Vec3, a_radius: f32, b_center: Vec3, b_radius: f32) -> Option<Contact> {
+    let delta = b_center - a_center;
+    let distance = delta.length();
+    let radii = a_radius + b_radius;
+    if distance > radii {
         return None;
-    } // no intersection
-    Some(0.0)
+    }
+    let normal = if distance > 0.0 { delta / distance } else { Vec3::Y };
+    let penetration = radii - distance;
+    Some(Contact {
+        normal,
+        penetration,
+        points: vec![a_center + normal * (a_radius - penetration * 0.5)],
+    })
+}
+
+//the normal points from the sphere to the box
+pub fn sphere_with_oriented_bounding_box(
+    sphere_center: Vec3,
+    sphere_radius: f32,
+    aabb_min: Vec3,
+    aabb_max: Vec3,
+    model_matrix: [[f32; 4]; 4],
+) -> Option<Contact> {
+    let obb = WorldBox::new(aabb_min, aabb_max, model_matrix);
+    let offset = sphere_center - obb.center;
+    let local = Vec3::new(obb.axes[0].dot(offset), obb.axes[1].dot(offset), obb.axes[2].dot(offset));
+    let closest_local = local.clamp(-obb.half_extents, obb.half_extents);
+    let to_world = |local: Vec3| obb.center + obb.axes[0] * local.x + obb.axes[1] * local.y + obb.axes[2] * local.z;
+
+    if closest_local != local {
+        //center outside the box, the closest point on the surface is the contact
+        let closest = to_world(closest_local);
+        let delta = sphere_center - closest;
+        let distance = delta.length();
+        if distance > sphere_radius {
+            return None;
+        }
+        return Some(Contact {
+            normal: -delta / distance,
+            penetration: sphere_radius - distance,
+            points: vec![closest],
+        });
+    }
+    //center inside the box, it leaves through the nearest face
+    let depths = obb.half_extents - local.abs();
+    let axis = (0..3).min_by(|&a, &b| depths[a].total_cmp(&depths[b])).unwrap_or(0);
+    let sign = if local[axis] < 0.0 { -1.0 } else { 1.0 };
+    let face_normal = obb.axes[axis] * sign;
+    Some(Contact {
+        normal: -face_normal,
+        penetration: sphere_radius + depths[axis],
+        points: vec![sphere_center + face_normal * depths[axis]],
+    })
 }
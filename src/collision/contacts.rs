@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+
+use bevy_ecs::prelude::*;
+
+use super::{spatial_query::SpatialQuery, structs_3d::Contact};
+
+//a collider that only reports what's inside it, pairs with a sensor send TriggerEntered/TriggerExited instead of CollisionStarted/CollisionEnded
+#[derive(Component, Copy, Clone, Debug, Default)]
+pub struct Sensor;
+
+//the entities are in order, so the same pair always reads the same way
+#[derive(Event, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CollisionStarted(pub Entity, pub Entity);
+#[derive(Event, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CollisionEnded(pub Entity, pub Entity); //one of them may already be despawned
+//sent once for each sensor in the pair, so two overlapping sensors both hear about it
+#[derive(Event, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TriggerEntered {
+    pub sensor: Entity,
+    pub other: Entity,
+}
+#[derive(Event, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TriggerExited {
+    pub sensor: Entity,
+    pub other: Entity,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContactPair {
+    pub contact: Contact, //normal points from the first entity to the second
+    pub sensors: [bool; 2], //which of the two were sensors when they started touching, so the exit matches the enter
+    pub frames: u32, //how many updates they've been touching for, 1 on the first
+}
+
+//every pair of colliders touching as of the last detect_collisions, kept across frames so starts and ends can be told apart
+#[derive(Resource, Clone, Debug, Default)]
+pub struct Contacts {
+    pairs: BTreeMap<(Entity, Entity), ContactPair>,
+}
+impl Contacts {
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+    pub fn touching(&self, a: Entity, b: Entity) -> bool {
+        self.pairs.contains_key(&ordered(a, b))
+    }
+    //the contact with its normal pointing from a to b
+    pub fn get(&self, a: Entity, b: Entity) -> Option<Contact> {
+        let contact = self.pairs.get(&ordered(a, b))?.contact.clone();
+        Some(if a <= b { contact } else { contact.flipped() })
+    }
+    pub fn pair(&self, a: Entity, b: Entity) -> Option<&ContactPair> {
+        self.pairs.get(&ordered(a, b))
+    }
+    //everything the entity is touching, the normals point away from it
+    pub fn contacts_of(&self, entity: Entity) -> impl Iterator<Item = (Entity, Contact)> + '_ {
+        self.pairs.iter().filter_map(move |(&(a, b), pair)| {
+            if a == entity {
+                Some((b, pair.contact.clone()))
+            } else if b == entity {
+                Some((a, pair.contact.clone().flipped()))
+            } else {
+                None
+            }
+        })
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, Entity, &ContactPair)> {
+        self.pairs.iter().map(|(&(a, b), pair)| (a, b, pair))
+    }
+}
+
+fn ordered(a: Entity, b: Entity) -> (Entity, Entity) {
+    (a.min(b), a.max(b))
+}
+
+//compares this frame's touching pairs with last frame's and sends events for the differences, runs in PreUpdate after the broadphase
+pub fn detect_collisions(
+    spatial_query: SpatialQuery,
+    sensors: Query<(), With<Sensor>>,
+    mut contacts: ResMut<Contacts>,
+    mut started: EventWriter<CollisionStarted>,
+    mut ended: EventWriter<CollisionEnded>,
+    mut entered: EventWriter<TriggerEntered>,
+    mut exited: EventWriter<TriggerExited>,
+) {
+    let mut touching = BTreeMap::new();
+    for (a, b, contact) in spatial_query.contact_pairs() {
+        let contact = if a <= b { contact } else { contact.flipped() };
+        let (a, b) = ordered(a, b);
+        let pair = match contacts.pairs.remove(&(a, b)) {
+            Some(previous) => ContactPair {
+                contact,
+                sensors: previous.sensors,
+                frames: previous.frames + 1,
+            },
+            None => {
+                let sensors = [sensors.contains(a), sensors.contains(b)];
+                match sensors {
+                    [false, false] => started.send(CollisionStarted(a, b)),
+                    _ => {
+                        if sensors[0] {
+                            entered.send(TriggerEntered { sensor: a, other: b });
+                        }
+                        if sensors[1] {
+                            entered.send(TriggerEntered { sensor: b, other: a });
+                        }
+                    }
+                }
+                ContactPair { contact, sensors, frames: 1 }
+            }
+        };
+        touching.insert((a, b), pair);
+    }
+    //whatever wasn't carried over stopped touching, moved apart, got disabled or despawned
+    for ((a, b), pair) in std::mem::replace(&mut contacts.pairs, touching) {
+        match pair.sensors {
+            [false, false] => ended.send(CollisionEnded(a, b)),
+            sensors => {
+                if sensors[0] {
+                    exited.send(TriggerExited { sensor: a, other: b });
+                }
+                if sensors[1] {
+                    exited.send(TriggerExited { sensor: b, other: a });
+                }
+            }
+        }
+    }
+}
+
+//the events and Contacts resource detect_collisions needs, State adds these itself
+pub fn init_collision_events(world: &mut World) {
+    world.init_resource::<Contacts>();
+    world.init_resource::<Events<CollisionStarted>>();
+    world.init_resource::<Events<CollisionEnded>>();
+    world.init_resource::<Events<TriggerEntered>>();
+    world.init_resource::<Events<TriggerExited>>();
+}
+//swaps the event buffers so events last until the end of the next frame, run before detect_collisions
+pub fn update_collision_events(
+    mut started: ResMut<Events<CollisionStarted>>,
+    mut ended: ResMut<Events<CollisionEnded>>,
+    mut entered: ResMut<Events<TriggerEntered>>,
+    mut exited: ResMut<Events<TriggerExited>>,
+) {
+    started.update();
+    ended.update();
+    entered.update();
+    exited.update();
+}
@@ -0,0 +1,274 @@
+use bevy_ecs::{prelude::*, system::SystemParam};
+use glam::{Vec2, Vec3};
+
+use crate::{hierarchy::GlobalTransform, instance::Instance};
+
+use super::{
+    broadphase::{collider_aabb, Aabb, Broadphase},
+    structs_2d::{Box2D, Circle, Shape2D},
+    structs_3d::{Collider3D, ColliderResult, Contact, Ray},
+};
+
+//which layers a collider is on, a query only sees colliders sharing a layer with its mask
+#[derive(Component, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CollisionLayers(pub u32);
+impl CollisionLayers {
+    pub const ALL: Self = Self(u32::MAX);
+    pub const NONE: Self = Self(0);
+    pub fn layer(layer: u32) -> Self {
+        Self(1 << layer)
+    }
+    pub fn with(self, layer: u32) -> Self {
+        Self(self.0 | 1 << layer)
+    }
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+//colliders without the component are on every layer
+impl Default for CollisionLayers {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+//narrows down what a query can hit
+#[derive(Clone, Debug, Default)]
+pub struct QueryFilter {
+    pub layers: CollisionLayers,
+    pub excluded: Vec<Entity>, //like the player casting a ray out of its own collider
+    pub include_disabled: bool, //disabled instances are skipped unless this is set
+}
+impl QueryFilter {
+    fn allows(&self, entity: Entity, instance: Option<&Instance>, layers: Option<&CollisionLayers>) -> bool {
+        layers.copied().unwrap_or_default().intersects(self.layers)
+            && !self.excluded.contains(&entity)
+            && (self.include_disabled || instance.map_or(true, |instance| instance.enabled))
+    }
+    pub fn with_layers(mut self, layers: CollisionLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+    pub fn exclude(mut self, entity: Entity) -> Self {
+        self.excluded.push(entity);
+        self
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RaycastHit {
+    pub entity: Entity,
+    pub distance: f32,
+    pub point: Vec3,
+    pub normal: Vec3,
+}
+
+type ColliderData = (
+    Entity,
+    &'static Collider3D,
+    Option<&'static GlobalTransform>,
+    Option<&'static Instance>,
+    Option<&'static CollisionLayers>,
+);
+type ColliderData2D = (
+    Entity,
+    Option<&'static Box2D>,
+    Option<&'static Circle>,
+    &'static Instance,
+    Option<&'static CollisionLayers>,
+);
+pub(crate) type Has2DCollider = Or<(With<Box2D>, With<Circle>)>;
+
+//casts rays and checks overlaps against every Collider3D, Box2D and Circle in the world, add it to a system's parameters
+//with the Broadphase resource from CollisionPlugin only nearby colliders are checked, it's refreshed in PreUpdate so colliders spawned this frame show up next frame
+#[derive(SystemParam)]
+pub struct SpatialQuery<'w, 's> {
+    colliders: Query<'w, 's, ColliderData>,
+    colliders_2d: Query<'w, 's, ColliderData2D, Has2DCollider>,
+    broadphase: Option<Res<'w, Broadphase>>,
+}
+impl<'w, 's> SpatialQuery<'w, 's> {
+    //the closest hit within max_distance
+    pub fn cast_ray(&self, ray: Ray, max_distance: f32, filter: &QueryFilter) -> Option<RaycastHit> {
+        self.hits(ray, max_distance, filter)
+            .into_iter()
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+    //every hit within max_distance, nearest first
+    pub fn cast_ray_all(&self, ray: Ray, max_distance: f32, filter: &QueryFilter) -> Vec<RaycastHit> {
+        let mut hits = self.hits(ray, max_distance, filter);
+        hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        hits
+    }
+    fn hits(&self, ray: Ray, max_distance: f32, filter: &QueryFilter) -> Vec<RaycastHit> {
+        let ray_collider = Collider3D::Ray(ray);
+        let mut hits = vec![];
+        let mut check = |(entity, collider, global_transform, instance, layers): (Entity, &Collider3D, _, _, _)| {
+            if !filter.allows(entity, instance, layers) {
+                return;
+            }
+            let transform = collider_transform(global_transform, instance);
+            if let ColliderResult::Collision(hit) = collider.check_collision(Some(&transform), &ray_collider, None) {
+                if hit.distance <= max_distance {
+                    hits.push(RaycastHit {
+                        entity,
+                        distance: hit.distance,
+                        point: hit.point,
+                        normal: hit.normal,
+                    });
+                }
+            }
+        };
+        match &self.broadphase {
+            Some(broadphase) => broadphase.tree.query_ray(ray.origin, ray.direction, max_distance, |_, entity| {
+                if let Ok(collider) = self.colliders.get(entity) {
+                    check(collider);
+                }
+            }),
+            None => self.colliders.iter().for_each(check),
+        }
+        hits
+    }
+    //colliders whose boxes overlap the box, without checking their actual shapes
+    pub fn aabb_overlaps(&self, aabb: &Aabb, filter: &QueryFilter) -> Vec<Entity> {
+        let mut found = vec![];
+        let mut check = |(entity, collider, global_transform, instance, layers): (Entity, &Collider3D, _, _, _)| {
+            let transform = collider_transform(global_transform, instance);
+            let overlaps = collider_aabb(collider, &transform).is_some_and(|collider_aabb| collider_aabb.intersects(aabb));
+            if overlaps && filter.allows(entity, instance, layers) {
+                found.push(entity);
+            }
+        };
+        match &self.broadphase {
+            Some(broadphase) => broadphase.tree.query_aabb(aabb, |_, entity| {
+                if let Ok(collider) = self.colliders.get(entity) {
+                    check(collider);
+                }
+            }),
+            None => self.colliders.iter().for_each(check),
+        }
+        found
+    }
+    //colliders touching the shape placed at transform, the contact normals point from the shape to them
+    pub fn shape_overlaps(&self, shape: &Collider3D, transform: Option<&GlobalTransform>, filter: &QueryFilter) -> Vec<(Entity, Contact)> {
+        let Some(aabb) = collider_aabb(shape, &transform.copied().unwrap_or_default()) else {
+            return vec![];
+        };
+        self.aabb_overlaps(&aabb, filter)
+            .into_iter()
+            .filter_map(|entity| {
+                let (_, collider, global_transform, instance, _) = self.colliders.get(entity).ok()?;
+                let other_transform = collider_transform(global_transform, instance);
+                match shape.check_collision(transform, collider, Some(&other_transform)) {
+                    ColliderResult::Contact(contact) => Some((entity, contact)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+    //every pair of enabled colliders sharing a layer and touching each other, the contact normal points from the first to the second
+    pub fn contact_pairs(&self) -> Vec<(Entity, Entity, Contact)> {
+        let candidates = match &self.broadphase {
+            Some(broadphase) => broadphase.pairs_3d(),
+            None => {
+                let entities: Vec<Entity> = self.colliders.iter().map(|(entity, ..)| entity).collect();
+                entities
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, a)| entities[i + 1..].iter().map(move |b| (*a, *b)))
+                    .collect()
+            }
+        };
+        candidates
+            .into_iter()
+            .filter_map(|(a, b)| {
+                let [(_, a_collider, a_global, a_instance, a_layers), (_, b_collider, b_global, b_instance, b_layers)] =
+                    self.colliders.get_many([a, b]).ok()?;
+                if !a_instance.map_or(true, |instance| instance.enabled) || !b_instance.map_or(true, |instance| instance.enabled) {
+                    return None;
+                }
+                if !a_layers.copied().unwrap_or_default().intersects(b_layers.copied().unwrap_or_default()) {
+                    return None;
+                }
+                let a_transform = collider_transform(a_global, a_instance);
+                let b_transform = collider_transform(b_global, b_instance);
+                match a_collider.check_collision(Some(&a_transform), b_collider, Some(&b_transform)) {
+                    ColliderResult::Contact(contact) => Some((a, b, contact)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+    //2d colliders under the point, for picking
+    pub fn point_overlaps_2d(&self, point: Vec2, filter: &QueryFilter) -> Vec<Entity> {
+        self.overlaps_2d(point, point, filter, |shape| shape.contains_point(point))
+    }
+    //2d colliders whose shapes overlap the box
+    pub fn aabb_overlaps_2d(&self, min: Vec2, max: Vec2, filter: &QueryFilter) -> Vec<Entity> {
+        self.overlaps_2d(min, max, filter, |shape| shape.intersects_aabb(min, max))
+    }
+    fn overlaps_2d(&self, min: Vec2, max: Vec2, filter: &QueryFilter, overlaps: impl Fn(&Shape2D) -> bool) -> Vec<Entity> {
+        let candidates: Vec<Entity> = match &self.broadphase {
+            Some(broadphase) => broadphase.grid.query_aabb(min, max),
+            None => self.colliders_2d.iter().map(|(entity, ..)| entity).collect(),
+        };
+        candidates
+            .into_iter()
+            .filter(|entity| {
+                let Ok((_, box_2d, circle, instance, layers)) = self.colliders_2d.get(*entity) else {
+                    return false;
+                };
+                filter.allows(*entity, Some(instance), layers) && shape_2d(box_2d, circle, instance).is_some_and(|shape| overlaps(&shape))
+            })
+            .collect()
+    }
+    //every pair of enabled 2d colliders sharing a layer and touching each other
+    pub fn contact_pairs_2d(&self) -> Vec<(Entity, Entity)> {
+        let candidates = match &self.broadphase {
+            Some(broadphase) => broadphase.pairs_2d(),
+            None => {
+                let entities: Vec<Entity> = self.colliders_2d.iter().map(|(entity, ..)| entity).collect();
+                entities
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, a)| entities[i + 1..].iter().map(move |b| (*a, *b)))
+                    .collect()
+            }
+        };
+        candidates
+            .into_iter()
+            .filter(|(a, b)| {
+                let Ok([(_, a_box, a_circle, a_instance, a_layers), (_, b_box, b_circle, b_instance, b_layers)]) =
+                    self.colliders_2d.get_many([*a, *b])
+                else {
+                    return false;
+                };
+                a_instance.enabled
+                    && b_instance.enabled
+                    && a_layers.copied().unwrap_or_default().intersects(b_layers.copied().unwrap_or_default())
+                    && match (shape_2d(a_box, a_circle, a_instance), shape_2d(b_box, b_circle, b_instance)) {
+                        (Some(a_shape), Some(b_shape)) => a_shape.intersects(&b_shape),
+                        _ => false,
+                    }
+            })
+            .collect()
+    }
+}
+
+//the broadphase grid holds the circle when an entity has both, so queries use it too
+fn shape_2d(box_2d: Option<&Box2D>, circle: Option<&Circle>, instance: &Instance) -> Option<Shape2D> {
+    match (circle, box_2d) {
+        (Some(circle), _) => circle.shape(instance),
+        (None, Some(box_2d)) => box_2d.shape(instance),
+        (None, None) => None,
+    }
+}
+
+//global transforms only exist after the first frame has been prepared for rendering, until then the instance is the transform
+pub(crate) fn collider_transform(global_transform: Option<&GlobalTransform>, instance: Option<&Instance>) -> GlobalTransform {
+    match (global_transform, instance) {
+        (Some(transform), _) => *transform,
+        (None, Some(instance)) => GlobalTransform(instance.model_matrix()),
+        (None, None) => GlobalTransform::default(),
+    }
+}
@@ -4,6 +4,37 @@ use winit::dpi::PhysicalPosition;
 
 use crate::{resources::WindowEvents, prelude::Instance};
 
+//a 2d collider placed in the world, what overlap queries compare
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Shape2D {
+    Box { min: Vec2, max: Vec2 },
+    Circle { center: Vec2, radius: f32 },
+}
+impl Shape2D {
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        match *self {
+            Shape2D::Box { min, max } => min.cmple(point).all() && point.cmple(max).all(),
+            Shape2D::Circle { center, radius } => point.distance_squared(center) <= radius * radius,
+        }
+    }
+    pub fn intersects_aabb(&self, min: Vec2, max: Vec2) -> bool {
+        self.intersects(&Shape2D::Box { min, max })
+    }
+    pub fn intersects(&self, other: &Shape2D) -> bool {
+        match (*self, *other) {
+            (Shape2D::Box { min, max }, Shape2D::Box { min: other_min, max: other_max }) => {
+                min.cmple(other_max).all() && other_min.cmple(max).all()
+            }
+            (Shape2D::Circle { center, radius }, Shape2D::Circle { center: other_center, radius: other_radius }) => {
+                center.distance_squared(other_center) <= (radius + other_radius).powi(2)
+            }
+            //the closest point of the box to the circle's center decides it
+            (Shape2D::Box { min, max }, Shape2D::Circle { center, radius })
+            | (Shape2D::Circle { center, radius }, Shape2D::Box { min, max }) => center.clamp(min, max).distance_squared(center) <= radius * radius,
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct Box2D {
     x_max: f32,
@@ -22,16 +53,21 @@ impl Box2D {
             enabled: true,
         }
     }
+    //world space corners of the box placed on the instance, what the broadphase grid holds
+    pub fn bounds(&self, instance: &Instance) -> (Vec2, Vec2) {
+        let scale = instance.scale.truncate();
+        let corner_1 = instance.pos_2d() + Vec2::new(self.x_min, self.y_min) * scale;
+        let corner_2 = instance.pos_2d() + Vec2::new(self.x_max, self.y_max) * scale;
+        (corner_1.min(corner_2), corner_1.max(corner_2))
+    }
+    //None while the box is disabled
+    pub fn shape(&self, instance: &Instance) -> Option<Shape2D> {
+        let (min, max) = self.bounds(instance);
+        self.enabled.then_some(Shape2D::Box { min, max })
+    }
+    //the cursor is taken into screen space, where instances that aren't in world space are placed
     pub fn check_collision(&self, instance: &Instance, window_events: &WindowEvents) -> bool {
-        let x = window_events.screen_mouse_pos.x + instance.position.x;
-        let y =
-            (window_events.screen_mouse_pos.y + instance.position.y) / window_events.aspect_ratio;
-        if self.enabled {
-            if x < self.x_max && x > self.x_min && y < self.y_max && y > self.y_min {
-                return true;
-            }
-        }
-        return false;
+        self.shape(instance).is_some_and(|shape| shape.contains_point(window_events.screen_space_mouse_pos()))
     }
 }
 #[derive(Component)]
@@ -50,18 +86,21 @@ impl Circle {
             enabled,
         }
     }
-    pub fn check_collision(&self, pos: &PhysicalPosition<f32>) -> bool {
-        let x = pos.x;
-        let y = pos.y;
-        //find distance betweenn two points
-        let dist_x = (x - self.center_x).powi(2);
-        let dist_y = (y - self.center_y).powi(2);
-        let dist = (dist_x + dist_y).sqrt();
-        if self.enabled && dist < self.radius {
-            return true;
-        }
-        return false;
+    //center is relative to the instance, radius grows with the larger of the x and y scale
+    fn placed(&self, instance: &Instance) -> (Vec2, f32) {
+        let center = instance.pos_2d() + Vec2::new(self.center_x, self.center_y) * instance.scale.truncate();
+        (center, self.radius * instance.scale.truncate().abs().max_element())
+    }
+    pub fn bounds(&self, instance: &Instance) -> (Vec2, Vec2) {
+        let (center, radius) = self.placed(instance);
+        (center - radius, center + radius)
+    }
+    //None while the circle is disabled
+    pub fn shape(&self, instance: &Instance) -> Option<Shape2D> {
+        let (center, radius) = self.placed(instance);
+        self.enabled.then_some(Shape2D::Circle { center, radius })
+    }
+    pub fn check_collision(&self, pos: &PhysicalPosition<f32>, instance: &Instance) -> bool {
+        self.shape(instance).is_some_and(|shape| shape.contains_point(Vec2::new(pos.x, pos.y)))
     }
 }
-
-
@@ -1,9 +1,12 @@
 use bevy_ecs::{component::Component, system::Resource};
 use glam::Vec3;
 
-use crate::prelude::Instance;
+use crate::{prelude::Instance, hierarchy::GlobalTransform};
 
-use super::collision_fns_3d::{oriented_bounding_box_with_ray, sphere_with_ray_collision};
+use super::collision_fns_3d::{
+    oriented_bounding_box_with_oriented_bounding_box, oriented_bounding_box_with_ray, sphere_with_oriented_bounding_box,
+    sphere_with_ray_collision, sphere_with_sphere,
+};
 
 #[derive(Component, Resource)]
 pub enum Collider3D {
@@ -12,95 +15,125 @@ pub enum Collider3D {
     Ray(Ray),
 }
 impl Collider3D {
+    //transform places self in the world and other_transform places other, None means the shape is already in world space
     pub fn check_collision(
         &self,
-        parent_instance: Option<&Instance>,
+        transform: Option<&GlobalTransform>,
         other: &Self,
-        _other_instance: Option<&Instance>,
+        other_transform: Option<&GlobalTransform>,
     ) -> ColliderResult {
-        match self {
-            Collider3D::OBB(obb) => match other {
-                Collider3D::OBB(_) => ColliderResult::NotImplemented,
-                Collider3D::Sphere(_) => ColliderResult::NotImplemented,
-                Collider3D::Ray(ray) => match oriented_bounding_box_with_ray(
-                    ray.origin,
-                    ray.direction,
+        let matrix = |transform: Option<&GlobalTransform>| transform.copied().unwrap_or_default().0.to_cols_array_2d();
+        let contact = |contact: Option<Contact>| match contact {
+            Some(contact) => ColliderResult::Contact(contact),
+            None => ColliderResult::NoCollision,
+        };
+        match (self, other) {
+            (Collider3D::OBB(a), Collider3D::OBB(b)) => contact(oriented_bounding_box_with_oriented_bounding_box(
+                a.aabb_min,
+                a.aabb_max,
+                matrix(transform),
+                b.aabb_min,
+                b.aabb_max,
+                matrix(other_transform),
+            )),
+            (Collider3D::OBB(obb), Collider3D::Sphere(sphere)) => contact(
+                sphere_with_oriented_bounding_box(
+                    sphere.world_center(other_transform),
+                    sphere.world_radius(other_transform),
                     obb.aabb_min,
                     obb.aabb_max,
-                    if parent_instance.is_none() {
-                        Instance {
-                            ..Default::default()
-                        }.to_raw().unwrap().model
-                    } else {
-                        parent_instance.unwrap().to_raw().unwrap().model
-                    },
-                ) {
-                    Some(dist) => ColliderResult::Collision(dist),
-                    None => ColliderResult::NoCollision,
-                },
-            },
-            Collider3D::Sphere(sphere) => match other {
-                Collider3D::OBB(_) => ColliderResult::NotImplemented,
-                Collider3D::Sphere(_) => ColliderResult::NotImplemented,
-                Collider3D::Ray(ray) => match sphere_with_ray_collision(
-                    ray.origin,
-                    ray.direction,
-                    sphere.radius,
-                    sphere.center + if parent_instance.is_none() {
-                        Vec3::ZERO
-                    } else {
-                        parent_instance.unwrap().pos()
-                    }
-                ) {
-                    Some(dist) => ColliderResult::Collision(dist),
-                    None => ColliderResult::NoCollision,
-                },
-            },
-            Collider3D::Ray(ray) => match other {
-                Collider3D::OBB(obb) => match oriented_bounding_box_with_ray(
-                    ray.origin,
-                    ray.direction,
-                    obb.aabb_min,
-                    obb.aabb_max,
-                    if parent_instance.is_none() {
-                        Instance {
-                            ..Default::default()
-                        }.to_raw().unwrap().model
-                    } else {
-                        parent_instance.unwrap().to_raw().unwrap().model
-                    },
-                ) {
-                    Some(dist) => ColliderResult::Collision(dist),
-                    None => ColliderResult::NoCollision,
-                },
-                Collider3D::Sphere(sphere) => match sphere_with_ray_collision(
-                    ray.origin,
-                    ray.direction,
-                    sphere.radius,
-                    sphere.center + if parent_instance.is_none() {
-                        Vec3::ZERO
-                    } else {
-                        parent_instance.unwrap().pos()
-                    }
-                ) {
-                    Some(dist) => ColliderResult::Collision(dist),
-                    None => ColliderResult::NoCollision,
-                },
-                Collider3D::Ray(_) => ColliderResult::NotImplemented,
-            },
+                    matrix(transform),
+                )
+                .map(Contact::flipped),
+            ),
+            (Collider3D::Sphere(sphere), Collider3D::OBB(obb)) => contact(sphere_with_oriented_bounding_box(
+                sphere.world_center(transform),
+                sphere.world_radius(transform),
+                obb.aabb_min,
+                obb.aabb_max,
+                matrix(other_transform),
+            )),
+            (Collider3D::Sphere(a), Collider3D::Sphere(b)) => contact(sphere_with_sphere(
+                a.world_center(transform),
+                a.world_radius(transform),
+                b.world_center(other_transform),
+                b.world_radius(other_transform),
+            )),
+            (Collider3D::OBB(obb), Collider3D::Ray(ray)) => obb_ray(obb, transform, ray),
+            (Collider3D::Ray(ray), Collider3D::OBB(obb)) => obb_ray(obb, other_transform, ray),
+            (Collider3D::Sphere(sphere), Collider3D::Ray(ray)) => sphere_ray(sphere, transform, ray),
+            (Collider3D::Ray(ray), Collider3D::Sphere(sphere)) => sphere_ray(sphere, other_transform, ray),
+            (Collider3D::Ray(_), Collider3D::Ray(_)) => ColliderResult::NotImplemented,
         }
     }
 }
+fn obb_ray(obb: &OBB, transform: Option<&GlobalTransform>, ray: &Ray) -> ColliderResult {
+    match oriented_bounding_box_with_ray(
+        ray.origin,
+        ray.direction,
+        obb.aabb_min,
+        obb.aabb_max,
+        transform.copied().unwrap_or_default().0.to_cols_array_2d(),
+    ) {
+        Some(hit) => ColliderResult::Collision(hit),
+        None => ColliderResult::NoCollision,
+    }
+}
+fn sphere_ray(sphere: &Sphere, transform: Option<&GlobalTransform>, ray: &Ray) -> ColliderResult {
+    match sphere_with_ray_collision(
+        ray.origin,
+        ray.direction,
+        sphere.world_radius(transform),
+        sphere.world_center(transform)
+    ) {
+        Some(hit) => ColliderResult::Collision(hit),
+        None => ColliderResult::NoCollision,
+    }
+}
+#[derive(Clone, Debug, PartialEq)]
 pub enum ColliderResult {
     NotImplemented,
     NoCollision,
-    Collision(f32),
+    Collision(RayHit), //a ray hitting a shape
+    Contact(Contact), //two shapes overlapping
+}
+//where a ray first touches a shape
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RayHit {
+    pub distance: f32, //along the ray, in multiples of its direction
+    pub point: Vec3,
+    pub normal: Vec3, //the surface's, facing the ray
+}
+//how two shapes overlap
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contact {
+    pub normal: Vec3, //points from the first shape to the second, moving the second along it by penetration separates them
+    pub penetration: f32,
+    pub points: Vec<Vec3>, //where they touch in world space, up to 8 for two boxes resting face to face
+}
+impl Contact {
+    //the same contact seen from the other shape
+    pub fn flipped(self) -> Self {
+        Self {
+            normal: -self.normal,
+            ..self
+        }
+    }
 }
 #[derive(Copy, Clone)]
 pub struct Sphere {
     pub center: Vec3,
     pub radius: f32,
 }
+impl Sphere {
+    pub fn world_center(&self, transform: Option<&GlobalTransform>) -> Vec3 {
+        transform.map_or(self.center, |transform| transform.transform_point(self.center))
+    }
+    //a sphere can't be stretched, so the largest scale axis is used to keep it covering the mesh
+    pub fn world_radius(&self, transform: Option<&GlobalTransform>) -> f32 {
+        transform.map_or(self.radius, |transform| self.radius * transform.scale().abs().max_element())
+    }
+}
 #[derive(Copy, Clone)]
 pub struct OBB {
     pub aabb_min: Vec3,
@@ -116,11 +149,11 @@ impl OBB {
             aabb_max: Vec3::new(x, y, z),
         }
     }
-    pub fn check_collision_with_ray(&self, ray: Ray, instance: &Instance) -> Option<f32> {
+    pub fn check_collision_with_ray(&self, ray: Ray, instance: &Instance) -> Option<RayHit> {
         if !instance.enabled {
             return None;
         }
-        let model_matrix = instance.to_raw().unwrap().model;
+        let model_matrix = instance.model_matrix().to_cols_array_2d();
         oriented_bounding_box_with_ray(
             ray.origin,
             ray.direction,
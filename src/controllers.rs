@@ -0,0 +1,201 @@
+use bevy_ecs::prelude::*;
+use glam::{Vec2, Vec3};
+
+use crate::{
+    app_resource::App,
+    camera::Camera,
+    collision::{
+        spatial_query::{QueryFilter, SpatialQuery},
+        structs_3d::Ray,
+    },
+    hierarchy::{world_matrix, Parent},
+    input::{AxisBinding, InputBinding, InputMap},
+    instance::Instance,
+};
+use winit::event::VirtualKeyCode;
+
+const SAFE_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.0001;
+
+//the InputMap axes the free fly and 2d pan controllers move along, rebind them like any other axis
+pub const CAMERA_RIGHT: &str = "camera_right";
+pub const CAMERA_FORWARD: &str = "camera_forward";
+pub const CAMERA_UP: &str = "camera_up";
+
+//wasd or the arrow keys, space and left shift, leaves alone any of the axes that are already bound
+pub fn bind_camera_axes(input_map: &mut InputMap) {
+    use VirtualKeyCode::*;
+    let keys = |keys: &[VirtualKeyCode]| keys.iter().map(|key| InputBinding::Key(*key)).collect();
+    for (axis, negative, positive) in [
+        (CAMERA_RIGHT, &[A, Left][..], &[D, Right][..]),
+        (CAMERA_FORWARD, &[S, Down], &[W, Up]),
+        (CAMERA_UP, &[LShift], &[Space]),
+    ] {
+        if !input_map.bindings.axes.contains_key(axis) {
+            input_map.bind_axis(axis, AxisBinding { negative: keys(negative), positive: keys(positive) });
+        }
+    }
+}
+
+//which controller moves the camera, swap it at runtime by changing this resource
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub enum CameraControllerKind {
+    FreeFly, //the camera axes and mouse look, see default_3d_cam
+    Orbit(OrbitController),
+    Follow(FollowController),
+    PanZoom2D(PanZoom2DController),
+    None, //nothing moves the camera, for cutscenes or your own systems
+}
+
+//mouse turns the camera around the target, scroll moves it closer or further
+#[derive(Clone, Debug, PartialEq)]
+pub struct OrbitController {
+    pub target: Vec3,
+    pub distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+}
+impl Default for OrbitController {
+    fn default() -> Self {
+        Self {
+            target: Vec3::ZERO,
+            distance: 10.0,
+            min_distance: 1.0,
+            max_distance: 100.0,
+        }
+    }
+}
+
+//third person camera behind an entity, mouse turns it around the entity
+#[derive(Clone, Debug, PartialEq)]
+pub struct FollowController {
+    pub target: Entity,
+    pub distance: f32,
+    pub height: f32, //the camera looks at this far above the target's position
+    pub smoothing: f32, //how quickly the camera catches up, bigger is snappier, 0 snaps instantly
+    pub collision_margin: f32, //how far in front of a collider the camera stops when something is in the way
+}
+impl FollowController {
+    pub fn new(target: Entity) -> Self {
+        Self {
+            target,
+            distance: 6.0,
+            height: 1.5,
+            smoothing: 10.0,
+            collision_margin: 0.2,
+        }
+    }
+}
+
+//movement keys pan the 2d camera, scroll zooms it
+#[derive(Clone, Debug, PartialEq)]
+pub struct PanZoom2DController {
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+}
+impl Default for PanZoom2DController {
+    fn default() -> Self {
+        Self {
+            min_zoom: 0.1,
+            max_zoom: 10.0,
+        }
+    }
+}
+
+//turns the camera with the mouse, shared by the orbit and follow controllers
+fn rotate_camera(app: &mut App) {
+    let dt = app.dt.as_secs_f32();
+    let controller = &mut app.camera.camera_controller;
+    let camera = &mut app.camera.camera_transform;
+    camera.yaw += controller.rotate_horizontal * controller.sensitivity * dt;
+    camera.pitch = (camera.pitch - controller.rotate_vertical * controller.sensitivity * dt).clamp(-SAFE_PITCH, SAFE_PITCH);
+    controller.rotate_horizontal = 0.0;
+    controller.rotate_vertical = 0.0;
+}
+
+fn look_at(camera: &mut Camera, target: Vec3) {
+    let direction = (target - camera.position).normalize_or_zero();
+    if direction == Vec3::ZERO {
+        return;
+    }
+    camera.yaw = direction.z.atan2(direction.x);
+    camera.pitch = direction.y.asin().clamp(-SAFE_PITCH, SAFE_PITCH);
+}
+
+pub fn orbit_camera(mut kind: ResMut<CameraControllerKind>, mut app: ResMut<App>) {
+    let CameraControllerKind::Orbit(orbit) = &mut *kind else {
+        return;
+    };
+    rotate_camera(&mut app);
+    let controller = &mut app.camera.camera_controller;
+    //zooming scales the distance, so it feels the same close up and far away
+    orbit.distance = (orbit.distance * (1.0 - controller.scroll * controller.sensitivity * 0.01))
+        .clamp(orbit.min_distance, orbit.max_distance);
+    controller.scroll = 0.0;
+    let camera = &mut app.camera.camera_transform;
+    camera.position = orbit.target - camera.forward() * orbit.distance;
+}
+
+pub fn follow_camera(
+    kind: Res<CameraControllerKind>,
+    mut app: ResMut<App>,
+    targets: Query<(&Instance, Option<&Parent>)>,
+    spatial_query: SpatialQuery,
+) {
+    let CameraControllerKind::Follow(follow) = &*kind else {
+        return;
+    };
+    //GlobalTransform is only brought up to date before rendering, it would trail a parented target by a frame
+    let Some(target) = world_matrix(follow.target, &targets) else {
+        return;
+    };
+    rotate_camera(&mut app);
+    app.camera.camera_controller.scroll = 0.0;
+    let dt = app.dt.as_secs_f32();
+    let pivot = target.w_axis.truncate() + Vec3::Y * follow.height;
+    let camera = &mut app.camera.camera_transform;
+    let desired = pivot - camera.forward() * follow.distance;
+    let mut position = if follow.smoothing > 0.0 {
+        //framerate independent exponential smoothing
+        camera.position.lerp(desired, 1.0 - (-follow.smoothing * dt).exp())
+    } else {
+        desired
+    };
+
+    //anything between the target and the camera pulls the camera in front of it, without smoothing so it never clips through
+    let offset = position - pivot;
+    let length = offset.length();
+    if length > 0.0 {
+        let ray = Ray { origin: pivot, direction: offset / length };
+        //colliders the pivot is inside of hit at 0, those can't be pulled in front of
+        let closest_hit = spatial_query
+            .cast_ray_all(ray, length, &QueryFilter::default().exclude(follow.target))
+            .into_iter()
+            .find(|hit| hit.distance > 0.0);
+        if let Some(hit) = closest_hit {
+            position = pivot + ray.direction * (hit.distance - follow.collision_margin).max(0.0);
+        }
+    }
+    camera.position = position;
+    look_at(camera, pivot);
+}
+
+pub fn pan_zoom_camera_2d(kind: Res<CameraControllerKind>, mut app: ResMut<App>, input: Res<InputMap>) {
+    let CameraControllerKind::PanZoom2D(pan_zoom) = &*kind else {
+        return;
+    };
+    let dt = app.dt.as_secs_f32();
+    let camera = &mut app.camera;
+    let controller = &mut camera.camera_controller;
+    let scroll = controller.scroll;
+    controller.scroll = 0.0;
+    controller.rotate_horizontal = 0.0;
+    controller.rotate_vertical = 0.0;
+    let Some(camera_2d) = &mut camera.camera_2d else {
+        return;
+    };
+    let movement = Vec2::new(input.value(CAMERA_RIGHT), input.value(CAMERA_FORWARD));
+    //pans the same amount of screen whatever the zoom, along the screen's axes even when rotated
+    let pan = Vec2::from_angle(camera_2d.rotation).rotate(movement) * controller.speed * dt / camera_2d.zoom;
+    camera_2d.position += pan;
+    camera_2d.zoom = (camera_2d.zoom * (1.0 + scroll * controller.sensitivity * 0.01)).clamp(pan_zoom.min_zoom, pan_zoom.max_zoom);
+}
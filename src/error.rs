@@ -0,0 +1,57 @@
+use thiserror::Error;
+
+//everything that can go wrong starting the engine or loading assets
+#[derive(Error, Debug)]
+pub enum VertixError {
+    #[error("failed to create window: {0}")]
+    Window(#[from] winit::error::OsError),
+    #[error("no compatible graphics adapter found")]
+    AdapterNotFound,
+    #[error("failed to create graphics device: {0}")]
+    Device(#[from] wgpu::RequestDeviceError),
+    #[error("failed to create surface: {0}")]
+    Surface(#[from] wgpu::CreateSurfaceError),
+    #[error("failed to read {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[cfg(target_arch = "wasm32")]
+    #[error("failed to fetch {path}: {source}")]
+    Request {
+        path: String,
+        source: reqwest::Error,
+    },
+    #[error("failed to decode image {path}: {source}")]
+    ImageDecode {
+        path: String,
+        source: image::ImageError,
+    },
+    #[error("failed to parse obj {path}: {source}")]
+    ObjParse {
+        path: String,
+        source: tobj::LoadError,
+    },
+    #[error("failed to parse gltf {path}: {source}")]
+    GltfParse {
+        path: String,
+        source: gltf::Error,
+    },
+    #[error("invalid input bindings {path}: {source}")]
+    InputBindings {
+        path: String,
+        source: serde_json::Error,
+    },
+    #[error("invalid input recording {path}: {source}")]
+    InvalidRecording {
+        path: String,
+        source: serde_json::Error,
+    },
+    #[error("invalid gltf {path}: {reason}")]
+    InvalidGltf {
+        path: String,
+        reason: String,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, VertixError>;
@@ -1,12 +1,11 @@
 use instant::Duration;
-use winit::{event_loop::{EventLoop, ControlFlow}, event::{Event, DeviceEvent, WindowEvent, KeyboardInput, ElementState, VirtualKeyCode}};
+use winit::{dpi::PhysicalSize, event_loop::{EventLoop, ControlFlow}, event::{Event, DeviceEvent, WindowEvent, KeyboardInput, ElementState, VirtualKeyCode}};
 
-use crate::{state::State, render::render, app_resource::App};
+use crate::{state::State, render::render};
 
 pub fn run_event_loop(
     mut state: State,
     event_loop: EventLoop<()>,
-    cam_update: Option<fn (&mut State, dt: std::time::Duration)>,
 ) {
     let mut last_render_time = instant::Instant::now();
     
@@ -17,16 +16,15 @@ pub fn run_event_loop(
             Event::DeviceEvent {
                 event: DeviceEvent::MouseMotion{ delta, },
                 .. // We're not using device_id currently
-            } => {let mut app = state.world
-                .get_resource_mut::<App>()
-                .unwrap();if app.window_events.left_held() || state.mouse_locked {
-                app.camera.camera_controller.process_mouse(delta.0, delta.1)
-            }}
+            } if !state.is_replaying() => state.mouse_motion(delta),
             Event::WindowEvent {
                 ref event,
                 window_id,
             } if window_id == state.window().id() => {
-                state.input(event);
+                //a replay stands in for the player, so live input is dropped until it ends
+                if !state.is_replaying() {
+                    state.input(event);
+                }
                 match event {
                     #[cfg(not(target_arch="wasm32"))]
                     WindowEvent::CloseRequested
@@ -39,11 +37,9 @@ pub fn run_event_loop(
                             },
                         ..
                     } => *control_flow = ControlFlow::Exit,
-                    WindowEvent::CursorMoved { position, .. } => {
-                        let mut app = state.world
-                    .get_resource_mut::<App>()
-                    .unwrap();
-                        app.cursor_move(state.window.normalize_position(position));
+                    WindowEvent::CursorMoved { position, .. } if !state.is_replaying() => {
+                        let normalized_position = state.window.as_ref().unwrap().normalize_position(position);
+                        state.cursor_moved(normalized_position);
                     }
                     WindowEvent::Resized(physical_size) => {
                         state.resize(*physical_size);
@@ -58,27 +54,28 @@ pub fn run_event_loop(
                 let now = instant::Instant::now();
                 let dt = now - last_render_time;
                 last_render_time = now;
-                state.world
-                    .get_resource_mut::<App>()
-                    .unwrap().dt = dt;
-                if cam_update.is_some() {
-                    cam_update.unwrap()(&mut state, dt);
+                if !state.run_replay_frame() {
+                    state.run_frame(dt);
                 }
-                state.update();
-                state.schedule.run(&mut state.world);
-                state.world
-                    .get_resource_mut::<App>()
-                    .unwrap().window_events.next_frame();
                 match render(&mut state) {
                     Ok(_) => {}
                     // Reconfigure the surface if it's lost or outdated
-                    Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => state.resize(state.window.size),
+                    Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => state.resize(PhysicalSize::new(state.config.width, state.config.height)),
                     // The system is out of memory, we should probably quit
                     Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
                     // We're ignoring timeouts
                     Err(wgpu::SurfaceError::Timeout) => log::warn!("Surface timeout"),
                 }
             }
+            Event::LoopDestroyed => {
+                if let Some(recorder) = state.recorder.take() {
+                    if let Some(path) = &recorder.save_path {
+                        if let Err(error) = recorder.recording.save_to_file(path) {
+                            log::error!("{error}");
+                        }
+                    }
+                }
+            }
             _ => {}
         }
     });
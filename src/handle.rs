@@ -0,0 +1,137 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    sync::{Arc, Weak},
+};
+
+use slab::Slab;
+
+//a reference counted pointer into an Assets<T>, the asset is freed once every handle to it is dropped
+pub struct Handle<T> {
+    id: usize,
+    refs: Arc<()>,
+    _marker: PhantomData<fn() -> T>,
+}
+impl<T> Handle<T> {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+    //how many handles currently point at this asset
+    pub fn ref_count(&self) -> usize {
+        Arc::strong_count(&self.refs)
+    }
+}
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            refs: self.refs.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+impl<T> Eq for Handle<T> {}
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handle")
+            .field("id", &self.id)
+            .field("ref_count", &self.ref_count())
+            .finish()
+    }
+}
+
+struct AssetEntry<T> {
+    asset: T,
+    key: Option<String>,
+    refs: Weak<()>,
+}
+
+//storage for one kind of asset, keyed assets (usually the path they were loaded from) are only ever stored once
+pub struct Assets<T> {
+    entries: Slab<AssetEntry<T>>,
+    keys: HashMap<String, usize>,
+}
+impl<T> Default for Assets<T> {
+    fn default() -> Self {
+        Self {
+            entries: Slab::new(),
+            keys: HashMap::new(),
+        }
+    }
+}
+impl<T> Assets<T> {
+    pub fn add(&mut self, asset: T) -> Handle<T> {
+        self.insert(asset, None)
+    }
+    pub fn add_keyed(&mut self, key: &str, asset: T) -> Handle<T> {
+        self.insert(asset, Some(key.to_string()))
+    }
+    fn insert(&mut self, asset: T, key: Option<String>) -> Handle<T> {
+        let refs = Arc::new(());
+        let id = self.entries.insert(AssetEntry {
+            asset,
+            key: key.clone(),
+            refs: Arc::downgrade(&refs),
+        });
+        if let Some(key) = key {
+            //an old entry under this key with no handles left is replaced, free_unused will clean it up
+            self.keys.insert(key, id);
+        }
+        Handle {
+            id,
+            refs,
+            _marker: PhantomData,
+        }
+    }
+    //a new handle to an already loaded asset, None if it was never loaded or every handle to it was dropped
+    pub fn get_keyed(&self, key: &str) -> Option<Handle<T>> {
+        let id = *self.keys.get(key)?;
+        let refs = self.entries.get(id)?.refs.upgrade()?;
+        Some(Handle {
+            id,
+            refs,
+            _marker: PhantomData,
+        })
+    }
+    pub fn get(&self, handle: &Handle<T>) -> Option<&T> {
+        self.entries.get(handle.id).map(|entry| &entry.asset)
+    }
+    pub fn get_mut(&mut self, handle: &Handle<T>) -> Option<&mut T> {
+        self.entries.get_mut(handle.id).map(|entry| &mut entry.asset)
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    //drops every asset nothing holds a handle to anymore, which frees its gpu memory
+    pub fn free_unused(&mut self) {
+        let unused: Vec<usize> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.refs.strong_count() == 0)
+            .map(|(id, _)| id)
+            .collect();
+        for id in unused {
+            let entry = self.entries.remove(id);
+            if let Some(key) = entry.key {
+                if self.keys.get(&key) == Some(&id) {
+                    self.keys.remove(&key);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use bevy_ecs::prelude::*;
+use glam::{Mat4, Vec3};
+
+use crate::instance::Instance;
+
+#[derive(Component, Copy, Clone, Debug)]
+pub struct Parent(pub Entity);
+
+#[derive(Component, Clone, Debug, Default)]
+pub struct Children(pub Vec<Entity>);
+
+//the instance's model matrix after every parent's transform has been applied
+#[derive(Component, Copy, Clone, Debug, PartialEq)]
+pub struct GlobalTransform(pub Mat4);
+impl Default for GlobalTransform {
+    fn default() -> Self {
+        GlobalTransform(Mat4::IDENTITY)
+    }
+}
+impl GlobalTransform {
+    pub fn translation(&self) -> Vec3 {
+        self.0.w_axis.truncate()
+    }
+    pub fn scale(&self) -> Vec3 {
+        self.0.to_scale_rotation_translation().0
+    }
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        self.0.transform_point3(point)
+    }
+}
+
+//child's Instance becomes relative to the parent's, a child that already had a parent is taken out of its Children first
+pub fn add_child(world: &mut World, parent: Entity, child: Entity) {
+    remove_parent(world, child);
+    world.entity_mut(child).insert(Parent(parent));
+    let mut parent_entity = world.entity_mut(parent);
+    match parent_entity.get_mut::<Children>() {
+        Some(mut children) => {
+            if !children.0.contains(&child) {
+                children.0.push(child);
+            }
+        }
+        None => {
+            parent_entity.insert(Children(vec![child]));
+        }
+    }
+}
+
+pub fn remove_parent(world: &mut World, child: Entity) {
+    let Some(Parent(parent)) = world.entity_mut(child).take::<Parent>() else {
+        return;
+    };
+    if let Some(mut children) = world.get_mut::<Children>(parent) {
+        children.0.retain(|entity| *entity != child);
+    }
+}
+
+//the instance's model matrix with every parent's applied, straight from the Parent chain
+//for systems that run before propagate_transforms has caught up this frame, None if entity has no Instance
+pub fn world_matrix(entity: Entity, nodes: &Query<(&Instance, Option<&Parent>)>) -> Option<Mat4> {
+    let (instance, mut parent) = nodes.get(entity).ok()?;
+    let mut matrix = instance.model_matrix();
+    let mut visited = vec![entity];
+    //stops at a parent without an Instance, or one it has already been through
+    while let Some(Parent(next)) = parent {
+        let Ok((instance, next_parent)) = nodes.get(*next) else {
+            break;
+        };
+        if visited.contains(next) {
+            break;
+        }
+        visited.push(*next);
+        matrix = instance.model_matrix() * matrix;
+        parent = next_parent;
+    }
+    Some(matrix)
+}
+
+//every instance gets a GlobalTransform so colliders and the gpu upload can always read one
+pub fn insert_global_transforms(
+    mut commands: Commands,
+    query: Query<(Entity, &Instance), Without<GlobalTransform>>,
+) {
+    for (entity, instance) in &query {
+        commands
+            .entity(entity)
+            .insert(GlobalTransform(instance.model_matrix()));
+    }
+}
+
+type ParentedNode = (Entity, &'static Parent, &'static Instance, Option<&'static Children>);
+type ChildNode = (&'static Parent, &'static Instance, Option<&'static Children>);
+
+pub fn propagate_transforms(
+    roots: Query<(Entity, &Instance, Option<&Children>), Without<Parent>>,
+    parented: Query<ParentedNode>,
+    parents: Query<&Children, With<Instance>>,
+    nodes: Query<ChildNode>,
+    mut globals: Query<&mut GlobalTransform>,
+) {
+    //a child whose parent got despawned, or doesn't list it anymore, would never be reached from a root, so it's treated as one
+    let orphans = parented.iter().filter_map(|(entity, Parent(parent), instance, children)| {
+        let is_reachable = parents.get(*parent).is_ok_and(|siblings| siblings.0.contains(&entity));
+        (!is_reachable).then_some((entity, instance, children))
+    });
+    for (entity, instance, children) in roots.iter().chain(orphans) {
+        let matrix = instance.model_matrix();
+        if let Ok(mut global) = globals.get_mut(entity) {
+            //only write when it moved so Changed<GlobalTransform> stays meaningful
+            global.set_if_neq(GlobalTransform(matrix));
+        }
+        if let Some(children) = children {
+            for child in &children.0 {
+                propagate_recursive(entity, *child, matrix, &nodes, &mut globals);
+            }
+        }
+    }
+}
+
+fn propagate_recursive(
+    parent: Entity,
+    entity: Entity,
+    parent_matrix: Mat4,
+    nodes: &Query<ChildNode>,
+    globals: &mut Query<&mut GlobalTransform>,
+) {
+    //a stale entry in a Children list that moved elsewhere is walked from its real parent instead
+    let Ok((Parent(real_parent), instance, children)) = nodes.get(entity) else {
+        return;
+    };
+    if *real_parent != parent {
+        return;
+    }
+    let matrix = parent_matrix * instance.model_matrix();
+    if let Ok(mut global) = globals.get_mut(entity) {
+        global.set_if_neq(GlobalTransform(matrix));
+    }
+    if let Some(children) = children {
+        for child in &children.0 {
+            propagate_recursive(entity, *child, matrix, nodes, globals);
+        }
+    }
+}
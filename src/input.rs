@@ -0,0 +1,226 @@
+use std::collections::BTreeMap;
+
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use winit::{
+    dpi::PhysicalPosition,
+    event::{ElementState, KeyboardInput, MouseButton, MouseScrollDelta, WindowEvent},
+};
+
+use crate::{
+    error::{Result, VertixError},
+    resources::ButtonState,
+};
+
+//anything a player can press, scroll counts as pressed for the frame the wheel moved
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputBinding {
+    Key(winit::event::VirtualKeyCode),
+    Mouse(MouseButton),
+    ScrollUp,
+    ScrollDown,
+}
+
+//two sets of bindings pulling a value between -1 and 1, like a and d for "move_x"
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AxisBinding {
+    pub negative: Vec<InputBinding>,
+    pub positive: Vec<InputBinding>,
+}
+impl AxisBinding {
+    pub fn new(negative: InputBinding, positive: InputBinding) -> Self {
+        Self {
+            negative: vec![negative],
+            positive: vec![positive],
+        }
+    }
+}
+
+//the part of an InputMap that gets saved, kept sorted so the file stays diffable
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Bindings {
+    pub actions: BTreeMap<String, Vec<InputBinding>>,
+    pub axes: BTreeMap<String, AxisBinding>,
+}
+
+//maps named actions and axes to inputs, so game code asks for "jump" instead of a key and players can rebind it
+#[derive(Resource, Clone, Debug, Default)]
+pub struct InputMap {
+    pub bindings: Bindings,
+    buttons: ButtonState<InputBinding>,
+    last_pressed: Option<InputBinding>, //for "press a key to bind" menus
+    scroll: f32, //lines scrolled this frame, up is positive
+}
+impl InputMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_action(mut self, action: &str, binding: InputBinding) -> Self {
+        self.bind_action(action, binding);
+        self
+    }
+    pub fn with_axis(mut self, axis: &str, binding: AxisBinding) -> Self {
+        self.bind_axis(axis, binding);
+        self
+    }
+    //adds another binding to the action, keeping the ones it already has
+    pub fn bind_action(&mut self, action: &str, binding: InputBinding) -> &mut Self {
+        let bindings = self.bindings.actions.entry(action.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+        self
+    }
+    //replaces every binding of the action, for rebinding at runtime
+    pub fn rebind_action(&mut self, action: &str, bindings: Vec<InputBinding>) -> &mut Self {
+        self.bindings.actions.insert(action.to_string(), bindings);
+        self
+    }
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) -> &mut Self {
+        self.bindings.axes.insert(axis.to_string(), binding);
+        self
+    }
+    pub fn unbind(&mut self, name: &str) {
+        self.bindings.actions.remove(name);
+        self.bindings.axes.remove(name);
+    }
+    //every action and axis bound to this input, to warn about conflicts in a rebinding menu
+    pub fn bound_to(&self, binding: InputBinding) -> Vec<&str> {
+        let actions = self
+            .bindings
+            .actions
+            .iter()
+            .filter(|(_, bindings)| bindings.contains(&binding));
+        let axes = self
+            .bindings
+            .axes
+            .iter()
+            .filter(|(_, axis)| axis.negative.contains(&binding) || axis.positive.contains(&binding));
+        actions.map(|(name, _)| name.as_str()).chain(axes.map(|(name, _)| name.as_str())).collect()
+    }
+
+    fn action_bindings(&self, name: &str) -> impl Iterator<Item = &InputBinding> {
+        let action = self.bindings.actions.get(name).into_iter().flatten();
+        let axis = self
+            .bindings
+            .axes
+            .get(name)
+            .into_iter()
+            .flat_map(|axis| axis.negative.iter().chain(axis.positive.iter()));
+        action.chain(axis)
+    }
+    //an axis counts as pressed when either direction is held
+    pub fn pressed(&self, name: &str) -> bool {
+        self.action_bindings(name).any(|binding| self.buttons.held(*binding))
+    }
+    pub fn just_pressed(&self, name: &str) -> bool {
+        self.action_bindings(name).any(|binding| self.buttons.just_pressed(*binding))
+    }
+    pub fn just_released(&self, name: &str) -> bool {
+        self.action_bindings(name).any(|binding| self.buttons.just_released(*binding))
+    }
+    //-1 to 1 for axes (further with scroll bound to them), 0 or 1 for actions
+    pub fn value(&self, name: &str) -> f32 {
+        if let Some(axis) = self.bindings.axes.get(name) {
+            let strength = |bindings: &Vec<InputBinding>| {
+                bindings.iter().map(|binding| self.binding_value(*binding)).fold(0.0, f32::max)
+            };
+            return strength(&axis.positive) - strength(&axis.negative);
+        }
+        if self.pressed(name) {
+            1.0
+        } else {
+            0.0
+        }
+    }
+    //scroll bindings give how far the wheel moved this frame, so zooming on an axis isn't capped at one step
+    fn binding_value(&self, binding: InputBinding) -> f32 {
+        match binding {
+            InputBinding::ScrollUp => self.scroll.max(0.0),
+            InputBinding::ScrollDown => (-self.scroll).max(0.0),
+            binding if self.buttons.held(binding) => 1.0,
+            _ => 0.0,
+        }
+    }
+    //the most recent input pressed, take it to bind whatever the player presses next
+    pub fn take_last_pressed(&mut self) -> Option<InputBinding> {
+        self.last_pressed.take()
+    }
+
+    //called by State::input for every window event
+    pub fn process_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(key),
+                        state,
+                        ..
+                    },
+                ..
+            } => self.set_held(InputBinding::Key(*key), *state == ElementState::Pressed),
+            WindowEvent::MouseInput { button, state, .. } => {
+                self.set_held(InputBinding::Mouse(*button), *state == ElementState::Pressed)
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, lines) => *lines,
+                    //assuming a line is about 100 pixels
+                    MouseScrollDelta::PixelDelta(PhysicalPosition { y, .. }) => *y as f32 / 100.0,
+                };
+                self.scroll += lines;
+                let binding = if lines > 0.0 {
+                    InputBinding::ScrollUp
+                } else if lines < 0.0 {
+                    InputBinding::ScrollDown
+                } else {
+                    return;
+                };
+                self.buttons.press(binding);
+                self.last_pressed = Some(binding);
+            }
+            _ => {}
+        }
+    }
+    fn set_held(&mut self, binding: InputBinding, pressed: bool) {
+        if !pressed {
+            self.buttons.release(binding);
+        } else if self.buttons.press(binding) {
+            self.last_pressed = Some(binding);
+        }
+    }
+    //called at the end of every frame, scroll is released the frame after it happened
+    pub fn next_frame(&mut self) {
+        self.buttons.next_frame();
+        self.buttons.release(InputBinding::ScrollUp);
+        self.buttons.release(InputBinding::ScrollDown);
+        self.scroll = 0.0;
+    }
+    //forgets everything held, for when the window loses focus and the releases never arrive
+    pub fn release_all(&mut self) {
+        self.buttons.release_all();
+    }
+
+    pub fn save_to_file(&self, path: &str) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.bindings).map_err(|source| VertixError::InputBindings {
+            path: path.to_string(),
+            source,
+        })?;
+        std::fs::write(path, json).map_err(|source| VertixError::Io {
+            path: path.to_string(),
+            source,
+        })
+    }
+    //replaces the bindings with the ones in the file, what's currently held stays held
+    pub fn load_from_file(&mut self, path: &str) -> Result<()> {
+        let json = std::fs::read_to_string(path).map_err(|source| VertixError::Io {
+            path: path.to_string(),
+            source,
+        })?;
+        self.bindings = serde_json::from_str(&json).map_err(|source| VertixError::InputBindings {
+            path: path.to_string(),
+            source,
+        })?;
+        Ok(())
+    }
+}
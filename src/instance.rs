@@ -1,27 +1,38 @@
-use bevy_ecs::component::Component;
+use std::collections::{HashMap, HashSet};
+
+use bevy_ecs::{component::Component, entity::Entity, query::{Changed, Or}, removal_detection::RemovedComponents, system::{Query, ResMut, Resource}};
 use glam::{Quat, Vec3, Mat4, Vec2};
 
-use crate::assets::AssetServer;
+use crate::{assets::AssetServer, app_resource::App, hierarchy::GlobalTransform};
 
 
 #[derive(Debug, Copy, Clone, Component)]
 pub struct Instance {
     pub position: Vec3,
     pub rotation: Quat,
+    pub scale: Vec3, //non uniform, applied before rotation
     pub color: [f32; 4],
     pub is_world_space: bool,
     pub prefab_index: usize,
-    pub enabled: bool
+    pub enabled: bool,
+    pub cast_shadows: bool,
+    pub receive_shadows: bool,
 }
 impl Default for Instance {
     fn default() -> Self {
-        Instance { position: Vec3::ZERO, rotation: Quat::IDENTITY, color: [1.0,1.0,1.0,1.0], is_world_space: true, prefab_index: 0, enabled: true }
+        Instance { position: Vec3::ZERO, rotation: Quat::IDENTITY, scale: Vec3::ONE, color: [1.0,1.0,1.0,1.0], is_world_space: true, prefab_index: 0, enabled: true, cast_shadows: true, receive_shadows: true }
     }
 }
 
 impl Instance {
     pub fn to_raw(&self) -> Option<InstanceRaw> {
-        if self.enabled {Some(InstanceRaw::new(self.position, self.rotation, self.color, self.is_world_space))} else {None}
+        if self.enabled {Some(InstanceRaw::new(self.position, self.rotation, self.scale, self.color, self.is_world_space).with_shadows(self.cast_shadows, self.receive_shadows))} else {None}
+    }
+    pub fn to_raw_global(&self, global_transform: &GlobalTransform) -> Option<InstanceRaw> {
+        if self.enabled {Some(InstanceRaw::from_matrix(global_transform.0, self.color, self.is_world_space).with_shadows(self.cast_shadows, self.receive_shadows))} else {None}
+    }
+    pub fn model_matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.position)
     }
     pub fn update(&self, instances: Vec<InstanceRaw>, asset_server: &mut AssetServer) {
         asset_server.prefab_slab.get_mut(self.prefab_index).unwrap().update_buffer(instances, &asset_server.queue);
@@ -34,21 +45,125 @@ impl Instance {
     }
 }
 
+type InstanceMoved = Or<(Changed<Instance>, Changed<GlobalTransform>)>;
+
+//which prefab every instance was last written to, so despawning it or moving it to another prefab clears it from the old buffer
+#[derive(Resource, Default, Debug)]
+pub struct InstancePrefabs(pub HashMap<Entity, usize>);
+
+//engine system, runs in Stage::RenderPrep every frame so changed instances get pushed to the gpu automatically
+pub fn sync_instance_buffers(
+    changed: Query<(Entity, &Instance), InstanceMoved>,
+    instances: Query<(&Instance, Option<&GlobalTransform>)>,
+    mut removed: RemovedComponents<Instance>,
+    mut instance_prefabs: ResMut<InstancePrefabs>,
+    mut app: ResMut<App>,
+) {
+    let mut dirty_prefabs = HashSet::new();
+    for entity in removed.iter() {
+        if let Some(prefab_index) = instance_prefabs.0.remove(&entity) {
+            dirty_prefabs.insert(prefab_index);
+        }
+    }
+    for (entity, instance) in &changed {
+        dirty_prefabs.insert(instance.prefab_index);
+        if let Some(old_prefab_index) = instance_prefabs.0.insert(entity, instance.prefab_index) {
+            dirty_prefabs.insert(old_prefab_index);
+        }
+    }
+    if dirty_prefabs.is_empty() {
+        return;
+    }
+    //the whole buffer gets rewritten, so every instance of a dirty prefab is needed, not just the changed ones
+    let mut grouped: HashMap<usize, Vec<InstanceRaw>> = dirty_prefabs.into_iter().map(|idx| (idx, vec![])).collect();
+    for (instance, global_transform) in &instances {
+        if let Some(instance_data) = grouped.get_mut(&instance.prefab_index) {
+            let instance_raw = match global_transform {
+                Some(global_transform) => instance.to_raw_global(global_transform),
+                None => instance.to_raw(),
+            };
+            if let Some(instance_raw) = instance_raw {
+                instance_data.push(instance_raw);
+            }
+        }
+    }
+    let asset_server = &mut app.asset_server;
+    for (prefab_index, instance_data) in grouped {
+        if let Some(prefab) = asset_server.prefab_slab.get_mut(prefab_index) {
+            //prefabs made with is_updating = false can't be written to
+            if prefab.is_updating() {
+                prefab.write_instances(instance_data, &asset_server.device, &asset_server.queue);
+            }
+        }
+    }
+}
+
+//engine system, runs in Stage::RenderPrep after sync_instance_buffers so blended instances get drawn back to front
+pub fn sort_transparent_instances(mut app: ResMut<App>) {
+    let app = &mut *app;
+    let camera_position = app.camera.camera_transform.position;
+    let asset_server = &mut app.asset_server;
+    let transparent: Vec<usize> = asset_server
+        .prefab_slab
+        .iter()
+        .filter(|(_, prefab)| prefab.is_updating() && asset_server.is_transparent(prefab))
+        .map(|(prefab_index, _)| prefab_index)
+        .collect();
+    for prefab_index in transparent {
+        asset_server.prefab_slab[prefab_index].sort_back_to_front(camera_position, &asset_server.queue);
+    }
+}
+
+const SHADOW_CAST: u32 = 1;
+const SHADOW_RECEIVE: u32 = 2;
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct InstanceRaw {
     pub model: [[f32; 4]; 4],
     color: [f32; 4],
     is_world_space: u32,
+    shadow_flags: u32, //1 casts, 2 receives
 }
 
 impl InstanceRaw {
-    pub fn new(position: Vec3, rotation: Quat, color: [f32; 4], is_world_space: bool) -> Self {
+    pub fn new(position: Vec3, rotation: Quat, scale: Vec3, color: [f32; 4], is_world_space: bool) -> Self {
+        Self::from_matrix(Mat4::from_scale_rotation_translation(scale, rotation, position), color, is_world_space)
+    }
+    pub fn from_matrix(model: Mat4, color: [f32; 4], is_world_space: bool) -> Self {
         Self {
-            model: Mat4::from_rotation_translation(rotation, position).to_cols_array_2d(),
-            color: color,
+            model: model.to_cols_array_2d(),
+            color,
             is_world_space: if is_world_space { 1 } else { 0 },
+            shadow_flags: SHADOW_CAST | SHADOW_RECEIVE,
+        }
+    }
+    pub fn position(&self) -> Vec3 {
+        Vec3::new(self.model[3][0], self.model[3][1], self.model[3][2])
+    }
+    pub fn is_world_space(&self) -> bool {
+        self.is_world_space == 1
+    }
+    pub fn is_transparent(&self) -> bool {
+        self.color[3] < 1.0
+    }
+    //bigger is further back, distance from the camera in the world and z on the screen
+    pub fn depth(&self, camera_position: Vec3) -> f32 {
+        if self.is_world_space() {
+            self.position().distance_squared(camera_position)
+        } else {
+            self.position().z
+        }
+    }
+    pub fn with_shadows(mut self, cast_shadows: bool, receive_shadows: bool) -> Self {
+        self.shadow_flags = 0;
+        if cast_shadows {
+            self.shadow_flags |= SHADOW_CAST;
         }
+        if receive_shadows {
+            self.shadow_flags |= SHADOW_RECEIVE;
+        }
+        self
     }
     pub fn desc() -> wgpu::VertexBufferLayout<'static> {
         use std::mem;
@@ -93,6 +208,11 @@ impl InstanceRaw {
                     shader_location: 10,
                     format: wgpu::VertexFormat::Uint32,
                 },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 21]>() as wgpu::BufferAddress,
+                    shader_location: 11,
+                    format: wgpu::VertexFormat::Uint32,
+                },
             ],
         }
     }
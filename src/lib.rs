@@ -13,22 +13,48 @@ pub mod resources;
 pub mod instance;
 pub mod assets;
 pub mod app_resource;
+pub mod hierarchy;
+pub mod builder;
+pub mod plugins;
+pub mod error;
+pub mod handle;
+pub mod lighting;
+pub mod shadows;
+pub mod controllers;
+pub mod input;
+pub mod replay;
 mod render;
 pub mod collision {
     pub mod structs_3d;
     pub mod structs_2d;
     pub mod collision_fns_3d;
+    pub mod spatial_query;
+    pub mod broadphase;
+    pub mod contacts;
 }
 pub mod prelude {
     pub use crate::{
         instance::{Instance,InstanceRaw},
         event::{run_event_loop,delta_time_to_seconds},
-        state::State,
+        state::{State, Stage},
+        builder::{AppBuilder, Plugin},
+        window::{WindowConfig, WindowMode},
+        plugins::{FlyCameraPlugin, CameraControllerPlugin, UiPlugin, DebugDrawPlugin, CollisionDebugPlugin, CollisionPlugin},
+        controllers::{CameraControllerKind, OrbitController, FollowController, PanZoom2DController},
         structs::Vertex,
-        camera::Camera,
+        model::AlphaMode,
+        camera::{Camera, Camera2D, OrthographicScaling, PerspectiveSettings},
+        error::VertixError,
+        handle::Handle,
+        lighting::{Lights, DirectionalLight, PointLight, SpotLight},
+        shadows::ShadowSettings,
+        input::{InputMap, InputBinding, AxisBinding},
+        replay::InputRecording,
+        collision::spatial_query::{SpatialQuery, QueryFilter, CollisionLayers, RaycastHit},
+        collision::contacts::{Sensor, Contacts, CollisionStarted, CollisionEnded, TriggerEntered, TriggerExited},
         resources::*
     };
     #[cfg(target_arch = "wasm32")]
     pub use wasm_bindgen::prelude::*;
-    pub use winit::event::{ElementState, KeyboardInput, VirtualKeyCode};
+    pub use winit::event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode};
 }
\ No newline at end of file
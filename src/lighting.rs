@@ -0,0 +1,190 @@
+use bevy_ecs::system::Resource;
+use glam::Vec3;
+use wgpu::{util::DeviceExt, BindGroup, BindGroupLayout, Buffer, Device, Queue};
+
+//the uniform has fixed size arrays so it also works on webgl, lights past these are ignored
+pub const MAX_DIRECTIONAL_LIGHTS: usize = 4;
+pub const MAX_POINT_LIGHTS: usize = 16;
+pub const MAX_SPOT_LIGHTS: usize = 8;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DirectionalLight {
+    pub direction: Vec3, //the way the light travels, so (0, -1, 0) shines straight down
+    pub color: Vec3,
+    pub intensity: f32,
+    pub cast_shadows: bool, //only the first shadow casting directional light gets shadows, see ShadowSettings
+}
+//point lights don't cast shadows, use a spot light for that
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PointLight {
+    pub position: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub range: f32, //no light reaches past this distance
+}
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SpotLight {
+    pub position: Vec3,
+    pub direction: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub range: f32,
+    pub inner_angle: f32, //radians from the center where the light starts fading out
+    pub outer_angle: f32, //radians from the center where the light is gone
+    pub cast_shadows: bool,
+}
+
+//edit this resource to change the scene's lighting, it's uploaded every frame
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct Lights {
+    pub ambient_color: Vec3,
+    pub ambient_intensity: f32,
+    pub directional: Vec<DirectionalLight>,
+    pub point: Vec<PointLight>,
+    pub spot: Vec<SpotLight>,
+    pub shininess: f32, //blinn-phong specular exponent
+    pub specular_strength: f32,
+}
+impl Default for Lights {
+    //a dim ambient light and a sun coming down at an angle
+    fn default() -> Self {
+        Self {
+            ambient_color: Vec3::ONE,
+            ambient_intensity: 0.2,
+            directional: vec![DirectionalLight {
+                direction: Vec3::new(-0.3, -1.0, -0.5).normalize(),
+                color: Vec3::ONE,
+                intensity: 0.8,
+                cast_shadows: true,
+            }],
+            point: vec![],
+            spot: vec![],
+            shininess: 32.0,
+            specular_strength: 0.3,
+        }
+    }
+}
+impl Lights {
+    //everything fully lit, the way meshes looked before lighting
+    pub fn unlit() -> Self {
+        Self {
+            ambient_intensity: 1.0,
+            directional: vec![],
+            specular_strength: 0.0,
+            ..Default::default()
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct DirectionalLightRaw {
+    direction: [f32; 4],
+    color: [f32; 4], //premultiplied by intensity
+}
+#[repr(C)]
+#[derive(Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct PointLightRaw {
+    position: [f32; 4], //range in w
+    color: [f32; 4],
+}
+#[repr(C)]
+#[derive(Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct SpotLightRaw {
+    position: [f32; 4], //range in w
+    direction: [f32; 4], //cos of the outer angle in w
+    color: [f32; 4], //cos of the inner angle in w
+}
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightsUniform {
+    ambient: [f32; 4],
+    counts: [u32; 4], //directional, point, spot
+    specular: [f32; 4], //shininess, strength
+    directional: [DirectionalLightRaw; MAX_DIRECTIONAL_LIGHTS],
+    point: [PointLightRaw; MAX_POINT_LIGHTS],
+    spot: [SpotLightRaw; MAX_SPOT_LIGHTS],
+}
+impl LightsUniform {
+    pub fn new(lights: &Lights) -> Self {
+        let mut directional = [DirectionalLightRaw::default(); MAX_DIRECTIONAL_LIGHTS];
+        for (raw, light) in directional.iter_mut().zip(&lights.directional) {
+            *raw = DirectionalLightRaw {
+                direction: light.direction.normalize_or_zero().extend(0.0).into(),
+                color: (light.color * light.intensity).extend(1.0).into(),
+            };
+        }
+        let mut point = [PointLightRaw::default(); MAX_POINT_LIGHTS];
+        for (raw, light) in point.iter_mut().zip(&lights.point) {
+            *raw = PointLightRaw {
+                position: light.position.extend(light.range).into(),
+                color: (light.color * light.intensity).extend(1.0).into(),
+            };
+        }
+        let mut spot = [SpotLightRaw::default(); MAX_SPOT_LIGHTS];
+        for (raw, light) in spot.iter_mut().zip(&lights.spot) {
+            *raw = SpotLightRaw {
+                position: light.position.extend(light.range).into(),
+                direction: light.direction.normalize_or_zero().extend(light.outer_angle.cos()).into(),
+                color: (light.color * light.intensity).extend(light.inner_angle.cos()).into(),
+            };
+        }
+        Self {
+            ambient: (lights.ambient_color * lights.ambient_intensity).extend(1.0).into(),
+            counts: [
+                lights.directional.len().min(MAX_DIRECTIONAL_LIGHTS) as u32,
+                lights.point.len().min(MAX_POINT_LIGHTS) as u32,
+                lights.spot.len().min(MAX_SPOT_LIGHTS) as u32,
+                0,
+            ],
+            specular: [lights.shininess, lights.specular_strength, 0.0, 0.0],
+            directional,
+            point,
+            spot,
+        }
+    }
+}
+
+pub struct LightsStruct {
+    pub buffer: Buffer,
+    pub bind_group_layout: BindGroupLayout,
+    pub bind_group: BindGroup,
+}
+impl LightsStruct {
+    pub fn new(device: &Device, lights: &Lights) -> Self {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Lights Buffer"),
+            contents: bytemuck::cast_slice(&[LightsUniform::new(lights)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("lights_bind_group_layout"),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("lights_bind_group"),
+        });
+        Self {
+            buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+    pub fn write(&self, queue: &Queue, lights: &Lights) {
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[LightsUniform::new(lights)]));
+    }
+}
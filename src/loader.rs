@@ -1,9 +1,11 @@
 use std::io::{BufReader, Cursor};
 
+use base64::Engine;
 use cfg_if::cfg_if;
+use glam::{Mat3, Mat4, Vec3};
 use wgpu::util::DeviceExt;
 
-use crate::{model, texture, prelude::Vertex};
+use crate::{model, texture, prelude::Vertex, structs::compute_tangents, error::{Result, VertixError}};
 
 #[cfg(target_arch = "wasm32")]
 fn format_url(file_name: &str) -> reqwest::Url {
@@ -17,39 +19,47 @@ fn format_url(file_name: &str) -> reqwest::Url {
     base.join(file_name).unwrap()
 }
 
-pub async fn load_string(file_name: &str, build_path: &str) -> anyhow::Result<String> {
+pub async fn load_string(file_name: &str, build_path: &str) -> Result<String> {
     cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
             let url = format_url(file_name);
+            let request_error = |source| VertixError::Request { path: file_name.to_string(), source };
             let txt = reqwest::get(url)
-                .await?
+                .await
+                .map_err(request_error)?
                 .text()
-                .await?;
+                .await
+                .map_err(request_error)?;
         } else {
             let path = std::path::Path::new(build_path)
                 .join("res")
                 .join(file_name);
-            let txt = std::fs::read_to_string(path)?;
+            let txt = std::fs::read_to_string(&path)
+                .map_err(|source| VertixError::Io { path: path.display().to_string(), source })?;
         }
     }
 
     Ok(txt)
 }
 
-pub async fn load_binary(file_name: &str,build_path: &str) -> anyhow::Result<Vec<u8>> {
+pub async fn load_binary(file_name: &str,build_path: &str) -> Result<Vec<u8>> {
     cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
             let url = format_url(file_name);
+            let request_error = |source| VertixError::Request { path: file_name.to_string(), source };
             let data = reqwest::get(url)
-                .await?
+                .await
+                .map_err(request_error)?
                 .bytes()
-                .await?
+                .await
+                .map_err(request_error)?
                 .to_vec();
         } else {
             let path = std::path::Path::new(build_path)
                 .join("res")
                 .join(file_name);
-            let data = std::fs::read(path)?;
+            let data = std::fs::read(&path)
+                .map_err(|source| VertixError::Io { path: path.display().to_string(), source })?;
         }
     }
 
@@ -62,18 +72,41 @@ pub async fn load_texture(
     device: &wgpu::Device,
     queue: &wgpu::Queue,
     filter_type: wgpu::FilterMode
-) -> anyhow::Result<texture::Texture> {
+) -> Result<texture::Texture> {
     let data = load_binary(file_name, build_path).await?;
     texture::Texture::from_bytes(device, queue, &data, file_name, filter_type)
 }
 
+//normal maps hold directions, not colors, so they skip the srgb conversion
+pub async fn load_normal_texture(
+    file_name: &str,
+    build_path: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    filter_type: wgpu::FilterMode
+) -> Result<texture::Texture> {
+    let data = load_binary(file_name, build_path).await?;
+    let img = image::load_from_memory(&data)
+        .map_err(|source| VertixError::ImageDecode { path: file_name.to_string(), source })?;
+    Ok(texture::Texture::from_image_linear(device, queue, &img, Some(file_name), filter_type))
+}
+
+//a normal map that points straight out of the surface, bound for materials without one
+pub fn flat_normal_texture(device: &wgpu::Device, queue: &wgpu::Queue) -> texture::Texture {
+    let img = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(1, 1, image::Rgba([128, 128, 255, 255])));
+    texture::Texture::from_image_linear(device, queue, &img, Some("flat normal map"), wgpu::FilterMode::Nearest)
+}
+
 pub async fn load_model(
     file_name: &str,
     build_path: &str,
     device: &wgpu::Device,
     queue: &wgpu::Queue,
     layout: &wgpu::BindGroupLayout,
-) -> anyhow::Result<model::Model> {
+) -> Result<model::Model> {
+    if file_name.ends_with(".gltf") || file_name.ends_with(".glb") {
+        return load_gltf(file_name, build_path, device, queue, layout).await;
+    }
     let obj_text = load_string(file_name, build_path).await?;
     let obj_cursor = Cursor::new(obj_text);
     let mut obj_reader = BufReader::new(obj_cursor);
@@ -86,46 +119,57 @@ pub async fn load_model(
             ..Default::default()
         },
         |p| async move {
-            let mat_text = load_string(&p, build_path).await.unwrap();
-            tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(mat_text)))
+            match load_string(&p, build_path).await {
+                Ok(mat_text) => tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(mat_text))),
+                Err(error) => {
+                    log::warn!("{}", error);
+                    Err(tobj::LoadError::OpenFileFailed)
+                }
+            }
         },
     )
-    .await?;
+    .await
+    .map_err(|source| VertixError::ObjParse { path: file_name.to_string(), source })?;
+    let obj_materials = obj_materials
+        .map_err(|source| VertixError::ObjParse { path: file_name.to_string(), source })?;
 
+    let flat_normal = flat_normal_texture(device, queue);
     let mut materials = Vec::new();
-    for m in obj_materials? {
+    for m in obj_materials {
         let diffuse_texture = load_texture(&m.diffuse_texture, build_path, device, queue, wgpu::FilterMode::Linear).await?;
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
-                },
-            ],
-            label: None,
-        });
-
-        materials.push(model::Material {
-            bind_group,
-        })
+        let normal_texture = if m.normal_texture.is_empty() {
+            None
+        } else {
+            Some(load_normal_texture(&m.normal_texture, build_path, device, queue, wgpu::FilterMode::Linear).await?)
+        };
+        let alpha_mode = if m.dissolve < 1.0 {
+            model::AlphaMode::Blend
+        } else {
+            model::AlphaMode::from_texture(&diffuse_texture)
+        };
+        let normal = normal_texture.as_ref().unwrap_or(&flat_normal);
+        let mut material = model::Material::new(device, layout, &diffuse_texture, normal, None, alpha_mode);
+        material.normal_texture = normal_texture;
+        materials.push(material)
     }
 
     let meshes = models
         .into_iter()
         .map(|m| {
-            let vertices = (0..m.mesh.positions.len() / 3)
-                .map(|i| Vertex::new([
+            let mut vertices = (0..m.mesh.positions.len() / 3)
+                .map(|i| Vertex::with_normal([
                     m.mesh.positions[i * 3],
                     m.mesh.positions[i * 3 + 1],
                     m.mesh.positions[i * 3 + 2],
                 ],
-                [m.mesh.texcoords[i * 2], m.mesh.texcoords[i * 2 + 1]]) )
+                [m.mesh.texcoords[i * 2], m.mesh.texcoords[i * 2 + 1]],
+                //objs without normals stay unlit
+                match m.mesh.normals.get(i * 3..i * 3 + 3) {
+                    Some(normal) => [normal[0], normal[1], normal[2]],
+                    None => [0.0; 3],
+                }))
                 .collect::<Vec<_>>();
+            compute_tangents(&mut vertices, &m.mesh.indices);
 
             let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some(&format!("{:?} Vertex Buffer", file_name)),
@@ -149,3 +193,320 @@ pub async fn load_model(
 
     Ok(model::Model { meshes, materials })
 }
+
+//.gltf with external or embedded (data uri) buffers and images, or a single .glb
+//node transforms are baked into the vertices, so the model draws like an obj would
+//a triangle primitive with its node transforms baked into the vertices
+pub struct GltfPrimitive {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub material: Option<usize>, //None for the gltf default material
+}
+
+pub async fn load_gltf(
+    file_name: &str,
+    build_path: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layout: &wgpu::BindGroupLayout,
+) -> Result<model::Model> {
+    let invalid = |reason: &str| VertixError::InvalidGltf { path: file_name.to_string(), reason: reason.to_string() };
+    let (gltf, buffers) = read_gltf(file_name, build_path).await?;
+
+    let mut images = Vec::new();
+    for image in gltf.images() {
+        let bytes = match image.source() {
+            gltf::image::Source::View { view, .. } => buffers[view.buffer().index()]
+                .get(view.offset()..view.offset() + view.length())
+                .ok_or_else(|| invalid(&format!("image {} is outside its buffer", image.index())))?
+                .to_vec(),
+            gltf::image::Source::Uri { uri, .. } => load_gltf_uri(uri, file_name, build_path).await?,
+        };
+        let image = image::load_from_memory(&bytes).map_err(|source| VertixError::ImageDecode {
+            path: format!("{} image {}", file_name, image.index()),
+            source,
+        })?;
+        images.push(image);
+    }
+
+    let flat_normal = flat_normal_texture(device, queue);
+    let mut materials = gltf
+        .materials()
+        .map(|material| gltf_material(&material, &images, &flat_normal, device, queue, layout))
+        .collect::<Vec<_>>();
+    //primitives without a material get the gltf default, plain white
+    let default_material = materials.len();
+    let white = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(1, 1, image::Rgba([255; 4])));
+    let white = texture::Texture::from_image(device, queue, &white, Some("gltf default material"), wgpu::FilterMode::Nearest);
+    let mut white_material = model::Material::new(device, layout, &white, &flat_normal, None, model::AlphaMode::Opaque);
+    white_material.metallic_factor = 1.0;
+    materials.push(white_material);
+
+    let meshes = gltf_primitives(&gltf, &buffers, file_name)?
+        .into_iter()
+        .map(|primitive| {
+            let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("{:?} Vertex Buffer", file_name)),
+                contents: bytemuck::cast_slice(&primitive.vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+            let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("{:?} Index Buffer", file_name)),
+                contents: bytemuck::cast_slice(&primitive.indices),
+                usage: wgpu::BufferUsages::INDEX,
+            });
+            model::Mesh {
+                vertex_buffer,
+                index_buffer,
+                num_elements: primitive.indices.len() as u32,
+                material: primitive.material.unwrap_or(default_material),
+            }
+        })
+        .collect();
+
+    Ok(model::Model { meshes, materials })
+}
+
+//just the geometry of a gltf or glb, without touching the gpu
+pub async fn load_gltf_primitives(file_name: &str, build_path: &str) -> Result<Vec<GltfPrimitive>> {
+    let (gltf, buffers) = read_gltf(file_name, build_path).await?;
+    gltf_primitives(&gltf, &buffers, file_name)
+}
+
+//the document and every buffer it uses, from data uris, files next to it or the glb's binary chunk
+async fn read_gltf(file_name: &str, build_path: &str) -> Result<(gltf::Gltf, Vec<Vec<u8>>)> {
+    let invalid = |reason: &str| VertixError::InvalidGltf { path: file_name.to_string(), reason: reason.to_string() };
+    let data = load_binary(file_name, build_path).await?;
+    let gltf = gltf::Gltf::from_slice(&data)
+        .map_err(|source| VertixError::GltfParse { path: file_name.to_string(), source })?;
+
+    let mut buffers = Vec::new();
+    for buffer in gltf.buffers() {
+        let bytes = match buffer.source() {
+            gltf::buffer::Source::Bin => gltf.blob.clone().ok_or_else(|| invalid("glb has no binary chunk"))?,
+            gltf::buffer::Source::Uri(uri) => load_gltf_uri(uri, file_name, build_path).await?,
+        };
+        if bytes.len() < buffer.length() {
+            return Err(invalid(&format!("buffer {} is shorter than its byteLength", buffer.index())));
+        }
+        buffers.push(bytes);
+    }
+    Ok((gltf, buffers))
+}
+
+fn gltf_primitives(gltf: &gltf::Gltf, buffers: &[Vec<u8>], file_name: &str) -> Result<Vec<GltfPrimitive>> {
+    let invalid = |reason: &str| VertixError::InvalidGltf { path: file_name.to_string(), reason: reason.to_string() };
+    let scene = gltf
+        .default_scene()
+        .or_else(|| gltf.scenes().next())
+        .ok_or_else(|| invalid("no scenes"))?;
+    let mut primitives = Vec::new();
+    let mut nodes = scene.nodes().map(|node| (node, Mat4::IDENTITY)).collect::<Vec<_>>();
+    while let Some((node, parent_matrix)) = nodes.pop() {
+        let matrix = parent_matrix * Mat4::from_cols_array_2d(&node.transform().matrix());
+        let normal_matrix = Mat3::from_mat4(matrix).inverse().transpose();
+        if let Some(mesh) = node.mesh() {
+            for primitive in mesh.primitives() {
+                if primitive.mode() != gltf::mesh::Mode::Triangles {
+                    log::warn!("{}: skipping {:?} primitive, only triangles are supported", file_name, primitive.mode());
+                    continue;
+                }
+                let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+                let Some(positions) = reader.read_positions() else {
+                    continue;
+                };
+                let mut tex_coords = reader.read_tex_coords(0).map(|tex_coords| tex_coords.into_f32());
+                let mut normals = reader.read_normals();
+                let mut tangents = reader.read_tangents();
+                let mut vertices = positions
+                    .map(|position| {
+                        let tex_coords = tex_coords.as_mut().and_then(Iterator::next).unwrap_or([0.0, 0.0]);
+                        let normal = match normals.as_mut().and_then(Iterator::next) {
+                            Some(normal) => (normal_matrix * Vec3::from(normal)).normalize_or_zero(),
+                            None => Vec3::ZERO,
+                        };
+                        let mut vertex = Vertex::with_normal(matrix.transform_point3(position.into()).into(), tex_coords, normal.into());
+                        //w is the handedness of the bitangent
+                        if let Some([x, y, z, w]) = tangents.as_mut().and_then(Iterator::next) {
+                            let tangent = matrix.transform_vector3(Vec3::new(x, y, z)).normalize_or_zero();
+                            vertex.tangent = tangent.into();
+                            vertex.bitangent = (normal.cross(tangent) * w).into();
+                        }
+                        vertex
+                    })
+                    .collect::<Vec<_>>();
+                let mut indices = match reader.read_indices() {
+                    Some(indices) => indices.into_u32().collect::<Vec<_>>(),
+                    None => (0..vertices.len() as u32).collect(),
+                };
+                //a mirrored node flips the winding, flip it back so culling still works
+                if matrix.determinant() < 0.0 {
+                    for triangle in indices.chunks_exact_mut(3) {
+                        triangle.swap(1, 2);
+                    }
+                }
+                if reader.read_tangents().is_none() {
+                    compute_tangents(&mut vertices, &indices);
+                }
+                primitives.push(GltfPrimitive {
+                    vertices,
+                    indices,
+                    material: primitive.material().index(),
+                });
+            }
+        }
+        nodes.extend(node.children().map(|child| (child, matrix)));
+    }
+    Ok(primitives)
+}
+
+//uris are either base64 data or a path relative to the gltf file
+async fn load_gltf_uri(uri: &str, file_name: &str, build_path: &str) -> Result<Vec<u8>> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let invalid = |reason: &str| VertixError::InvalidGltf { path: file_name.to_string(), reason: reason.to_string() };
+        let (header, payload) = data.split_once(',').ok_or_else(|| invalid("malformed data uri"))?;
+        if !header.ends_with(";base64") {
+            return Err(invalid("only base64 data uris are supported"));
+        }
+        return base64::engine::general_purpose::STANDARD
+            .decode(payload)
+            .map_err(|_| invalid("data uri is not valid base64"));
+    }
+    let relative_path = match file_name.rsplit_once('/') {
+        Some((folder, _)) => format!("{}/{}", folder, uri),
+        None => uri.to_string(),
+    };
+    load_binary(&relative_path, build_path).await
+}
+
+fn gltf_material(
+    material: &gltf::Material,
+    images: &[image::DynamicImage],
+    flat_normal: &texture::Texture,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layout: &wgpu::BindGroupLayout,
+) -> model::Material {
+    let label = material.name().unwrap_or("gltf material");
+    let pbr = material.pbr_metallic_roughness();
+    let base_color_factor = pbr.base_color_factor();
+    //the base color factor is multiplied into the texture here, so the shader doesn't need it
+    let base_color = match pbr.base_color_texture() {
+        Some(info) => {
+            let mut rgba = images[info.texture().source().index()].to_rgba8();
+            if base_color_factor != [1.0; 4] {
+                for pixel in rgba.pixels_mut() {
+                    *pixel = image::Rgba(scale_srgb(pixel.0, base_color_factor));
+                }
+            }
+            (rgba, gltf_filter(&info.texture()))
+        }
+        None => (
+            image::RgbaImage::from_pixel(1, 1, image::Rgba(scale_srgb([255; 4], base_color_factor))),
+            wgpu::FilterMode::Nearest,
+        ),
+    };
+    let diffuse_texture = texture::Texture::from_image(
+        device,
+        queue,
+        &image::DynamicImage::ImageRgba8(base_color.0),
+        Some(label),
+        base_color.1,
+    );
+    let linear_texture = |texture: gltf::Texture| {
+        texture::Texture::from_image_linear(
+            device,
+            queue,
+            &images[texture.source().index()],
+            Some(label),
+            gltf_filter(&texture),
+        )
+    };
+    let normal_texture = material.normal_texture().map(|normal| linear_texture(normal.texture()));
+    let alpha_mode = match material.alpha_mode() {
+        gltf::material::AlphaMode::Opaque => model::AlphaMode::Opaque,
+        //0.5 is the gltf default cutoff
+        gltf::material::AlphaMode::Mask => model::AlphaMode::Mask(material.alpha_cutoff().unwrap_or(0.5)),
+        gltf::material::AlphaMode::Blend => model::AlphaMode::Blend,
+    };
+    let normal = normal_texture.as_ref().unwrap_or(flat_normal);
+    let mut model_material = model::Material::new(device, layout, &diffuse_texture, normal, None, alpha_mode);
+    model_material.base_color_factor = base_color_factor;
+    model_material.metallic_factor = pbr.metallic_factor();
+    model_material.roughness_factor = pbr.roughness_factor();
+    model_material.normal_texture = normal_texture;
+    model_material.metallic_roughness_texture = pbr
+        .metallic_roughness_texture()
+        .map(|metallic_roughness| linear_texture(metallic_roughness.texture()));
+    model_material
+}
+
+fn gltf_filter(texture: &gltf::Texture) -> wgpu::FilterMode {
+    match texture.sampler().mag_filter() {
+        Some(gltf::texture::MagFilter::Nearest) => wgpu::FilterMode::Nearest,
+        _ => wgpu::FilterMode::Linear,
+    }
+}
+
+//multiplies an srgb color by a linear factor, alpha is already linear
+fn scale_srgb(color: [u8; 4], factor: [f32; 4]) -> [u8; 4] {
+    let mut scaled = [0.0; 4];
+    for channel in 0..4 {
+        let value = color[channel] as f32 / 255.0;
+        scaled[channel] = if channel == 3 {
+            value * factor[3]
+        } else {
+            linear_to_srgb(srgb_to_linear(value) * factor[channel])
+        };
+    }
+    scaled.map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn material_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    diffuse_texture: &texture::Texture,
+    normal_texture: &texture::Texture,
+    uniform_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&normal_texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Sampler(&normal_texture.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+        ],
+        label: None,
+    })
+}
@@ -1,6 +1,97 @@
 use std::ops::Range;
+
+use wgpu::util::DeviceExt;
+
+use crate::{handle::Handle, loader::material_bind_group, texture::Texture};
+
+//how a material's alpha is used, Blend and Premultiplied are drawn in the sorted transparent pass
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum AlphaMode {
+    #[default]
+    Opaque,
+    Mask(f32), //fragments with less alpha than the cutoff are discarded, the rest are opaque
+    Blend, //straight alpha, the usual for pngs
+    Premultiplied, //the texture's color is already multiplied by its alpha
+}
+impl AlphaMode {
+    //Opaque and Mask write depth and are drawn before everything else
+    pub fn is_blended(self) -> bool {
+        matches!(self, AlphaMode::Blend | AlphaMode::Premultiplied)
+    }
+    fn cutoff(self) -> f32 {
+        match self {
+            AlphaMode::Mask(cutoff) => cutoff,
+            _ => 0.0,
+        }
+    }
+    //Blend for textures with any see through texels, Opaque otherwise
+    pub fn from_texture(texture: &Texture) -> Self {
+        if texture.has_alpha {
+            AlphaMode::Blend
+        } else {
+            AlphaMode::Opaque
+        }
+    }
+}
+
+//what the shader reads from a material besides its textures
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct MaterialUniform {
+    settings: [f32; 4], //alpha cutoff
+}
+impl MaterialUniform {
+    fn new(alpha_mode: AlphaMode) -> Self {
+        Self { settings: [alpha_mode.cutoff(), 0.0, 0.0, 0.0] }
+    }
+}
+
 pub struct Material {
     pub bind_group: wgpu::BindGroup,
+    uniform_buffer: wgpu::Buffer,
+    pub diffuse_texture: Option<Handle<Texture>>, //keeps the texture alive, None when a model owns it
+    pub base_color_factor: [f32; 4], //already multiplied into the diffuse texture
+    //from gltf, kept for a pbr shader, the blinn-phong one doesn't read them yet
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub normal_texture: Option<Texture>,
+    pub metallic_roughness_texture: Option<Texture>, //roughness in g, metallic in b like gltf
+    alpha_mode: AlphaMode, //the cutoff lives in the uniform too, so it's only changed through set_alpha_mode
+}
+impl Material {
+    //a plain diffuse material, not metallic and fully rough
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        diffuse: &Texture,
+        normal: &Texture,
+        diffuse_texture: Option<Handle<Texture>>,
+        alpha_mode: AlphaMode,
+    ) -> Self {
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Material Buffer"),
+            contents: bytemuck::cast_slice(&[MaterialUniform::new(alpha_mode)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        Self {
+            bind_group: material_bind_group(device, layout, diffuse, normal, &uniform_buffer),
+            uniform_buffer,
+            diffuse_texture,
+            base_color_factor: [1.0; 4],
+            metallic_factor: 0.0,
+            roughness_factor: 1.0,
+            normal_texture: None,
+            metallic_roughness_texture: None,
+            alpha_mode,
+        }
+    }
+    pub fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+    pub fn set_alpha_mode(&mut self, queue: &wgpu::Queue, alpha_mode: AlphaMode) {
+        self.alpha_mode = alpha_mode;
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[MaterialUniform::new(alpha_mode)]));
+    }
 }
 
 pub struct Mesh {
@@ -0,0 +1,265 @@
+use bevy_ecs::prelude::*;
+use glam::{Vec2, Vec3};
+
+use crate::{
+    app_resource::App,
+    builder::Plugin,
+    camera::default_3d_cam,
+    controllers::{bind_camera_axes, follow_camera, orbit_camera, pan_zoom_camera_2d, CameraControllerKind},
+    collision::{
+        broadphase::{update_broadphase, Broadphase},
+        contacts::{detect_collisions, init_collision_events, update_collision_events},
+        spatial_query::{collider_transform, Has2DCollider},
+        structs_2d::{Box2D, Circle, Shape2D},
+        structs_3d::{Collider3D, Ray},
+    },
+    handle::Handle,
+    hierarchy::GlobalTransform,
+    input::InputMap,
+    model::Material,
+    prefabs::Prefab,
+    prelude::{Instance, Vertex},
+    shapes::line_3d,
+    state::{Stage, State},
+    structs::MeshType,
+};
+
+//free flying camera on the camera axes of the InputMap, the old default_3d_cam
+pub struct FlyCameraPlugin {
+    pub speed: f32,
+    pub sensitivity: f32,
+}
+impl Default for FlyCameraPlugin {
+    fn default() -> Self {
+        Self { speed: 5.0, sensitivity: 2.0 }
+    }
+}
+impl Plugin for FlyCameraPlugin {
+    fn build(&self, state: &mut State) {
+        CameraControllerPlugin {
+            kind: CameraControllerKind::FreeFly,
+            speed: self.speed,
+            sensitivity: self.sensitivity,
+        }
+        .build(state);
+    }
+}
+
+//adds every camera controller, the CameraControllerKind resource picks which one runs
+pub struct CameraControllerPlugin {
+    pub kind: CameraControllerKind,
+    pub speed: f32,
+    pub sensitivity: f32,
+}
+impl Plugin for CameraControllerPlugin {
+    fn build(&self, state: &mut State) {
+        let mut app = state.world.get_resource_mut::<App>().unwrap();
+        app.camera.camera_controller.speed = self.speed;
+        app.camera.camera_controller.sensitivity = self.sensitivity;
+        bind_camera_axes(&mut state.world.resource_mut::<InputMap>());
+        state.world.insert_resource(self.kind.clone());
+        state.add_systems(Stage::Update, (default_3d_cam, orbit_camera, pan_zoom_camera_2d));
+        //after the game's own systems, so a followed entity has already moved this frame
+        state.add_systems(Stage::PostUpdate, follow_camera);
+    }
+}
+
+//the broadphase and the contact events, without it SpatialQuery checks every collider and nothing sends CollisionStarted and friends
+pub struct CollisionPlugin;
+impl Plugin for CollisionPlugin {
+    fn build(&self, state: &mut State) {
+        state.world.insert_resource(Broadphase::default());
+        init_collision_events(&mut state.world);
+        //first thing every frame, so queries and collision events see where colliders ended up last frame
+        state.add_systems(Stage::PreUpdate, (update_collision_events, update_broadphase, detect_collisions).chain());
+    }
+}
+
+//screen space buttons, any entity with an Instance, a Box2D and an Interaction gets its Interaction updated every frame
+pub struct UiPlugin;
+impl Plugin for UiPlugin {
+    fn build(&self, state: &mut State) {
+        state.add_systems(Stage::PreUpdate, update_ui_interactions);
+    }
+}
+#[derive(Component, Copy, Clone, Debug, Default, PartialEq)]
+pub enum Interaction {
+    #[default]
+    None,
+    Hovered,
+    Clicked,
+}
+pub fn update_ui_interactions(
+    app: Res<App>,
+    mut query: Query<(&Instance, &Box2D, &mut Interaction)>,
+) {
+    for (instance, collider, mut interaction) in &mut query {
+        let new_interaction = if !collider.check_collision(instance, &app.window_events) {
+            Interaction::None
+        } else if app.window_events.left_clicked() {
+            Interaction::Clicked
+        } else {
+            Interaction::Hovered
+        };
+        interaction.set_if_neq(new_interaction);
+    }
+}
+
+//immediate mode lines, push them into the DebugDraw resource every frame you want them drawn
+pub struct DebugDrawPlugin;
+impl Plugin for DebugDrawPlugin {
+    fn build(&self, state: &mut State) {
+        let mut app = state.world.get_resource_mut::<App>().unwrap();
+        let material = app.asset_server.compile_color_material([255, 255, 255, 255]);
+        state.world.insert_resource(DebugDraw {
+            lines: vec![],
+            material,
+            prefab_idx: None,
+        });
+        state.add_systems(Stage::RenderPrep, draw_debug_lines);
+    }
+}
+#[derive(Resource)]
+pub struct DebugDraw {
+    lines: Vec<(Vec3, Vec3)>,
+    material: Handle<Material>,
+    prefab_idx: Option<usize>,
+}
+impl DebugDraw {
+    pub fn line(&mut self, start: Vec3, end: Vec3) {
+        self.lines.push((start, end));
+    }
+    pub fn ray(&mut self, ray: Ray, length: f32) {
+        self.line(ray.origin, ray.origin + ray.direction * length);
+    }
+}
+//the lines go into one mesh whose buffers are rewritten every frame, and only reallocated when they have to grow
+pub fn draw_debug_lines(mut debug_draw: ResMut<DebugDraw>, app: ResMut<App>) {
+    let mut vertices: Vec<Vertex> = vec![];
+    let mut indices: Vec<u32> = vec![];
+    //last frame's lines get thrown away, only the ones pushed this frame are drawn
+    for (start, end) in debug_draw.lines.drain(..) {
+        let (line_vertices, line_indices) = line_3d(start, end);
+        let base_index = vertices.len() as u32;
+        vertices.extend(line_vertices);
+        indices.extend(line_indices.into_iter().map(|index| index + base_index));
+    }
+    let asset_server = &mut app.into_inner().asset_server;
+    let (device, queue, prefab_slab) = (&asset_server.device, &asset_server.queue, &mut asset_server.prefab_slab);
+    let debug_mesh = debug_draw.prefab_idx.and_then(|prefab_idx| match prefab_slab.get_mut(prefab_idx) {
+        Some(Prefab { mesh_type: MeshType::Mesh(mesh), .. }) if mesh.material == debug_draw.material => Some(mesh),
+        _ => None,
+    });
+    match debug_mesh {
+        Some(mesh) => {
+            write_growable(device, queue, &mut mesh.vertex_buffer, bytemuck::cast_slice(&vertices), wgpu::BufferUsages::VERTEX);
+            write_growable(device, queue, &mut mesh.index_buffer, bytemuck::cast_slice(&indices), wgpu::BufferUsages::INDEX);
+            mesh.num_elements = indices.len() as u32;
+        }
+        //built the first time there's something to draw, or again if the prefab got cleared away
+        None if !indices.is_empty() => {
+            let mut instance = Instance { ..Default::default() };
+            asset_server.build_mesh((vertices, indices), vec![&mut instance], &debug_draw.material, false);
+            debug_draw.prefab_idx = Some(instance.prefab_index);
+        }
+        None => {}
+    }
+}
+//rewrites the buffer in place, or swaps it for one at least twice as big when the contents don't fit
+fn write_growable(device: &wgpu::Device, queue: &wgpu::Queue, buffer: &mut wgpu::Buffer, contents: &[u8], usage: wgpu::BufferUsages) {
+    let size = contents.len() as u64;
+    if size > buffer.size() || !buffer.usage().contains(wgpu::BufferUsages::COPY_DST) {
+        *buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Debug Line Buffer"),
+            size: size.max(buffer.size() * 2).next_power_of_two(),
+            usage: usage | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+    }
+    if size > 0 {
+        queue.write_buffer(buffer, 0, contents);
+    }
+}
+
+//outlines every enabled Collider3D, Box2D and Circle in the world with debug lines, adds DebugDrawPlugin if it isn't there yet
+pub struct CollisionDebugPlugin;
+impl Plugin for CollisionDebugPlugin {
+    fn build(&self, state: &mut State) {
+        if !state.world.contains_resource::<DebugDraw>() {
+            DebugDrawPlugin.build(state);
+        }
+        state.add_systems(Stage::PostUpdate, draw_collider_bounds);
+    }
+}
+//lines around a circle of the given radius, facing along normal
+const CIRCLE_SEGMENTS: usize = 16;
+fn circle_lines(debug_draw: &mut DebugDraw, center: Vec3, radius: f32, normal: Vec3) {
+    let (right, up) = normal.any_orthonormal_pair();
+    let point = |i: usize| {
+        let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+        center + (right * angle.cos() + up * angle.sin()) * radius
+    };
+    for i in 0..CIRCLE_SEGMENTS {
+        debug_draw.line(point(i), point(i + 1));
+    }
+}
+pub fn draw_collider_bounds(
+    mut debug_draw: ResMut<DebugDraw>,
+    colliders: Query<(&Collider3D, Option<&GlobalTransform>, Option<&Instance>)>,
+    colliders_2d: Query<(Option<&Box2D>, Option<&Circle>, &Instance), Has2DCollider>,
+) {
+    for (collider, global_transform, instance) in &colliders {
+        if instance.is_some_and(|instance| !instance.enabled) {
+            continue;
+        }
+        let transform = collider_transform(global_transform, instance);
+        match collider {
+            Collider3D::OBB(obb) => {
+                let corner = |i: usize| {
+                    let pick = |bit: usize, min: f32, max: f32| if i & bit == 0 { min } else { max };
+                    let local = Vec3::new(
+                        pick(1, obb.aabb_min.x, obb.aabb_max.x),
+                        pick(2, obb.aabb_min.y, obb.aabb_max.y),
+                        pick(4, obb.aabb_min.z, obb.aabb_max.z),
+                    );
+                    transform.transform_point(local)
+                };
+                //corners are numbered by which axes are at their max, edges join corners one axis apart
+                for i in 0..8 {
+                    for bit in [1, 2, 4] {
+                        if i & bit == 0 {
+                            debug_draw.line(corner(i), corner(i | bit));
+                        }
+                    }
+                }
+            }
+            Collider3D::Sphere(sphere) => {
+                let center = sphere.world_center(Some(&transform));
+                let radius = sphere.world_radius(Some(&transform));
+                for normal in [Vec3::X, Vec3::Y, Vec3::Z] {
+                    circle_lines(&mut debug_draw, center, radius, normal);
+                }
+            }
+            Collider3D::Ray(ray) => debug_draw.ray(*ray, 1.0),
+        }
+    }
+    //screen space ones are in a different space than the lines
+    for (box_2d, circle, instance) in &colliders_2d {
+        if !instance.enabled || !instance.is_world_space {
+            continue;
+        }
+        let shapes = box_2d.and_then(|box_2d| box_2d.shape(instance)).into_iter().chain(circle.and_then(|circle| circle.shape(instance)));
+        for shape in shapes {
+            let z = instance.position.z;
+            match shape {
+                Shape2D::Box { min, max } => {
+                    let corners = [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)];
+                    for i in 0..4 {
+                        debug_draw.line(corners[i].extend(z), corners[(i + 1) % 4].extend(z));
+                    }
+                }
+                Shape2D::Circle { center, radius } => circle_lines(&mut debug_draw, center.extend(z), radius, Vec3::Z),
+            }
+        }
+    }
+}
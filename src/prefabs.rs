@@ -1,23 +1,72 @@
 use bevy_ecs::component::Component;
-use wgpu::{Buffer, Queue};
+use glam::Vec3;
+use wgpu::{Buffer, Device, Queue, util::DeviceExt};
 use crate::{prelude::InstanceRaw, structs::MeshType};
 #[derive(Component)]
 pub struct Prefab {
     pub length: u32,
     pub buffer: Buffer,
     pub mesh_type: MeshType,
+    pub instances: Vec<InstanceRaw>, //cpu copy of the buffer, used to sort transparent instances
 }
 impl Prefab {
-    pub fn new(buffer: Buffer, mesh_type: MeshType, length: u32) -> Self {
+    pub fn new(buffer: Buffer, mesh_type: MeshType, instances: Vec<InstanceRaw>) -> Self {
         Self {
             buffer,
             mesh_type,
-            length,
+            length: instances.len() as u32,
+            instances,
         }
     }
     pub fn update_buffer(&mut self, instances: Vec<InstanceRaw>, queue: &Queue) {
         //optional, must call after you change position or rotation to update it in buffer, also when you add an instance
         queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&instances));
         self.length = instances.len() as u32;
+        self.instances = instances;
     }
-}
\ No newline at end of file
+    pub fn is_updating(&self) -> bool {
+        self.buffer.usage().contains(wgpu::BufferUsages::COPY_DST)
+    }
+    pub fn write_instances(&mut self, instances: Vec<InstanceRaw>, device: &Device, queue: &Queue) {
+        //same as update_buffer, but grows the buffer if there are more instances than it can hold
+        let contents: &[u8] = bytemuck::cast_slice(&instances);
+        if contents.len() as u64 > self.buffer.size() {
+            self.buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Instance Buffer"),
+                contents,
+                usage: self.buffer.usage(),
+            });
+            self.length = instances.len() as u32;
+            self.instances = instances;
+        } else {
+            self.update_buffer(instances, queue);
+        }
+    }
+    //any instance with a color alpha below 1 makes the whole prefab go through the transparent pass
+    pub fn has_transparent_instances(&self) -> bool {
+        self.instances.iter().any(|instance| instance.is_transparent())
+    }
+    //where the prefab lands in the back to front order, world space before screen space then furthest instance first
+    pub fn depth(&self, camera_position: Vec3) -> (bool, f32) {
+        let is_screen_space = !self.instances.iter().any(|instance| instance.is_world_space());
+        let depth = self
+            .instances
+            .iter()
+            .map(|instance| instance.depth(camera_position))
+            .fold(f32::MIN, f32::max);
+        (is_screen_space, depth)
+    }
+    //reorders the buffer so the furthest instances get drawn first, only rewrites it when the order changed
+    pub fn sort_back_to_front(&mut self, camera_position: Vec3, queue: &Queue) {
+        let sort_key = |instance: &InstanceRaw| (!instance.is_world_space(), -instance.depth(camera_position));
+        let is_sorted = self
+            .instances
+            .windows(2)
+            .all(|pair| sort_key(&pair[0]).partial_cmp(&sort_key(&pair[1])) != Some(std::cmp::Ordering::Greater));
+        if is_sorted {
+            return;
+        }
+        self.instances.sort_by(|a, b| sort_key(a).partial_cmp(&sort_key(b)).unwrap_or(std::cmp::Ordering::Equal));
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&self.instances));
+    }
+}
@@ -1,11 +1,17 @@
-use std::iter;
-use crate::{state::State, structs::MeshType, model::DrawModel, app_resource::App};
+use std::{cmp::Ordering, iter};
+use crate::{state::State, structs::MeshType, model::{AlphaMode, Material}, app_resource::App, assets::AssetServer, prefabs::Prefab};
 
 pub fn render(state: &mut State) -> Result<(), wgpu::SurfaceError> {
-    let output = state.window.surface.get_current_texture()?;
+    let output = state.window.as_ref().expect("headless State has no surface, use render_to_image").surface.get_current_texture()?;
     let view = output
         .texture
         .create_view(&wgpu::TextureViewDescriptor::default());
+    draw_frame(state, &view);
+    output.present();
+
+    Ok(())
+}
+pub fn draw_frame(state: &State, view: &wgpu::TextureView) {
     let app = state.world
     .get_resource::<App>()
     .unwrap();
@@ -14,12 +20,13 @@ pub fn render(state: &mut State) -> Result<(), wgpu::SurfaceError> {
         .create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Render Encoder"),
         });
+    state.shadows.render(&mut encoder, &app.asset_server);
 
     {
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Render Pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view,
+                view,
                 resolve_target: None,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(wgpu::Color {
@@ -34,42 +41,111 @@ pub fn render(state: &mut State) -> Result<(), wgpu::SurfaceError> {
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
                 view: &state.depth_texture.view,
                 depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(1.0),
+                    load: wgpu::LoadOp::Clear(app.camera.projection.depth_clear_value()),
                     store: true,
                 }),
                 stencil_ops: None,
             }),
         });
-        render_pass.set_pipeline(&state.render_pipeline);
         render_pass.set_bind_group(1, &app.camera.bind_group, &[]);
-        for (_, game_object) in &app.asset_server.prefab_slab {
-            render_pass.set_vertex_buffer(1, game_object.buffer.slice(..));
-            match &game_object.mesh_type {
-                MeshType::Model(model) => {
-                    render_pass.draw_model_instanced(&model, 0..game_object.length);
-                }
-                MeshType::Mesh(mesh) => {
-                    render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-                    render_pass
-                        .set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-                    let material = &app.asset_server.material_assets[mesh.material_idx];
-                    render_pass.set_bind_group(0, &material.bind_group, &[]);
-                    render_pass.draw_indexed(0..mesh.num_elements, 0, 0..game_object.length);
-                }
-                MeshType::Sprite(material_idx) => {
-                    render_pass.set_vertex_buffer(0, app.asset_server.sprite_mesh.vertex_buffer.slice(..));
-                    render_pass
-                        .set_index_buffer(app.asset_server.sprite_mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-                    let material = &app.asset_server.material_assets[*material_idx];
-                    render_pass.set_bind_group(0, &material.bind_group, &[]);
-                    render_pass.draw_indexed(0..app.asset_server.sprite_mesh.num_elements, 0, 0..game_object.length);
+        render_pass.set_bind_group(2, &state.lights.bind_group, &[]);
+        render_pass.set_bind_group(3, &state.shadows.bind_group, &[]);
+        let camera_position = app.camera.camera_transform.position;
+        let mut transparent_draws = vec![];
+        render_pass.set_pipeline(&state.render_pipeline);
+        for (_, prefab) in &app.asset_server.prefab_slab {
+            if prefab.length == 0 {
+                continue;
+            }
+            let is_transparent = prefab.has_transparent_instances();
+            let depth = prefab.depth(camera_position);
+            for draw in prefab_draws(&app.asset_server, prefab) {
+                let alpha_mode = match draw.material.alpha_mode() {
+                    AlphaMode::Opaque | AlphaMode::Mask(_) if is_transparent => AlphaMode::Blend,
+                    alpha_mode => alpha_mode,
+                };
+                if !alpha_mode.is_blended() {
+                    draw.record(&mut render_pass);
+                } else {
+                    transparent_draws.push((depth, alpha_mode, draw));
                 }
             }
         }
+        //opaque things are all drawn, so blended ones only need to be layered over each other back to front
+        transparent_draws.sort_by(|(a, _, _), (b, _, _)| {
+            a.0.cmp(&b.0).then(b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal))
+        });
+        for (_, alpha_mode, draw) in transparent_draws {
+            render_pass.set_pipeline(match alpha_mode {
+                AlphaMode::Premultiplied => &state.premultiplied_pipeline,
+                _ => &state.transparent_pipeline,
+            });
+            draw.record(&mut render_pass);
+        }
     }
 
     app.asset_server.queue.submit(iter::once(encoder.finish()));
-    output.present();
+}
 
-    Ok(())
+//one indexed draw of a prefab's instances
+struct PrefabDraw<'a> {
+    prefab: &'a Prefab,
+    vertex_buffer: &'a wgpu::Buffer,
+    index_buffer: &'a wgpu::Buffer,
+    num_elements: u32,
+    material: &'a Material,
+}
+impl<'a> PrefabDraw<'a> {
+    fn record(&self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.prefab.buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.set_bind_group(0, &self.material.bind_group, &[]);
+        render_pass.draw_indexed(0..self.num_elements, 0, 0..self.prefab.length);
+    }
+}
+//a draw per mesh, models can mix opaque and blended materials
+fn prefab_draws<'a>(asset_server: &'a AssetServer, prefab: &'a Prefab) -> Vec<PrefabDraw<'a>> {
+    match &prefab.mesh_type {
+        MeshType::Model(model) => {
+            let Some(model) = asset_server.models.get(model) else {
+                return vec![];
+            };
+            model
+                .meshes
+                .iter()
+                .map(|mesh| PrefabDraw {
+                    prefab,
+                    vertex_buffer: &mesh.vertex_buffer,
+                    index_buffer: &mesh.index_buffer,
+                    num_elements: mesh.num_elements,
+                    material: &model.materials[mesh.material],
+                })
+                .collect()
+        }
+        MeshType::Mesh(mesh) => asset_server
+            .materials
+            .get(&mesh.material)
+            .map(|material| PrefabDraw {
+                prefab,
+                vertex_buffer: &mesh.vertex_buffer,
+                index_buffer: &mesh.index_buffer,
+                num_elements: mesh.num_elements,
+                material,
+            })
+            .into_iter()
+            .collect(),
+        MeshType::Sprite(material) => asset_server
+            .materials
+            .get(material)
+            .map(|material| PrefabDraw {
+                prefab,
+                vertex_buffer: &asset_server.sprite_mesh.vertex_buffer,
+                index_buffer: &asset_server.sprite_mesh.index_buffer,
+                num_elements: asset_server.sprite_mesh.num_elements,
+                material,
+            })
+            .into_iter()
+            .collect(),
+    }
 }
@@ -0,0 +1,186 @@
+use instant::Duration;
+use serde::{Deserialize, Serialize};
+use winit::{
+    dpi::PhysicalPosition,
+    event::{DeviceId, ElementState, KeyboardInput, MouseButton, MouseScrollDelta, WindowEvent},
+};
+
+use crate::error::{Result, VertixError};
+
+//one input the engine reacted to, with what it needs to be fed back in exactly the same way
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RecordedInput {
+    Key {
+        scancode: u32,
+        key: winit::event::VirtualKeyCode,
+        state: ElementState,
+    },
+    MouseButton {
+        button: MouseButton,
+        state: ElementState,
+    },
+    MouseWheel(MouseScrollDelta),
+    CursorMoved(PhysicalPosition<f32>), //already normalized, so a replay doesn't need the window
+    MouseMotion(f64, f64),
+    Focused(bool),
+    Resized(u32, u32),
+}
+impl RecordedInput {
+    //the window events State::input handles, everything else isn't recorded
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        scancode,
+                        virtual_keycode: Some(key),
+                        state,
+                        ..
+                    },
+                ..
+            } => Some(Self::Key {
+                scancode: *scancode,
+                key: *key,
+                state: *state,
+            }),
+            WindowEvent::MouseInput { button, state, .. } => Some(Self::MouseButton {
+                button: *button,
+                state: *state,
+            }),
+            WindowEvent::MouseWheel { delta, .. } => Some(Self::MouseWheel(*delta)),
+            WindowEvent::Focused(focused) => Some(Self::Focused(*focused)),
+            _ => None,
+        }
+    }
+    //builds the window event back for State::input, None for inputs that don't come from one
+    #[allow(deprecated)] //the modifiers fields, they have to be filled in even though nothing reads them
+    pub fn to_window_event(&self) -> Option<WindowEvent<'static>> {
+        //safe as long as the id is only compared and never passed to winit, which the engine never does
+        let device_id = unsafe { DeviceId::dummy() };
+        match self {
+            Self::Key { scancode, key, state } => Some(WindowEvent::KeyboardInput {
+                device_id,
+                input: KeyboardInput {
+                    scancode: *scancode,
+                    state: *state,
+                    virtual_keycode: Some(*key),
+                    modifiers: Default::default(),
+                },
+                is_synthetic: false,
+            }),
+            Self::MouseButton { button, state } => Some(WindowEvent::MouseInput {
+                device_id,
+                state: *state,
+                button: *button,
+                modifiers: Default::default(),
+            }),
+            Self::MouseWheel(delta) => Some(WindowEvent::MouseWheel {
+                device_id,
+                delta: *delta,
+                phase: winit::event::TouchPhase::Moved,
+                modifiers: Default::default(),
+            }),
+            Self::Focused(focused) => Some(WindowEvent::Focused(*focused)),
+            Self::CursorMoved(_) | Self::MouseMotion(..) | Self::Resized(..) => None,
+        }
+    }
+}
+
+//everything that reached the engine between two frames, and the dt the frame ran with
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    pub dt: Duration,
+    pub inputs: Vec<RecordedInput>,
+}
+
+//a whole play session, feed it to State::start_replay or State::run_replay to play it back frame for frame
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputRecording {
+    pub width: u32, //the size it was recorded at, build a headless State this size to replay it
+    pub height: u32,
+    #[serde(default)]
+    pub fixed_accumulator: Duration, //time already put towards the next fixed step when recording started
+    pub frames: Vec<RecordedFrame>,
+}
+impl InputRecording {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            fixed_accumulator: Duration::ZERO,
+            frames: vec![],
+        }
+    }
+    pub fn duration(&self) -> Duration {
+        self.frames.iter().map(|frame| frame.dt).sum()
+    }
+    pub fn save_to_file(&self, path: &str) -> Result<()> {
+        let json = serde_json::to_string(self).map_err(|source| VertixError::InvalidRecording {
+            path: path.to_string(),
+            source,
+        })?;
+        std::fs::write(path, json).map_err(|source| VertixError::Io {
+            path: path.to_string(),
+            source,
+        })
+    }
+    pub fn load_from_file(path: &str) -> Result<Self> {
+        let json = std::fs::read_to_string(path).map_err(|source| VertixError::Io {
+            path: path.to_string(),
+            source,
+        })?;
+        serde_json::from_str(&json).map_err(|source| VertixError::InvalidRecording {
+            path: path.to_string(),
+            source,
+        })
+    }
+}
+
+//collects inputs while the game runs, State::run_frame closes each frame
+#[derive(Clone, Debug)]
+pub struct InputRecorder {
+    pub recording: InputRecording,
+    pending: Vec<RecordedInput>, //inputs since the last frame
+    pub save_path: Option<String>, //written when the event loop exits
+}
+impl InputRecorder {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            recording: InputRecording::new(width, height),
+            pending: vec![],
+            save_path: None,
+        }
+    }
+    pub fn record(&mut self, input: RecordedInput) {
+        self.pending.push(input);
+    }
+    pub fn end_frame(&mut self, dt: Duration) {
+        self.recording.frames.push(RecordedFrame {
+            dt,
+            inputs: std::mem::take(&mut self.pending),
+        });
+    }
+}
+
+//plays a recording back one frame at a time
+#[derive(Clone, Debug)]
+pub struct InputReplay {
+    pub recording: InputRecording,
+    next_frame: usize,
+}
+impl InputReplay {
+    pub fn new(recording: InputRecording) -> Self {
+        Self {
+            recording,
+            next_frame: 0,
+        }
+    }
+    pub fn next_frame(&mut self) -> Option<RecordedFrame> {
+        let frame = self.recording.frames.get(self.next_frame).cloned();
+        self.next_frame += 1;
+        frame
+    }
+    pub fn is_finished(&self) -> bool {
+        self.next_frame >= self.recording.frames.len()
+    }
+}
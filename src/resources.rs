@@ -1,17 +1,81 @@
-use glam::{Vec4, Vec3, Mat4, vec3};
-use winit::{dpi::PhysicalPosition, event::{VirtualKeyCode, ElementState}};
+use std::{collections::HashSet, hash::Hash};
 
-use crate::camera::{Camera, Projection};
+use glam::{Vec2, Vec3};
+use winit::{dpi::PhysicalPosition, event::{VirtualKeyCode, ElementState, MouseButton}};
+
+use crate::camera::CameraStruct;
 pub struct WindowEvents {
-    pub keys_pressed: Vec<(VirtualKeyCode, ElementState)>,
+    pub keys_pressed: Vec<(VirtualKeyCode, ElementState)>, //only the key events from this frame
+    pub keys: ButtonState<VirtualKeyCode>,
+    pub mouse_buttons: ButtonState<MouseButton>,
     pub screen_mouse_pos: PhysicalPosition<f32>,
     pub world_mouse_pos: PhysicalPosition<f32>,
     pub left_mouse: MouseClickType,
     pub right_mouse: MouseClickType,
     pub middle_mouse: MouseClickType,
     pub aspect_ratio: f32,
+    pub mouse_ray_origin: Vec3, //the camera for perspective projections, the cursor on the near plane for orthographic ones
     pub mouse_ray_direction: Vec3,
 }
+//what's held down across frames, and what changed this frame
+#[derive(Clone, Debug)]
+pub struct ButtonState<T> {
+    held: HashSet<T>,
+    just_pressed: HashSet<T>,
+    just_released: HashSet<T>,
+}
+impl<T> Default for ButtonState<T> {
+    fn default() -> Self {
+        Self {
+            held: HashSet::new(),
+            just_pressed: HashSet::new(),
+            just_released: HashSet::new(),
+        }
+    }
+}
+impl<T: Copy + Eq + Hash> ButtonState<T> {
+    //returns false for key repeats, which send more presses while the key stays held
+    pub fn press(&mut self, button: T) -> bool {
+        let is_new = self.held.insert(button);
+        if is_new {
+            self.just_pressed.insert(button);
+        }
+        is_new
+    }
+    pub fn release(&mut self, button: T) {
+        if self.held.remove(&button) {
+            self.just_released.insert(button);
+        }
+    }
+    pub fn set(&mut self, button: T, state: ElementState) {
+        match state {
+            ElementState::Pressed => {
+                self.press(button);
+            }
+            ElementState::Released => self.release(button),
+        }
+    }
+    pub fn held(&self, button: T) -> bool {
+        self.held.contains(&button)
+    }
+    pub fn just_pressed(&self, button: T) -> bool {
+        self.just_pressed.contains(&button)
+    }
+    pub fn just_released(&self, button: T) -> bool {
+        self.just_released.contains(&button)
+    }
+    pub fn all_held(&self) -> impl Iterator<Item = &T> {
+        self.held.iter()
+    }
+    //releases everything, for when the window loses focus and the release events never arrive
+    pub fn release_all(&mut self) {
+        self.just_released.extend(self.held.drain());
+    }
+    pub fn next_frame(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+    }
+}
 pub enum MouseClickType{
     Clicked,
     Held,
@@ -19,17 +83,28 @@ pub enum MouseClickType{
     NotHeld
 }
 impl WindowEvents {
+    //nothing held and the cursor in the middle of a window this big
+    pub fn new(width: u32, height: u32) -> Self {
+        WindowEvents {
+            keys_pressed: vec![],
+            keys: ButtonState::default(),
+            mouse_buttons: ButtonState::default(),
+            screen_mouse_pos: PhysicalPosition { x: 0.0, y: 0.0 },
+            world_mouse_pos: PhysicalPosition { x: 0.0, y: 0.0 },
+            left_mouse: MouseClickType::NotHeld,
+            right_mouse: MouseClickType::NotHeld,
+            middle_mouse: MouseClickType::NotHeld,
+            aspect_ratio: (width as f32) / (height as f32),
+            mouse_ray_origin: Vec3::ZERO,
+            mouse_ray_direction: Vec3::ZERO,
+        }
+    }
     pub fn is_key_pressed(&self, key: VirtualKeyCode, press_type: Option<ElementState>) -> bool {
         for (key_pressed, pressed_type) in &self.keys_pressed{
             if key_pressed == &key {
-                if press_type.is_none() {
-                    return true
-                }
-                else{
-                    if pressed_type == &press_type.unwrap() {
-                        return true
-                    }
-                    return false
+                return match press_type {
+                    None => true,
+                    Some(press_type) => pressed_type == &press_type,
                 }
             }
         }
@@ -47,46 +122,97 @@ impl WindowEvents {
         }
         false
     }
-    pub fn next_frame(&mut self) {
-        self.keys_pressed = vec![];
-        match self.left_mouse {
-            MouseClickType::Clicked => self.left_mouse = MouseClickType::Held,
-            MouseClickType::Released => self.left_mouse = MouseClickType::NotHeld,
-            _ => {}
+    pub fn key_held(&self, key: VirtualKeyCode) -> bool {
+        self.keys.held(key)
+    }
+    pub fn key_just_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.keys.just_pressed(key)
+    }
+    pub fn key_just_released(&self, key: VirtualKeyCode) -> bool {
+        self.keys.just_released(key)
+    }
+    pub fn mouse_held(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.held(button)
+    }
+    pub fn mouse_just_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.just_pressed(button)
+    }
+    pub fn mouse_just_released(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.just_released(button)
+    }
+    //called by State::input
+    pub fn process_key(&mut self, key: VirtualKeyCode, state: ElementState) {
+        self.keys_pressed.push((key, state));
+        self.keys.set(key, state);
+    }
+    pub fn process_mouse_button(&mut self, button: MouseButton, state: ElementState) {
+        self.mouse_buttons.set(button, state);
+        let click_type = if state == ElementState::Pressed {
+            MouseClickType::Clicked
+        } else {
+            MouseClickType::Released
+        };
+        match button {
+            MouseButton::Left => self.left_mouse = click_type,
+            MouseButton::Right => self.right_mouse = click_type,
+            MouseButton::Middle => self.middle_mouse = click_type,
+            MouseButton::Other(_) => {}
         }
-        match self.right_mouse {
-            MouseClickType::Clicked => self.left_mouse = MouseClickType::Held,
-            MouseClickType::Released => self.left_mouse = MouseClickType::NotHeld,
-            _ => {}
+    }
+    pub fn release_all(&mut self) {
+        self.keys.release_all();
+        self.mouse_buttons.release_all();
+        for mouse in [&mut self.left_mouse, &mut self.right_mouse, &mut self.middle_mouse] {
+            if let MouseClickType::Clicked | MouseClickType::Held = mouse {
+                *mouse = MouseClickType::Released;
+            }
         }
-        match self.middle_mouse {
-            MouseClickType::Clicked => self.left_mouse = MouseClickType::Held,
-            MouseClickType::Released => self.left_mouse = MouseClickType::NotHeld,
-            _ => {}
+    }
+    pub fn next_frame(&mut self) {
+        self.keys_pressed = vec![];
+        self.keys.next_frame();
+        self.mouse_buttons.next_frame();
+        for mouse in [&mut self.left_mouse, &mut self.right_mouse, &mut self.middle_mouse] {
+            match mouse {
+                MouseClickType::Clicked => *mouse = MouseClickType::Held,
+                MouseClickType::Released => *mouse = MouseClickType::NotHeld,
+                _ => {}
+            }
         }
     }
-    pub fn update_mouse_pos(&mut self, normalized_mouse_pos: PhysicalPosition<f32>, camera_transform: &Camera){
+    pub fn update_mouse_pos(&mut self, normalized_mouse_pos: PhysicalPosition<f32>, camera: &CameraStruct){
         self.screen_mouse_pos = normalized_mouse_pos;
-        self.world_mouse_pos = PhysicalPosition::new(normalized_mouse_pos.x + camera_transform.position.x, normalized_mouse_pos.y + camera_transform.position.y);
+        self.update_world_mouse_pos(camera);
     }
-    pub fn update_mouse_pos_with_cam_if_cam_2d(&mut self, camera_transform: &mut Camera){ //only works if cam is in 2d and isnt rotated
+    //call after the camera moves, the mouse can point at a new place in the world without moving itself
+    pub fn update_world_mouse_pos(&mut self, camera: &CameraStruct){
         let normalized_mouse_pos = self.screen_mouse_pos;
-        self.world_mouse_pos = PhysicalPosition::new(normalized_mouse_pos.x + camera_transform.position.x, normalized_mouse_pos.y + camera_transform.position.y);
+        if camera.camera_2d.is_some() {
+            //straight back through the 2d camera, so zoom and rotation are accounted for
+            let world_pos = camera.view_proj().inverse().project_point3(Vec3::new(normalized_mouse_pos.x, normalized_mouse_pos.y, 0.0));
+            self.world_mouse_pos = PhysicalPosition::new(world_pos.x, world_pos.y);
+        } else {
+            let camera_transform = &camera.camera_transform;
+            self.world_mouse_pos = PhysicalPosition::new(normalized_mouse_pos.x + camera_transform.position.x, normalized_mouse_pos.y + camera_transform.position.y);
+        }
     }
     pub fn update_aspect_ratio(&mut self, width: u32, height: u32) {
         self.aspect_ratio = (width as f32) /(height as f32);
     }
-    pub fn calculate_mouse_dir(&mut self, proj_matrix: &Projection, view_matrix: &[[f32; 4]; 4]) {
-        let ray_clip_start = Vec4::new(-self.screen_mouse_pos.x, -self.screen_mouse_pos.y, -1.0, 1.0); //screen mosue pos is (-1,-1) to (1,1)
-        let ray_clip_end = Vec4::new(-self.screen_mouse_pos.x, -self.screen_mouse_pos.y, 0.0, 1.0); //screen mosue pos is (-1,-1) to (1,1)
-        let view_mat = Mat4::from_cols_array_2d(&view_matrix);
-        let inversed_view_and_proj_m = (proj_matrix.calc_matrix() * view_mat).inverse();
-        let mut ray_wor_start = inversed_view_and_proj_m * ray_clip_start;
-        ray_wor_start /= ray_wor_start.w;
-        let mut ray_wor_end = inversed_view_and_proj_m * ray_clip_end;
-        ray_wor_end /= ray_wor_end.w;
-        let ray_dir_world = (ray_wor_end - ray_wor_start).normalize();
-        self.mouse_ray_direction = vec3(-ray_dir_world.x, -ray_dir_world.y, -ray_dir_world.z);
+    //the cursor in the space instances that aren't in world space are placed in, see camera::screen_projection
+    pub fn screen_space_mouse_pos(&self) -> Vec2 {
+        Vec2::new(self.screen_mouse_pos.x * self.aspect_ratio, self.screen_mouse_pos.y)
+    }
+    //call after the camera or the cursor moves, keeps mouse_ray_origin and mouse_ray_direction pointing through the cursor
+    pub fn calculate_mouse_dir(&mut self, camera: &CameraStruct) {
+        let cursor = Vec2::new(self.screen_mouse_pos.x, self.screen_mouse_pos.y);
+        let (near_point, direction) = camera.projection.unproject(camera.view_proj(), cursor);
+        self.mouse_ray_direction = direction;
+        self.mouse_ray_origin = if camera.projection.is_orthographic() {
+            near_point
+        } else {
+            camera.camera_transform.position
+        };
     }
 }
 pub struct Timer {
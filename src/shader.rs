@@ -1,15 +1,16 @@
 use wgpu::{RenderPipeline, Device, SurfaceConfiguration};
 
-use crate::{texture::Texture, prelude::{Vertex, InstanceRaw}};
+use crate::{texture::Texture, prelude::{Vertex, InstanceRaw}, model::AlphaMode};
 
-pub fn make_shader(shader: &str, device: &Device, render_pipeline_layout:wgpu::PipelineLayout, config: &SurfaceConfiguration) -> RenderPipeline{
+//one pipeline per alpha mode, Mask shares the opaque one, the blended ones test against the depth buffer but don't write to it
+pub fn make_shader(shader: &str, device: &Device, render_pipeline_layout: &wgpu::PipelineLayout, config: &SurfaceConfiguration, alpha_mode: AlphaMode, reverse_z: bool) -> RenderPipeline{
     let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
         label: Some(shader),
         source: wgpu::ShaderSource::Wgsl(shader.into()),
     });
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: Some("Render Pipeline"),
-        layout: Some(&render_pipeline_layout),
+        layout: Some(render_pipeline_layout),
         vertex: wgpu::VertexState {
             module: &shader,
             entry_point: "vs_main",
@@ -17,12 +18,16 @@ pub fn make_shader(shader: &str, device: &Device, render_pipeline_layout:wgpu::P
         },
         fragment: Some(wgpu::FragmentState {
             module: &shader,
-            entry_point: "fs_main",
+            entry_point: match alpha_mode {
+                AlphaMode::Premultiplied => "fs_premultiplied",
+                _ => "fs_main",
+            },
             targets: &[Some(wgpu::ColorTargetState {
                 format: config.format,
-                blend: Some(wgpu::BlendState {
-                    color: wgpu::BlendComponent::REPLACE,
-                    alpha: wgpu::BlendComponent::REPLACE,
+                blend: Some(match alpha_mode {
+                    AlphaMode::Opaque | AlphaMode::Mask(_) => wgpu::BlendState::REPLACE,
+                    AlphaMode::Blend => wgpu::BlendState::ALPHA_BLENDING,
+                    AlphaMode::Premultiplied => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
                 }),
                 write_mask: wgpu::ColorWrites::ALL,
             })],
@@ -42,8 +47,8 @@ pub fn make_shader(shader: &str, device: &Device, render_pipeline_layout:wgpu::P
         },
         depth_stencil: Some(wgpu::DepthStencilState {
             format: Texture::DEPTH_FORMAT,
-            depth_write_enabled: true,
-            depth_compare: wgpu::CompareFunction::Less,
+            depth_write_enabled: !alpha_mode.is_blended(),
+            depth_compare: if reverse_z { wgpu::CompareFunction::Greater } else { wgpu::CompareFunction::Less },
             stencil: wgpu::StencilState::default(),
             bias: wgpu::DepthBiasState::default(),
         }),
@@ -56,4 +61,49 @@ pub fn make_shader(shader: &str, device: &Device, render_pipeline_layout:wgpu::P
         // indicates how many array layers the attachments will have.
         multiview: None,
     })
-}
\ No newline at end of file
+}
+//depth only, used to render the scene from a light into a shadow map
+pub fn make_shadow_shader(shader: &str, device: &Device, shadow_pipeline_layout: wgpu::PipelineLayout) -> RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Shadow Shader"),
+        source: wgpu::ShaderSource::Wgsl(shader.into()),
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Shadow Pipeline"),
+        layout: Some(&shadow_pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[Vertex::desc(), InstanceRaw::desc()],
+        },
+        fragment: None,
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            //both sides, so single sided quads like the ground still cast
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: Texture::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil: wgpu::StencilState::default(),
+            //slope scaled bias keeps surfaces at grazing angles from shadowing themselves
+            bias: wgpu::DepthBiasState {
+                constant: 2,
+                slope_scale: 2.0,
+                clamp: 0.0,
+            },
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
@@ -3,6 +3,7 @@
 struct Camera {
     view_pos: vec4<f32>,
     view_proj: mat4x4<f32>,
+    screen_proj: mat4x4<f32>,
 }
 @group(1) @binding(0)
 var<uniform> camera: Camera;
@@ -10,6 +11,9 @@ var<uniform> camera: Camera;
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>, //zero for unlit vertices
+    @location(3) tangent: vec3<f32>, //zero when there's nothing to normal map with
+    @location(4) bitangent: vec3<f32>,
 }
 struct InstanceInput {
     @location(5) model_matrix_0: vec4<f32>,
@@ -18,12 +22,18 @@ struct InstanceInput {
     @location(8) model_matrix_3: vec4<f32>,
     @location(9) color: vec4<f32>,
     @location(10) is_world_space: u32,
+    @location(11) shadow_flags: u32, //1 casts, 2 receives
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
     @location(1) color: vec4<f32>,
+    @location(2) world_position: vec3<f32>,
+    @location(3) world_normal: vec3<f32>, //zero when the fragment shouldn't be lit
+    @location(4) world_tangent: vec3<f32>,
+    @location(5) world_bitangent: vec3<f32>,
+    @location(6) @interpolate(flat) receive_shadows: u32,
 }
 
 @vertex
@@ -31,23 +41,41 @@ fn vs_main(
     vertex: VertexInput,
     instance: InstanceInput,
 ) -> VertexOutput {
-    var model = vertex;
-    if (instance.is_world_space == u32(0)) {model.position.y = model.position.y * camera.view_pos.w;} //hid the aspect ratio in the w component
     let model_matrix = mat4x4<f32>(
         instance.model_matrix_0,
         instance.model_matrix_1,
         instance.model_matrix_2,
         instance.model_matrix_3,
     );
-    let world_position = model_matrix * vec4<f32>(model.position, 1.0);
+    let world_position = model_matrix * vec4<f32>(vertex.position, 1.0);
     var out: VertexOutput;
-    out.tex_coords = model.tex_coords;
+    out.tex_coords = vertex.tex_coords;
     out.color = instance.color;
+    out.world_position = world_position.xyz;
+    //dividing by the squared scale of each axis is the inverse transpose for a scale + rotation matrix
+    let scale_squared = vec3<f32>(
+        dot(instance.model_matrix_0.xyz, instance.model_matrix_0.xyz),
+        dot(instance.model_matrix_1.xyz, instance.model_matrix_1.xyz),
+        dot(instance.model_matrix_2.xyz, instance.model_matrix_2.xyz),
+    );
+    let normal_matrix = mat3x3<f32>(
+        instance.model_matrix_0.xyz,
+        instance.model_matrix_1.xyz,
+        instance.model_matrix_2.xyz,
+    );
+    out.world_normal = vec3<f32>(0.0);
+    if (instance.is_world_space == u32(1) && dot(vertex.normal, vertex.normal) > 0.0) {
+        out.world_normal = normal_matrix * (vertex.normal / scale_squared);
+    }
+    //tangents lie along the surface, so they transform like positions
+    out.world_tangent = normal_matrix * vertex.tangent;
+    out.world_bitangent = normal_matrix * vertex.bitangent;
+    out.receive_shadows = (instance.shadow_flags >> u32(1)) & u32(1);
     if (instance.is_world_space == u32(1)) {
         out.clip_position = camera.view_proj * world_position;
     }
     else {
-        out.clip_position = world_position;
+        out.clip_position = camera.screen_proj * world_position;
     }
     return out;
 }
@@ -58,8 +86,178 @@ fn vs_main(
 var t_diffuse: texture_2d<f32>;
 @group(0)@binding(1)
 var s_diffuse: sampler;
+@group(0) @binding(2)
+var t_normal: texture_2d<f32>;
+@group(0) @binding(3)
+var s_normal: sampler;
+struct Material {
+    settings: vec4<f32>, //alpha cutoff, 0 for everything but masked materials
+}
+@group(0) @binding(4)
+var<uniform> material: Material;
+
+struct DirectionalLight {
+    direction: vec4<f32>,
+    color: vec4<f32>,
+}
+struct PointLight {
+    position: vec4<f32>, //range in w
+    color: vec4<f32>,
+}
+struct SpotLight {
+    position: vec4<f32>, //range in w
+    direction: vec4<f32>, //cos of the outer angle in w
+    color: vec4<f32>, //cos of the inner angle in w
+}
+struct Lights {
+    ambient: vec4<f32>,
+    counts: vec4<u32>, //directional, point, spot
+    specular: vec4<f32>, //shininess, strength
+    directional: array<DirectionalLight, 4>,
+    point: array<PointLight, 16>,
+    spot: array<SpotLight, 8>,
+}
+@group(2) @binding(0)
+var<uniform> lights: Lights;
+
+struct Shadows {
+    light_view_proj: array<mat4x4<f32>, 5>, //directional cascades, then spot lights
+    cascade_splits: vec4<f32>,
+    cascade_texel_sizes: vec4<f32>,
+    camera_forward: vec4<f32>,
+    settings: vec4<f32>, //depth bias, normal bias, pcf radius, 1 / map size
+    directional: vec4<i32>, //index of the shadowed directional light, -1 for none
+    spot_layers: array<vec4<i32>, 2>, //shadow map layer of every spot light, -1 for none
+}
+@group(3) @binding(0)
+var t_shadow: texture_depth_2d_array;
+@group(3) @binding(1)
+var s_shadow: sampler_comparison;
+@group(3) @binding(2)
+var<uniform> shadows: Shadows;
+
+//how lit a position is by the light rendered into a layer, 0 fully shadowed, 1 fully lit
+fn sample_shadow(layer: i32, world_position: vec3<f32>) -> f32 {
+    let clip = shadows.light_view_proj[layer] * vec4<f32>(world_position, 1.0);
+    if (clip.w <= 0.0) {
+        return 1.0;
+    }
+    let ndc = clip.xyz / clip.w;
+    if (ndc.z > 1.0 || any(abs(ndc.xy) > vec2<f32>(1.0))) {
+        return 1.0;
+    }
+    let uv = ndc.xy * vec2<f32>(0.5, -0.5) + 0.5;
+    let depth = ndc.z - shadows.settings.x;
+    //percentage closer filtering, averages a square of hardware filtered lookups
+    let radius = i32(shadows.settings.z);
+    var lit = 0.0;
+    for (var x = -radius; x <= radius; x += 1) {
+        for (var y = -radius; y <= radius; y += 1) {
+            let offset = vec2<f32>(f32(x), f32(y)) * shadows.settings.w;
+            lit += textureSampleCompareLevel(t_shadow, s_shadow, uv + offset, layer, depth);
+        }
+    }
+    let width = f32(radius * 2 + 1);
+    return lit / (width * width);
+}
+
+fn directional_shadow(world_position: vec3<f32>, normal: vec3<f32>) -> f32 {
+    let view_depth = dot(world_position - camera.view_pos.xyz, shadows.camera_forward.xyz);
+    for (var cascade = 0; cascade < 3; cascade += 1) {
+        if (view_depth <= shadows.cascade_splits[cascade]) {
+            //pushing out along the normal by a few texels stops surfaces shadowing themselves
+            let offset = normal * shadows.cascade_texel_sizes[cascade] * shadows.settings.y;
+            return sample_shadow(cascade, world_position + offset);
+        }
+    }
+    return 1.0;
+}
+
+//blinn-phong, returns the diffuse and specular amount for one light
+fn blinn_phong(normal: vec3<f32>, light_dir: vec3<f32>, view_dir: vec3<f32>) -> vec2<f32> {
+    let diffuse = max(dot(normal, light_dir), 0.0);
+    let half_dir = normalize(light_dir + view_dir);
+    var specular = pow(max(dot(normal, half_dir), 0.0), lights.specular.x) * lights.specular.y;
+    if (diffuse <= 0.0) {
+        specular = 0.0;
+    }
+    return vec2<f32>(diffuse, specular);
+}
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords) * in.color;
+    let base_color = textureSample(t_diffuse, s_diffuse, in.tex_coords) * in.color;
+    //sampled before any branching, textureSample needs uniform control flow
+    let tangent_normal = textureSample(t_normal, s_normal, in.tex_coords).xyz * 2.0 - 1.0;
+    if (base_color.a < material.settings.x) {
+        discard;
+    }
+    return shade(in, base_color, tangent_normal);
+}
+
+//for premultiplied textures, the instance color has to be premultiplied too before it tints them
+@fragment
+fn fs_premultiplied(in: VertexOutput) -> @location(0) vec4<f32> {
+    let tint = vec4<f32>(in.color.rgb * in.color.a, in.color.a);
+    let base_color = textureSample(t_diffuse, s_diffuse, in.tex_coords) * tint;
+    let tangent_normal = textureSample(t_normal, s_normal, in.tex_coords).xyz * 2.0 - 1.0;
+    return shade(in, base_color, tangent_normal);
+}
+
+fn shade(in: VertexOutput, base_color: vec4<f32>, tangent_normal: vec3<f32>) -> vec4<f32> {
+    if (dot(in.world_normal, in.world_normal) == 0.0) {
+        return base_color;
+    }
+    var normal = normalize(in.world_normal);
+    if (dot(in.world_tangent, in.world_tangent) > 0.0 && dot(in.world_bitangent, in.world_bitangent) > 0.0) {
+        let tbn = mat3x3<f32>(normalize(in.world_tangent), normalize(in.world_bitangent), normal);
+        normal = normalize(tbn * tangent_normal);
+    }
+    let view_dir = normalize(camera.view_pos.xyz - in.world_position);
+    var diffuse = lights.ambient.rgb;
+    var specular = vec3<f32>(0.0);
+    let receive_shadows = in.receive_shadows == u32(1);
+    //the surface normal, normal maps would make the offset noisy
+    let geometry_normal = normalize(in.world_normal);
+    for (var i = 0u; i < lights.counts.x; i += 1u) {
+        let light = lights.directional[i];
+        var amount = blinn_phong(normal, -light.direction.xyz, view_dir);
+        if (receive_shadows && i32(i) == shadows.directional.x) {
+            amount *= directional_shadow(in.world_position, geometry_normal);
+        }
+        diffuse += light.color.rgb * amount.x;
+        specular += light.color.rgb * amount.y;
+    }
+    for (var i = 0u; i < lights.counts.y; i += 1u) {
+        let light = lights.point[i];
+        let to_light = light.position.xyz - in.world_position;
+        let distance = length(to_light);
+        //inverse square, smoothly cut off at the range
+        let falloff = clamp(1.0 - pow(distance / light.position.w, 4.0), 0.0, 1.0);
+        let attenuation = falloff * falloff / (distance * distance + 1.0);
+        let amount = blinn_phong(normal, to_light / distance, view_dir) * attenuation;
+        diffuse += light.color.rgb * amount.x;
+        specular += light.color.rgb * amount.y;
+    }
+    for (var i = 0u; i < lights.counts.z; i += 1u) {
+        let light = lights.spot[i];
+        let to_light = light.position.xyz - in.world_position;
+        let distance = length(to_light);
+        let light_dir = to_light / distance;
+        let cone = smoothstep(light.direction.w, light.color.w, dot(-light_dir, light.direction.xyz));
+        let falloff = clamp(1.0 - pow(distance / light.position.w, 4.0), 0.0, 1.0);
+        let attenuation = cone * falloff * falloff / (distance * distance + 1.0);
+        var amount = blinn_phong(normal, light_dir, view_dir) * attenuation;
+        let layer = shadows.spot_layers[i / 4u][i % 4u];
+        if (receive_shadows && layer >= 0) {
+            //a perspective map's texels grow with distance, so the offset does too
+            let cos_outer = light.direction.w;
+            let texel_size = 2.0 * distance * sqrt(1.0 - cos_outer * cos_outer) / cos_outer * shadows.settings.w;
+            let offset = geometry_normal * texel_size * shadows.settings.y;
+            amount *= sample_shadow(layer, in.world_position + offset);
+        }
+        diffuse += light.color.rgb * amount.x;
+        specular += light.color.rgb * amount.y;
+    }
+    return vec4<f32>(base_color.rgb * diffuse + specular, base_color.a);
 }
\ No newline at end of file
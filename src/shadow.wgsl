@@ -0,0 +1,34 @@
+// Depth only pass from a light's point of view
+
+@group(0) @binding(0)
+var<uniform> light_view_proj: mat4x4<f32>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+}
+struct InstanceInput {
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+    @location(10) is_world_space: u32,
+    @location(11) shadow_flags: u32, //1 casts, 2 receives
+}
+
+@vertex
+fn vs_main(
+    vertex: VertexInput,
+    instance: InstanceInput,
+) -> @builtin(position) vec4<f32> {
+    //ui and instances that don't cast are pushed outside the clip volume
+    if (instance.is_world_space == u32(0) || (instance.shadow_flags & u32(1)) == u32(0)) {
+        return vec4<f32>(0.0, 0.0, 2.0, 1.0);
+    }
+    let model_matrix = mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+    return light_view_proj * model_matrix * vec4<f32>(vertex.position, 1.0);
+}
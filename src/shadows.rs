@@ -0,0 +1,396 @@
+use bevy_ecs::system::Resource;
+use glam::{Mat4, Vec3};
+use wgpu::{util::DeviceExt, BindGroup, BindGroupLayout, Buffer, Device, Queue, RenderPipeline, TextureView};
+
+use crate::{
+    assets::AssetServer,
+    camera::CameraStruct,
+    lighting::{Lights, SpotLight, MAX_DIRECTIONAL_LIGHTS, MAX_SPOT_LIGHTS},
+    shader,
+    structs::MeshType,
+    texture::Texture,
+};
+
+pub const NUM_CASCADES: usize = 3;
+pub const MAX_SHADOWED_SPOT_LIGHTS: usize = 2;
+//cascades of the directional light first, then one layer per shadowed spot light
+const SHADOW_LAYERS: usize = NUM_CASCADES + MAX_SHADOWED_SPOT_LIGHTS;
+//how far outside of a cascade something can be and still throw a shadow into it
+const CASTER_DISTANCE: f32 = 50.0;
+
+//the directional light's shadow is split into cascades so close shadows stay sharp while far ones still show up
+//off by default, the maps take map_size * map_size * 4 bytes for each of the NUM_CASCADES + MAX_SHADOWED_SPOT_LIGHTS layers,
+//about 80 MB at 2048. only a 1x1 placeholder is kept while disabled
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct ShadowSettings {
+    pub enabled: bool,
+    pub map_size: u32, //width and height of every shadow map in texels
+    pub cascade_splits: [f32; NUM_CASCADES], //distance from the camera where each cascade ends, the last one is the shadow distance
+    pub depth_bias: f32,
+    pub normal_bias: f32, //in texels, pushes the lookup out along the normal to stop acne on slopes
+    pub pcf_radius: u32, //0 is a single filtered lookup, 1 blurs over 3x3 lookups, 2 over 5x5
+}
+impl ShadowSettings {
+    //texels across each shadow map actually on the gpu
+    fn allocated_size(&self) -> u32 {
+        if self.enabled {
+            self.map_size
+        } else {
+            1
+        }
+    }
+}
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            map_size: 2048,
+            cascade_splits: [10.0, 30.0, 80.0],
+            depth_bias: 0.0005,
+            normal_bias: 1.5,
+            pcf_radius: 1,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ShadowUniform {
+    light_view_proj: [[[f32; 4]; 4]; SHADOW_LAYERS],
+    cascade_splits: [f32; 4],
+    cascade_texel_sizes: [f32; 4], //world units covered by one texel of each cascade
+    camera_forward: [f32; 4],
+    settings: [f32; 4], //depth bias, normal bias, pcf radius, 1 / map size
+    directional: [i32; 4], //index of the shadowed directional light, -1 for none
+    spot_layers: [[i32; 4]; MAX_SPOT_LIGHTS / 4], //shadow map layer of every spot light, -1 for none
+}
+
+//gpu side of the shadows, renders a depth pass per active layer before the main pass
+pub struct ShadowMaps {
+    map_size: u32, //what the maps were allocated with, see ShadowSettings::allocated_size
+    layer_views: Vec<TextureView>,
+    sampler: wgpu::Sampler,
+    uniform_buffer: Buffer,
+    pub bind_group_layout: BindGroupLayout,
+    pub bind_group: BindGroup,
+    caster_buffers: Vec<Buffer>,
+    caster_bind_groups: Vec<BindGroup>,
+    pipeline: RenderPipeline,
+    active_layers: Vec<usize>,
+}
+impl ShadowMaps {
+    pub fn new(device: &Device, settings: &ShadowSettings) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Shadow Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            //linear filtering on a comparison sampler gives a free 2x2 pcf
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Shadow Uniform Buffer"),
+            size: std::mem::size_of::<ShadowUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        sample_type: wgpu::TextureSampleType::Depth,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("shadow_bind_group_layout"),
+        });
+        let (layer_views, bind_group) =
+            create_shadow_maps(device, settings.allocated_size(), &bind_group_layout, &sampler, &uniform_buffer);
+
+        let caster_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("shadow_caster_bind_group_layout"),
+        });
+        let caster_buffers = (0..SHADOW_LAYERS)
+            .map(|_| {
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Shadow Caster Buffer"),
+                    contents: bytemuck::cast_slice(&Mat4::IDENTITY.to_cols_array()),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                })
+            })
+            .collect::<Vec<_>>();
+        let caster_bind_groups = caster_buffers
+            .iter()
+            .map(|buffer| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: &caster_bind_group_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                    label: Some("shadow_caster_bind_group"),
+                })
+            })
+            .collect();
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shadow Pipeline Layout"),
+            bind_group_layouts: &[&caster_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = shader::make_shadow_shader(include_str!("shadow.wgsl"), device, pipeline_layout);
+        Self {
+            map_size: settings.allocated_size(),
+            layer_views,
+            sampler,
+            uniform_buffer,
+            bind_group_layout,
+            bind_group,
+            caster_buffers,
+            caster_bind_groups,
+            pipeline,
+            active_layers: vec![],
+        }
+    }
+
+    //picks the shadow casting lights and fits their maps to the camera, called once a frame before rendering
+    pub fn update(&mut self, device: &Device, queue: &Queue, lights: &Lights, settings: &ShadowSettings, camera: &CameraStruct) {
+        if settings.allocated_size() != self.map_size {
+            let (layer_views, bind_group) =
+                create_shadow_maps(device, settings.allocated_size(), &self.bind_group_layout, &self.sampler, &self.uniform_buffer);
+            self.layer_views = layer_views;
+            self.bind_group = bind_group;
+            self.map_size = settings.allocated_size();
+        }
+        let mut uniform = ShadowUniform {
+            light_view_proj: [Mat4::IDENTITY.to_cols_array_2d(); SHADOW_LAYERS],
+            cascade_splits: [0.0; 4],
+            cascade_texel_sizes: [0.0; 4],
+            camera_forward: camera.camera_transform.forward().extend(0.0).into(),
+            settings: [settings.depth_bias, settings.normal_bias, settings.pcf_radius as f32, 1.0 / settings.map_size as f32],
+            directional: [-1; 4],
+            spot_layers: [[-1; 4]; MAX_SPOT_LIGHTS / 4],
+        };
+        self.active_layers.clear();
+        if settings.enabled {
+            let shadowed_directional = lights
+                .directional
+                .iter()
+                .take(MAX_DIRECTIONAL_LIGHTS)
+                .position(|light| light.cast_shadows);
+            if let Some(index) = shadowed_directional {
+                uniform.directional[0] = index as i32;
+                //orthographic cameras can see behind themselves, shadows only start in front
+                let mut near = camera.projection.znear().max(0.0);
+                for cascade in 0..NUM_CASCADES {
+                    let far = settings.cascade_splits[cascade].min(camera.projection.zfar()).max(near);
+                    let (view_proj, texel_size) =
+                        cascade_view_proj(camera, near, far, lights.directional[index].direction, settings.map_size);
+                    uniform.light_view_proj[cascade] = view_proj.to_cols_array_2d();
+                    uniform.cascade_splits[cascade] = far;
+                    uniform.cascade_texel_sizes[cascade] = texel_size;
+                    self.active_layers.push(cascade);
+                    near = far;
+                }
+            }
+            let mut layer = NUM_CASCADES;
+            for (index, light) in lights.spot.iter().enumerate().take(MAX_SPOT_LIGHTS) {
+                if !light.cast_shadows || layer == SHADOW_LAYERS {
+                    continue;
+                }
+                uniform.light_view_proj[layer] = spot_view_proj(light).to_cols_array_2d();
+                uniform.spot_layers[index / 4][index % 4] = layer as i32;
+                self.active_layers.push(layer);
+                layer += 1;
+            }
+        }
+        for &layer in &self.active_layers {
+            queue.write_buffer(&self.caster_buffers[layer], 0, bytemuck::cast_slice(&uniform.light_view_proj[layer]));
+        }
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, asset_server: &AssetServer) {
+        for &layer in &self.active_layers {
+            let mut shadow_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Shadow Pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.layer_views[layer],
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+            shadow_pass.set_pipeline(&self.pipeline);
+            shadow_pass.set_bind_group(0, &self.caster_bind_groups[layer], &[]);
+            for (_, prefab) in &asset_server.prefab_slab {
+                shadow_pass.set_vertex_buffer(1, prefab.buffer.slice(..));
+                //materials don't matter for depth, so every mesh is drawn the same way
+                let meshes: Vec<(&Buffer, &Buffer, u32)> = match &prefab.mesh_type {
+                    MeshType::Model(model) => match asset_server.models.get(model) {
+                        Some(model) => model
+                            .meshes
+                            .iter()
+                            .map(|mesh| (&mesh.vertex_buffer, &mesh.index_buffer, mesh.num_elements))
+                            .collect(),
+                        None => vec![],
+                    },
+                    MeshType::Mesh(mesh) => vec![(&mesh.vertex_buffer, &mesh.index_buffer, mesh.num_elements)],
+                    MeshType::Sprite(_) => {
+                        let sprite = &asset_server.sprite_mesh;
+                        vec![(&sprite.vertex_buffer, &sprite.index_buffer, sprite.num_elements)]
+                    }
+                };
+                for (vertex_buffer, index_buffer, num_elements) in meshes {
+                    shadow_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                    shadow_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                    shadow_pass.draw_indexed(0..num_elements, 0, 0..prefab.length);
+                }
+            }
+        }
+    }
+}
+
+fn create_shadow_maps(
+    device: &Device,
+    map_size: u32,
+    layout: &BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    uniform_buffer: &Buffer,
+) -> (Vec<TextureView>, BindGroup) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Shadow Maps"),
+        size: wgpu::Extent3d {
+            width: map_size,
+            height: map_size,
+            depth_or_array_layers: SHADOW_LAYERS as u32,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: Texture::DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    let layer_views = (0..SHADOW_LAYERS as u32)
+        .map(|layer| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("Shadow Map Layer"),
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                base_array_layer: layer,
+                array_layer_count: Some(1),
+                ..Default::default()
+            })
+        })
+        .collect();
+    let array_view = texture.create_view(&wgpu::TextureViewDescriptor {
+        label: Some("Shadow Maps View"),
+        dimension: Some(wgpu::TextureViewDimension::D2Array),
+        ..Default::default()
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&array_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+        ],
+        label: Some("shadow_bind_group"),
+    });
+    (layer_views, bind_group)
+}
+
+fn light_up(direction: Vec3) -> Vec3 {
+    if direction.y.abs() > 0.99 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    }
+}
+
+//an orthographic box around the part of the camera's view between near and far, returns it and the world size of a texel
+fn cascade_view_proj(camera: &CameraStruct, near: f32, far: f32, light_direction: Vec3, map_size: u32) -> (Mat4, f32) {
+    let transform = &camera.camera_transform;
+    let forward = transform.forward();
+    let right = forward.cross(Vec3::Y).normalize_or_zero();
+    let up = right.cross(forward);
+    let mut corners = Vec::with_capacity(8);
+    for distance in [near, far] {
+        let half_extents = camera.half_extents(distance);
+        let center = transform.position + forward * distance;
+        for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+            corners.push(center + right * half_extents.x * x + up * half_extents.y * y);
+        }
+    }
+    //a bounding sphere keeps the box the same size while the camera turns, which stops shadow edges from crawling
+    let mut center = corners.iter().sum::<Vec3>() / corners.len() as f32;
+    let radius = corners.iter().map(|corner| corner.distance(center)).fold(0.0, f32::max);
+    let radius = (radius * 16.0).ceil() / 16.0;
+    let texel_size = radius * 2.0 / map_size as f32;
+
+    //snapping the center to whole texels stops it shimmering while the camera moves
+    let direction = light_direction.normalize_or_zero();
+    let up = light_up(direction);
+    let light_rotation = Mat4::look_to_rh(Vec3::ZERO, direction, up);
+    let mut light_space_center = light_rotation.transform_point3(center);
+    light_space_center.x = (light_space_center.x / texel_size).floor() * texel_size;
+    light_space_center.y = (light_space_center.y / texel_size).floor() * texel_size;
+    center = light_rotation.inverse().transform_point3(light_space_center);
+
+    let view = Mat4::look_to_rh(center - direction * (radius + CASTER_DISTANCE), direction, up);
+    let projection = Mat4::orthographic_rh(-radius, radius, -radius, radius, 0.0, radius * 2.0 + CASTER_DISTANCE);
+    (projection * view, texel_size)
+}
+
+fn spot_view_proj(light: &SpotLight) -> Mat4 {
+    let direction = light.direction.normalize_or_zero();
+    let view = Mat4::look_to_rh(light.position, direction, light_up(direction));
+    let fov = (light.outer_angle * 2.0).clamp(0.01, std::f32::consts::PI - 0.01);
+    Mat4::perspective_rh(fov, 1.0, 0.05, light.range) * view
+}
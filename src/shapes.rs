@@ -6,10 +6,10 @@ pub fn rect(x_width: f32, y_height: f32) -> (Vec<Vertex>, Vec<u32>) {
     let y = y_height/2.;
     let x = x_width/2.;
     let vertices = vec![
-        Vertex::new([-x,-y, 0.0], [0.0,1.0]),
-        Vertex::new([-x,y, 0.0],[0.0,0.0]),
-        Vertex::new([x,-y, 0.0],[1.0,1.0]),
-        Vertex::new([x,y, 0.0],[1.0, 0.0]),
+        Vertex::with_normal([-x,-y, 0.0], [0.0,1.0], [0.0, 0.0, 1.0]),
+        Vertex::with_normal([-x,y, 0.0],[0.0,0.0], [0.0, 0.0, 1.0]),
+        Vertex::with_normal([x,-y, 0.0],[1.0,1.0], [0.0, 0.0, 1.0]),
+        Vertex::with_normal([x,y, 0.0],[1.0, 0.0], [0.0, 0.0, 1.0]),
     ];
     
     let indices = vec![2,1,0, 1, 2, 3];
@@ -30,55 +30,37 @@ pub fn rect_with_tex_coords(height: f32, width: f32, tex_1: Vec2, tex_2: Vec2) -
     let y = height/2.;
     let x = width/2.;
     let vertices = vec![
-        Vertex::new([-x,-y, 0.0], [tex_1.x,tex_2.y]),
-        Vertex::new([-x,y, 0.0],[tex_1.x,tex_1.y]),
-        Vertex::new([x,-y, 0.0],[tex_2.x,tex_2.y]),
-        Vertex::new([x,y, 0.0],[tex_2.x, tex_1.y]),
+        Vertex::with_normal([-x,-y, 0.0], [tex_1.x,tex_2.y], [0.0, 0.0, 1.0]),
+        Vertex::with_normal([-x,y, 0.0],[tex_1.x,tex_1.y], [0.0, 0.0, 1.0]),
+        Vertex::with_normal([x,-y, 0.0],[tex_2.x,tex_2.y], [0.0, 0.0, 1.0]),
+        Vertex::with_normal([x,y, 0.0],[tex_2.x, tex_1.y], [0.0, 0.0, 1.0]),
     ];
 
     let indices = vec![2,1,0, 1, 2, 3];
     (vertices,indices)
 }
+//4 vertices per face so every face gets its own normal and the full texture
 pub fn cube(x_width: f32, y_height: f32, z_length: f32) -> (Vec<Vertex>, Vec<u32>) {
-    let x = x_width/2.;
-    let y = y_height/2.;
-    let z = z_length/2.;
-    let indices = vec![
-        //Top
-        7, 6, 2,
-        2, 3, 7,
-
-        //Bottom
-        0, 4, 5,
-        5, 1, 0,
-
-        //Left
-        0, 2, 6,
-        6, 4, 0,
-
-        //Right
-        7, 3, 1,
-        1, 5, 7,
-
-        //Front
-        3, 2, 0,
-        0, 1, 3,
-
-        //Back
-        4, 6, 7,
-        7, 5, 4
-    ];
-
-
-    let vertices = vec![
-        Vertex::new([-x,-y, z], [0.0,0.0]),
-        Vertex::new([x,-y, z], [0.0,0.0]),
-        Vertex::new([-x,y, z], [0.0,0.0]),
-        Vertex::new([x,y, z], [0.0,0.0]),
-        Vertex::new([-x,-y, -z], [0.0,0.0]),
-        Vertex::new([x,-y, -z], [0.0,0.0]),
-        Vertex::new([-x,y, -z], [0.0,0.0]),
-        Vertex::new([x,y, -z], [0.0,0.0]),
+    let half_size = Vec3::new(x_width, y_height, z_length) / 2.;
+    //normal, then the face's right and up, right x up = normal keeps the winding counter clockwise
+    let faces = [
+        (Vec3::X, Vec3::NEG_Z, Vec3::Y),
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y),
+        (Vec3::Y, Vec3::X, Vec3::NEG_Z),
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z),
+        (Vec3::Z, Vec3::X, Vec3::Y),
+        (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y),
     ];
+    let corners = [(-1., -1., [0.0, 1.0]), (1., -1., [1.0, 1.0]), (1., 1., [1.0, 0.0]), (-1., 1., [0.0, 0.0])];
+    let mut vertices = Vec::with_capacity(24);
+    let mut indices = Vec::with_capacity(36);
+    for (normal, right, up) in faces {
+        let base_index = vertices.len() as u32;
+        for (right_sign, up_sign, tex_coords) in corners {
+            let position = (normal + right * right_sign + up * up_sign) * half_size;
+            vertices.push(Vertex::with_normal(position.into(), tex_coords, normal.into()));
+        }
+        indices.extend([0, 1, 2, 2, 3, 0].map(|index| index + base_index));
+    }
     (vertices,indices)
-}
\ No newline at end of file
+}
@@ -1,55 +1,70 @@
 use crate::{
     assets::AssetServer,
     camera::{Camera, CameraStruct},
-    resources::{MouseClickType, WindowEvents},
-    shader,
+    resources::WindowEvents,
+    render, shader,
     structs::CameraController,
-    texture, window, app_resource::App,
+    texture, window::{self, WindowConfig, WindowMode}, app_resource::App, instance::{sync_instance_buffers, sort_transparent_instances, InstancePrefabs},
+    hierarchy::{insert_global_transforms, propagate_transforms},
+    error::{Result, VertixError},
+    lighting::{Lights, LightsStruct},
+    shadows::{ShadowMaps, ShadowSettings},
+    input::InputMap,
+    replay::{InputRecorder, InputRecording, InputReplay, RecordedInput},
+    model::AlphaMode,
 };
 use bevy_ecs::prelude::*;
-use glam::Vec3;
 use instant::Duration;
 use winit::{
-    dpi::PhysicalPosition,
-    event::{ElementState, KeyboardInput, MouseButton, WindowEvent},
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{KeyboardInput, WindowEvent},
     event_loop::EventLoop,
     window::Window,
 };
 pub struct State {
     pub config: wgpu::SurfaceConfiguration,
     pub render_pipeline: wgpu::RenderPipeline,
+    pub transparent_pipeline: wgpu::RenderPipeline, //straight alpha blending
+    pub premultiplied_pipeline: wgpu::RenderPipeline,
+    render_pipeline_layout: wgpu::PipelineLayout,
+    pipelines_reverse_z: bool, //the direction of the depth test the pipelines were made with
     pub depth_texture: texture::Texture,
-    pub window: window::Window,
+    pub lights: LightsStruct, //gpu side of the Lights resource
+    pub shadows: ShadowMaps, //gpu side of the ShadowSettings resource
+    pub window: Option<window::Window>, //None when headless
+    applied_window_config: WindowConfig, //what the window currently looks like, compared against the WindowConfig resource
     pub mouse_locked: bool,
     pub world: World,
-    pub schedule: Schedule,
+    pub pre_update_schedule: Schedule,
+    pub fixed_update_schedule: Schedule, //runs every App::fixed_timestep instead of every frame
+    pub schedule: Schedule, //update
+    pub post_update_schedule: Schedule,
+    pub render_prep_schedule: Schedule, //engine systems that push the world to the gpu, right before render
+    fixed_accumulator: Duration,
+    pub recorder: Option<InputRecorder>, //Some while recording input
+    replay: Option<InputReplay>, //Some while a recording is played back, live input is ignored then
 }
+//the order they run in every frame
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Stage {
+    PreUpdate,
+    FixedUpdate,
+    Update,
+    PostUpdate,
+    RenderPrep,
+}
+//stops a slow frame from queueing up more fixed steps than the next frame can run (spiral of death)
+pub const MAX_FIXED_STEPS_PER_FRAME: u32 = 8;
 
 impl State {
-    pub async fn new(
+    pub(crate) async fn new(
         mouse_lock: bool,
         build_path: &str,
         cam: Camera,
-        speed: f32,
-        sensitivity: f32,
-    ) -> (Self, EventLoop<()>) {
-        let (window, event_loop) = window::Window::new(mouse_lock).await;
-        let (device, queue) = window
-            .adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: None,
-                    features: wgpu::Features::empty(),
-                    limits: if cfg!(target_arch = "wasm32") {
-                        wgpu::Limits::downlevel_webgl2_defaults()
-                    } else {
-                        wgpu::Limits::default()
-                    },
-                },
-                None,
-            )
-            .await
-            .unwrap();
+        window_config: WindowConfig,
+    ) -> Result<(Self, EventLoop<()>)> {
+        let (window, event_loop) = window::Window::new(mouse_lock, &window_config).await?;
+        let (device, queue) = request_device(&window.adapter).await?;
 
         log::warn!("Surface");
         let surface_caps = window.surface.get_capabilities(&window.adapter);
@@ -64,13 +79,82 @@ impl State {
             format: surface_format,
             width: window.size.width,
             height: window.size.height,
-            present_mode: surface_caps.present_modes[0],
+            present_mode: window.supported_present_mode(window_config.present_mode),
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
         };
 
         window.surface.configure(&device, &config);
-
+        window.window.set_visible(true);
+        let mut state = Self::from_device(
+            Some(window),
+            device,
+            queue,
+            config,
+            build_path,
+            cam,
+            window_config,
+        );
+        state.mouse_locked = mouse_lock;
+        Ok((state, event_loop))
+    }
+    //no window or surface, frames are rendered offscreen and read back with render_to_image
+    //force_fallback_adapter picks a software adapter (lavapipe, llvmpipe, warp) so output is the same across machines
+    pub(crate) async fn new_headless(
+        width: u32,
+        height: u32,
+        force_fallback_adapter: bool,
+        build_path: &str,
+        cam: Camera,
+    ) -> Result<Self> {
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = env_logger::try_init();
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            dx12_shader_compiler: Default::default(),
+        });
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter,
+            })
+            .await
+            .ok_or(VertixError::AdapterNotFound)?;
+        let (device, queue) = request_device(&adapter).await?;
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+        let window_config = WindowConfig {
+            size: winit::dpi::PhysicalSize::new(width, height),
+            mode: WindowMode::Windowed,
+            ..Default::default()
+        };
+        Ok(Self::from_device(
+            None,
+            device,
+            queue,
+            config,
+            build_path,
+            cam,
+            window_config,
+        ))
+    }
+    fn from_device(
+        window: Option<window::Window>,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        config: wgpu::SurfaceConfiguration,
+        build_path: &str,
+        cam: Camera,
+        window_config: WindowConfig,
+    ) -> Self {
         let texture_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[
@@ -90,6 +174,34 @@ impl State {
                         ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                         count: None,
                     },
+                    //normal map
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    //alpha cutoff
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
                 ],
                 label: Some("texture_bind_group_layout"),
             });
@@ -99,25 +211,24 @@ impl State {
             &device,
             &config,
             cam,
-            CameraController::new(speed, sensitivity),
+            CameraController::new(5.0, 2.0),
         );
 
+        let lights = LightsStruct::new(&device, &Lights::default());
+        let shadows = ShadowMaps::new(&device, &ShadowSettings::default());
+
         let depth_texture =
             texture::Texture::create_depth_texture(&device, &config, "depth_texture");
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&texture_bind_group_layout, &camera.bind_group_layout],
+                bind_group_layouts: &[&texture_bind_group_layout, &camera.bind_group_layout, &lights.bind_group_layout, &shadows.bind_group_layout],
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = shader::make_shader(
-            include_str!("shader.wgsl"),
-            &device,
-            render_pipeline_layout,
-            &config,
-        );
-        window.window.set_visible(true);
+        let pipelines_reverse_z = camera.projection.reverse_z();
+        let (render_pipeline, transparent_pipeline, premultiplied_pipeline) =
+            make_pipelines(&device, &render_pipeline_layout, &config, pipelines_reverse_z);
         let mut world = World::new();
         let asset_server = AssetServer::new(
             device,
@@ -125,38 +236,61 @@ impl State {
             build_path.to_string(),
             texture_bind_group_layout,
         );
-        let mut window_events = WindowEvents {
-            keys_pressed: vec![],
-            screen_mouse_pos: PhysicalPosition { x: 0.0, y: 0.0 },
-            world_mouse_pos: PhysicalPosition { x: 0.0, y: 0.0 },
-            left_mouse: MouseClickType::NotHeld,
-            right_mouse: MouseClickType::NotHeld,
-            middle_mouse: MouseClickType::NotHeld,
-            aspect_ratio: (config.width as f32) / (config.height as f32),
-            mouse_ray_direction: Vec3::ZERO,
-        };
-        window_events.calculate_mouse_dir(&camera.projection, &camera.camera_uniform.view_proj);
-        world.insert_resource(App {asset_server, dt: Duration::ZERO,window_events, camera});
-        let schedule = Schedule::default();
-        (
-            Self {
-                config,
-                render_pipeline,
-                depth_texture,
-                window,
-                mouse_locked: mouse_lock,
-                world,
-                schedule,
-            },
-            event_loop,
-        )
+        let mut window_events = WindowEvents::new(config.width, config.height);
+        window_events.calculate_mouse_dir(&camera);
+        world.insert_resource(App {
+            asset_server,
+            dt: Duration::ZERO,
+            fixed_timestep: Duration::from_secs_f64(1.0 / 60.0),
+            fixed_alpha: 0.0,
+            window_events,
+            camera,
+        });
+        world.insert_resource(window_config.clone());
+        world.insert_resource(Lights::default());
+        world.insert_resource(ShadowSettings::default());
+        world.insert_resource(InputMap::default());
+        world.insert_resource(InstancePrefabs::default());
+        let mut render_prep_schedule = Schedule::default();
+        render_prep_schedule.add_systems(
+            (insert_global_transforms, apply_deferred, propagate_transforms, sync_instance_buffers, sort_transparent_instances).chain(),
+        );
+        Self {
+            config,
+            render_pipeline,
+            transparent_pipeline,
+            premultiplied_pipeline,
+            pipelines_reverse_z,
+            render_pipeline_layout,
+            depth_texture,
+            lights,
+            shadows,
+            window,
+            applied_window_config: window_config,
+            mouse_locked: false,
+            world,
+            pre_update_schedule: Schedule::default(),
+            fixed_update_schedule: Schedule::default(),
+            schedule: Schedule::default(),
+            post_update_schedule: Schedule::default(),
+            render_prep_schedule,
+            fixed_accumulator: Duration::ZERO,
+            recorder: None,
+            replay: None,
+        }
     }
     pub fn window(&self) -> &Window {
-        &self.window.window
+        &self.window.as_ref().expect("headless State has no window").window
+    }
+    pub fn is_headless(&self) -> bool {
+        self.window.is_none()
     }
 
-    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+    pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
+            if new_size.width != self.config.width || new_size.height != self.config.height {
+                self.record(RecordedInput::Resized(new_size.width, new_size.height));
+            }
             let mut app = self.world
                 .get_resource_mut::<App>()
                 .unwrap();
@@ -165,16 +299,29 @@ impl State {
                 .resize(new_size.width, new_size.height);
             app.window_events
                 .update_aspect_ratio(new_size.width, new_size.height);
-            self.window.size = new_size;
             self.config.width = new_size.width;
             self.config.height = new_size.height;
             let device = &app.asset_server.device;
-            self.window.surface.configure(device, &self.config);
+            if let Some(window) = &mut self.window {
+                window.size = new_size;
+                window.surface.configure(device, &self.config);
+            }
             self.depth_texture =
-                texture::Texture::create_depth_texture(&device, &self.config, "depth_texture");
+                texture::Texture::create_depth_texture(device, &self.config, "depth_texture");
         }
     }
     pub fn input(&mut self, event: &WindowEvent) -> bool {
+        if let Some(input) = RecordedInput::from_window_event(event) {
+            self.record(input);
+        }
+        let mut input_map = self.world.resource_mut::<InputMap>();
+        input_map.process_event(event);
+        if let WindowEvent::Focused(false) = event {
+            input_map.release_all();
+        }
+        let mut app = self.world
+            .get_resource_mut::<App>()
+            .unwrap();
         match event {
             WindowEvent::KeyboardInput {
                 input:
@@ -185,65 +332,251 @@ impl State {
                     },
                 ..
             } => {
-                let key_pressed = (*key, *state);
-                let mut app = self.world
-                    .get_resource_mut::<App>()
-                    .unwrap();
-                app.window_events
-                    .keys_pressed
-                    .push(key_pressed);
-                app.camera.camera_controller.process_keyboard(*key, *state)
+                app.window_events.process_key(*key, *state);
+                true
             }
             WindowEvent::MouseWheel { delta, .. } => {
-                let mut app = self.world
-                    .get_resource_mut::<App>()
-                    .unwrap();
                 app.camera.camera_controller.process_scroll(delta);
                 true
             }
             WindowEvent::MouseInput { button, state, .. } => {
-                let mut app = self.world
-                    .get_resource_mut::<App>()
-                    .unwrap();
-                let events = &mut app.window_events;
-                match button {
-                    MouseButton::Left => {
-                        events.left_mouse = if *state == ElementState::Pressed {
-                            MouseClickType::Clicked
-                        } else {
-                            MouseClickType::Released
-                        }
-                    }
-                    MouseButton::Right => {
-                        events.right_mouse = if *state == ElementState::Pressed {
-                            MouseClickType::Clicked
-                        } else {
-                            MouseClickType::Released
-                        }
-                    }
-                    MouseButton::Middle => {
-                        events.middle_mouse = if *state == ElementState::Pressed {
-                            MouseClickType::Clicked
-                        } else {
-                            MouseClickType::Released
-                        }
-                    }
-                    _ => {}
-                }
+                app.window_events.process_mouse_button(*button, *state);
                 true
             }
+            WindowEvent::Focused(false) => {
+                app.window_events.release_all();
+                false
+            }
             _ => false,
         }
     }
+    //called by the event loop with the cursor position from window.normalize_position
+    pub fn cursor_moved(&mut self, normalized_position: PhysicalPosition<f32>) {
+        self.record(RecordedInput::CursorMoved(normalized_position));
+        self.world.resource_mut::<App>().cursor_move(normalized_position);
+    }
+    //raw mouse movement, turns the camera while the mouse is locked or the left button is held
+    pub fn mouse_motion(&mut self, delta: (f64, f64)) {
+        self.record(RecordedInput::MouseMotion(delta.0, delta.1));
+        let mouse_locked = self.mouse_locked;
+        let mut app = self.world.resource_mut::<App>();
+        if app.window_events.left_held() || mouse_locked {
+            app.camera.camera_controller.process_mouse(delta.0, delta.1)
+        }
+    }
+    //feeds a recorded input through the same path a live one takes
+    pub fn apply_input(&mut self, input: &RecordedInput) {
+        match input {
+            RecordedInput::CursorMoved(position) => self.cursor_moved(*position),
+            RecordedInput::MouseMotion(dx, dy) => self.mouse_motion((*dx, *dy)),
+            //a window keeps the size the os gives it, only headless states can follow the recording
+            RecordedInput::Resized(width, height) => {
+                if self.is_headless() {
+                    self.resize(PhysicalSize::new(*width, *height));
+                }
+            }
+            input => {
+                if let Some(event) = input.to_window_event() {
+                    self.input(&event);
+                }
+            }
+        }
+    }
+    fn record(&mut self, input: RecordedInput) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(input);
+        }
+    }
+    pub fn start_recording(&mut self) {
+        let mut recorder = InputRecorder::new(self.config.width, self.config.height);
+        recorder.recording.fixed_accumulator = self.fixed_accumulator;
+        self.recorder = Some(recorder);
+    }
+    //records until the event loop exits, then writes the recording to path
+    pub fn record_to_file(&mut self, path: &str) {
+        self.start_recording();
+        if let Some(recorder) = &mut self.recorder {
+            recorder.save_path = Some(path.to_string());
+        }
+    }
+    pub fn stop_recording(&mut self) -> Option<InputRecording> {
+        self.recorder.take().map(|recorder| recorder.recording)
+    }
+    //the event loop plays this back instead of live input until it runs out
+    pub fn start_replay(&mut self, recording: InputRecording) {
+        //fixed steps land on the same frames they did while recording
+        self.fixed_accumulator = recording.fixed_accumulator;
+        self.replay = Some(InputReplay::new(recording));
+    }
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+    //applies the next recorded frame's inputs and runs it with the recorded dt, false once the replay is over
+    pub fn run_replay_frame(&mut self) -> bool {
+        let Some(frame) = self.replay.as_mut().and_then(|replay| replay.next_frame()) else {
+            self.replay = None;
+            return false;
+        };
+        for input in &frame.inputs {
+            self.apply_input(input);
+        }
+        self.run_frame(frame.dt);
+        if self.replay.as_ref().is_some_and(|replay| replay.is_finished()) {
+            self.replay = None;
+        }
+        true
+    }
+    //plays a whole recording back without rendering, for regression tests on a headless State
+    pub fn run_replay(&mut self, recording: InputRecording) {
+        self.start_replay(recording);
+        while self.run_replay_frame() {}
+    }
     pub fn update(&mut self) {
         let mut app = self.world
                     .get_resource_mut::<App>()
                     .unwrap();
         app.camera.update_view_proj();
+        let app = &mut *app;
+        app.window_events.update_world_mouse_pos(&app.camera);
+        app.window_events.calculate_mouse_dir(&app.camera);
         app.asset_server.queue.write_buffer(
             &app.camera.buffer,
             0,
             bytemuck::cast_slice(&[app.camera.camera_uniform]),
         );
+        let app = self.world.resource::<App>();
+        //switching to or from reverse z flips the depth test, so the pipelines have to be remade
+        if app.camera.projection.reverse_z() != self.pipelines_reverse_z {
+            self.pipelines_reverse_z = app.camera.projection.reverse_z();
+            (self.render_pipeline, self.transparent_pipeline, self.premultiplied_pipeline) = make_pipelines(
+                &app.asset_server.device,
+                &self.render_pipeline_layout,
+                &self.config,
+                app.camera.projection.reverse_z(),
+            );
+        }
+        let lights = self.world.resource::<Lights>();
+        self.lights.write(&app.asset_server.queue, lights);
+        self.shadows.update(
+            &app.asset_server.device,
+            &app.asset_server.queue,
+            lights,
+            self.world.resource::<ShadowSettings>(),
+            &app.camera,
+        );
     }
+    pub fn schedule_mut(&mut self, stage: Stage) -> &mut Schedule {
+        match stage {
+            Stage::PreUpdate => &mut self.pre_update_schedule,
+            Stage::FixedUpdate => &mut self.fixed_update_schedule,
+            Stage::Update => &mut self.schedule,
+            Stage::PostUpdate => &mut self.post_update_schedule,
+            Stage::RenderPrep => &mut self.render_prep_schedule,
+        }
+    }
+    pub fn add_systems<M>(&mut self, stage: Stage, systems: impl IntoSystemConfigs<M>) -> &mut Self {
+        self.schedule_mut(stage).add_systems(systems);
+        self
+    }
+    //runs FixedUpdate as many times as fit in the time since last frame, app.dt is the fixed timestep while it runs
+    fn run_fixed_update(&mut self, dt: Duration) {
+        let fixed_timestep = self.world.resource::<App>().fixed_timestep;
+        if fixed_timestep.is_zero() {
+            return;
+        }
+        self.fixed_accumulator += dt;
+        let mut steps = 0;
+        while self.fixed_accumulator >= fixed_timestep {
+            if steps == MAX_FIXED_STEPS_PER_FRAME {
+                self.fixed_accumulator = Duration::ZERO;
+                break;
+            }
+            self.fixed_accumulator -= fixed_timestep;
+            self.world.resource_mut::<App>().dt = fixed_timestep;
+            self.fixed_update_schedule.run(&mut self.world);
+            steps += 1;
+        }
+        let mut app = self.world.resource_mut::<App>();
+        app.dt = dt;
+        //how far between the last fixed step and the next one this frame is, for interpolating what gets drawn
+        app.fixed_alpha = self.fixed_accumulator.as_secs_f32() / fixed_timestep.as_secs_f32();
+    }
+    //pushes edits to the WindowConfig resource out to the real window
+    pub fn apply_window_config(&mut self) {
+        let window_config = self.world.resource::<WindowConfig>();
+        if *window_config == self.applied_window_config {
+            return;
+        }
+        let window_config = window_config.clone();
+        if let Some(window) = &self.window {
+            window.apply_config(&self.applied_window_config, &window_config);
+            if window_config.present_mode != self.applied_window_config.present_mode {
+                self.config.present_mode = window.supported_present_mode(window_config.present_mode);
+                let device = &self.world.resource::<App>().asset_server.device;
+                window.surface.configure(device, &self.config);
+            }
+        }
+        self.applied_window_config = window_config;
+    }
+    //everything the event loop does for one frame besides drawing, so headless code can step the world itself
+    pub fn run_frame(&mut self, dt: Duration) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.end_frame(dt);
+        }
+        self.world
+            .get_resource_mut::<App>()
+            .unwrap().dt = dt;
+        self.pre_update_schedule.run(&mut self.world);
+        self.run_fixed_update(dt);
+        self.schedule.run(&mut self.world);
+        self.post_update_schedule.run(&mut self.world);
+        self.apply_window_config();
+        //after the schedules so camera movement from systems shows up this frame
+        self.update();
+        self.render_prep_schedule.run(&mut self.world);
+        let mut app = self.world.get_resource_mut::<App>().unwrap();
+        app.asset_server.free_unused_assets();
+        app.window_events.next_frame();
+        self.world.resource_mut::<InputMap>().next_frame();
+        //RemovedComponents only keeps the last two frames of despawns after this
+        self.world.clear_trackers();
+    }
+    //draws the scene into an offscreen texture and copies it back to the cpu, works with or without a window
+    pub fn render_to_image(&mut self) -> image::RgbaImage {
+        let app = self.world
+            .get_resource::<App>()
+            .unwrap();
+        let device = &app.asset_server.device;
+        let target = texture::Texture::create_render_target(device, &self.config, "offscreen_target");
+        render::draw_frame(self, &target.view);
+        texture::read_texture(device, &app.asset_server.queue, &target.texture, self.config.format)
+    }
+}
+
+async fn request_device(adapter: &wgpu::Adapter) -> Result<(wgpu::Device, wgpu::Queue)> {
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features: wgpu::Features::empty(),
+                limits: if cfg!(target_arch = "wasm32") {
+                    wgpu::Limits::downlevel_webgl2_defaults()
+                } else {
+                    wgpu::Limits::default()
+                },
+            },
+            None,
+        )
+        .await?;
+    Ok((device, queue))
+}
+
+fn make_pipelines(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    config: &wgpu::SurfaceConfiguration,
+    reverse_z: bool,
+) -> (wgpu::RenderPipeline, wgpu::RenderPipeline, wgpu::RenderPipeline) {
+    let make = |alpha_mode| shader::make_shader(include_str!("shader.wgsl"), device, layout, config, alpha_mode, reverse_z);
+    (make(AlphaMode::Opaque), make(AlphaMode::Blend), make(AlphaMode::Premultiplied))
 }
@@ -1,33 +1,46 @@
-use crate::model::Model;
+use glam::{Vec2, Vec3};
+
+use crate::{handle::Handle, model::{Material, Model}};
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 use winit::{
     dpi::PhysicalPosition,
-    event::{ElementState, MouseScrollDelta, VirtualKeyCode},
+    event::MouseScrollDelta,
 };
+#[allow(clippy::large_enum_variant)]
 pub enum MeshType {
-    Model(Model),
+    Model(Handle<Model>),
     Mesh(Mesh),
-    Sprite(usize) //material idx
+    Sprite(Handle<Material>)
 }
 pub struct Mesh {
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
     pub num_elements: u32,
-    pub material_idx: usize,
+    pub material: Handle<Material>,
 }
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Vertex {
     pub position: [f32; 3],
     pub tex_coords: [f32; 2],
+    pub normal: [f32; 3], //all zeros means unlit
+    pub tangent: [f32; 3], //all zeros means no normal mapping, see compute_tangents
+    pub bitangent: [f32; 3],
 }
 
 impl Vertex {
+    //no normal, so lighting is skipped for it, fine for ui, sprites and lines
     pub fn new(position: [f32; 3], tex_coords: [f32; 2]) -> Self {
+        Self::with_normal(position, tex_coords, [0.0; 3])
+    }
+    pub fn with_normal(position: [f32; 3], tex_coords: [f32; 2], normal: [f32; 3]) -> Self {
         Self {
             position,
             tex_coords,
+            normal,
+            tangent: [0.0; 3],
+            bitangent: [0.0; 3],
         }
     }
     pub fn desc() -> wgpu::VertexBufferLayout<'static> {
@@ -46,19 +59,64 @@ impl Vertex {
                     shader_location: 1,
                     format: wgpu::VertexFormat::Float32x2,
                 },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 11]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
             ],
         }
     }
 }
 
+//fills in tangent and bitangent from the tex coords of every triangle, vertices without a normal are left alone
+pub fn compute_tangents(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut tangents = vec![Vec3::ZERO; vertices.len()];
+    let mut bitangents = vec![Vec3::ZERO; vertices.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+        let (pos_a, pos_b, pos_c) = (Vec3::from(vertices[a].position), Vec3::from(vertices[b].position), Vec3::from(vertices[c].position));
+        let (uv_a, uv_b, uv_c) = (Vec2::from(vertices[a].tex_coords), Vec2::from(vertices[b].tex_coords), Vec2::from(vertices[c].tex_coords));
+        let (edge_1, edge_2) = (pos_b - pos_a, pos_c - pos_a);
+        let (delta_uv_1, delta_uv_2) = (uv_b - uv_a, uv_c - uv_a);
+        let determinant = delta_uv_1.x * delta_uv_2.y - delta_uv_2.x * delta_uv_1.y;
+        if determinant.abs() < f32::EPSILON {
+            continue; //the tex coords don't span an area, nothing to go off of
+        }
+        let r = 1.0 / determinant;
+        //tex coords go down in y, flip the bitangent so it points up along the texture
+        let tangent = (edge_1 * delta_uv_2.y - edge_2 * delta_uv_1.y) * r;
+        let bitangent = (edge_2 * delta_uv_1.x - edge_1 * delta_uv_2.x) * -r;
+        for index in [a, b, c] {
+            tangents[index] += tangent;
+            bitangents[index] += bitangent;
+        }
+    }
+    for ((vertex, tangent), bitangent) in vertices.iter_mut().zip(tangents).zip(bitangents) {
+        let normal = Vec3::from(vertex.normal);
+        if normal == Vec3::ZERO || tangent == Vec3::ZERO {
+            continue;
+        }
+        //gram-schmidt so the tangent is perpendicular to the (possibly smoothed) normal
+        let tangent = (tangent - normal * normal.dot(tangent)).normalize_or_zero();
+        let handedness = if normal.cross(tangent).dot(bitangent) < 0.0 { -1.0 } else { 1.0 };
+        vertex.tangent = tangent.into();
+        vertex.bitangent = (normal.cross(tangent) * handedness).normalize_or_zero().into();
+    }
+}
+
 #[derive(Debug)]
 pub struct CameraController {
-    pub amount_left: f32,
-    pub amount_right: f32,
-    pub amount_forward: f32,
-    pub amount_backward: f32,
-    pub amount_up: f32,
-    pub amount_down: f32,
     pub rotate_horizontal: f32,
     pub rotate_vertical: f32,
     pub scroll: f32,
@@ -69,12 +127,6 @@ pub struct CameraController {
 impl CameraController {
     pub fn new(speed: f32, sensitivity: f32) -> Self {
         Self {
-            amount_left: 0.0,
-            amount_right: 0.0,
-            amount_forward: 0.0,
-            amount_backward: 0.0,
-            amount_up: 0.0,
-            amount_down: 0.0,
             rotate_horizontal: 0.0,
             rotate_vertical: 0.0,
             scroll: 0.0,
@@ -83,41 +135,6 @@ impl CameraController {
         }
     }
 
-    pub fn process_keyboard(&mut self, key: VirtualKeyCode, state: ElementState) -> bool {
-        let amount = if state == ElementState::Pressed {
-            1.0
-        } else {
-            0.0
-        };
-        match key {
-            VirtualKeyCode::W | VirtualKeyCode::Up => {
-                self.amount_forward = amount;
-                true
-            }
-            VirtualKeyCode::S | VirtualKeyCode::Down => {
-                self.amount_backward = amount;
-                true
-            }
-            VirtualKeyCode::A | VirtualKeyCode::Left => {
-                self.amount_left = amount;
-                true
-            }
-            VirtualKeyCode::D | VirtualKeyCode::Right => {
-                self.amount_right = amount;
-                true
-            }
-            VirtualKeyCode::Space => {
-                self.amount_up = amount;
-                true
-            }
-            VirtualKeyCode::LShift => {
-                self.amount_down = amount;
-                true
-            }
-            _ => false,
-        }
-    }
-
     pub fn process_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
         self.rotate_horizontal = mouse_dx as f32;
         self.rotate_vertical = mouse_dy as f32;
@@ -1,10 +1,12 @@
-use anyhow::*;
 use image::GenericImageView;
 
+use crate::error::{Result, VertixError};
+
 pub struct Texture {
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
     pub sampler: wgpu::Sampler,
+    pub has_alpha: bool, //true if any texel isn't fully opaque
 }
 
 impl Texture {
@@ -49,6 +51,38 @@ impl Texture {
             texture,
             view,
             sampler,
+            has_alpha: false,
+        }
+    }
+
+    pub fn create_render_target(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        label: &str,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+
+        Self {
+            texture,
+            view,
+            sampler,
+            has_alpha: false,
         }
     }
 
@@ -59,8 +93,9 @@ impl Texture {
         label: &str,
         filter_type: wgpu::FilterMode
     ) -> Result<Self> {
-        let img = image::load_from_memory(bytes)?;
-        Self::from_image(device, queue, &img, Some(label), filter_type)
+        let img = image::load_from_memory(bytes)
+            .map_err(|source| VertixError::ImageDecode { path: label.to_string(), source })?;
+        Ok(Self::from_image(device, queue, &img, Some(label), filter_type))
     }
 
     pub fn from_image(
@@ -69,16 +104,38 @@ impl Texture {
         img: &image::DynamicImage,
         label: Option<&str>,
         filter_type: wgpu::FilterMode
-    ) -> Result<Self> {
+    ) -> Self {
+        Self::from_image_with_format(device, queue, img, label, filter_type, wgpu::TextureFormat::Rgba8UnormSrgb)
+    }
+
+    //for data textures like normal maps, where the values shouldn't be gamma corrected
+    pub fn from_image_linear(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+        filter_type: wgpu::FilterMode
+    ) -> Self {
+        Self::from_image_with_format(device, queue, img, label, filter_type, wgpu::TextureFormat::Rgba8Unorm)
+    }
+
+    fn from_image_with_format(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+        filter_type: wgpu::FilterMode,
+        format: wgpu::TextureFormat,
+    ) -> Self {
         let dimensions = img.dimensions();
         let rgba = img.to_rgba8();
+        let has_alpha = rgba.pixels().any(|pixel| pixel.0[3] < 255);
 
         let size = wgpu::Extent3d {
             width: dimensions.0,
             height: dimensions.1,
             depth_or_array_layers: 1,
         };
-        let format = wgpu::TextureFormat::Rgba8UnormSrgb;
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label,
             size,
@@ -117,10 +174,75 @@ impl Texture {
             ..Default::default()
         });
 
-        Ok(Self {
+        Self {
             texture,
             view,
             sampler,
-        })
+            has_alpha,
+        }
+    }
+}
+
+//copies a rgba or bgra texture back to the cpu, blocks until the gpu is done
+pub fn read_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    format: wgpu::TextureFormat,
+) -> image::RgbaImage {
+    let width = texture.width();
+    let height = texture.height();
+    //rows in a buffer copy have to be padded to a multiple of 256 bytes
+    let unpadded_bytes_per_row = 4 * width;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Readback Encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            aspect: wgpu::TextureAspect::All,
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let buffer_slice = buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver.recv().unwrap().unwrap();
+
+    let data = buffer_slice.get_mapped_range();
+    let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+    for row in data.chunks(padded_bytes_per_row as usize) {
+        pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+    }
+    drop(data);
+    buffer.unmap();
+    if matches!(format, wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb) {
+        for pixel in pixels.chunks_mut(4) {
+            pixel.swap(0, 2);
+        }
     }
+    image::RgbaImage::from_raw(width, height, pixels).unwrap()
 }
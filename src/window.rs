@@ -1,8 +1,12 @@
+use bevy_ecs::system::Resource;
+
+use crate::error::{Result, VertixError};
 use wgpu::{Adapter, Surface};
 use winit::{
     dpi::{PhysicalSize, PhysicalPosition},
     event_loop::EventLoop,
-    window::{Fullscreen, WindowBuilder},
+    monitor::MonitorHandle,
+    window::{Fullscreen, Icon, WindowBuilder},
 };
 pub struct Window {
     pub window: winit::window::Window,
@@ -10,8 +14,79 @@ pub struct Window {
     pub adapter: Adapter,
     pub surface: Surface,
 }
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WindowMode {
+    Windowed,
+    BorderlessFullscreen,
+    ExclusiveFullscreen, //uses the monitor's video mode closest to WindowConfig::size
+}
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WindowIcon {
+    pub rgba: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+impl WindowIcon {
+    pub fn from_image(image: &image::DynamicImage) -> Self {
+        let rgba = image.to_rgba8();
+        Self { width: rgba.width(), height: rgba.height(), rgba: rgba.into_raw() }
+    }
+    fn to_winit(&self) -> Option<Icon> {
+        match Icon::from_rgba(self.rgba.clone(), self.width, self.height) {
+            Ok(icon) => Some(icon),
+            Err(error) => {
+                log::warn!("Invalid window icon: {:?}", error);
+                None
+            }
+        }
+    }
+}
+//passed to AppBuilder::with_window_config at startup, then lives in the world as a resource
+//change it from a system and the window gets updated at the end of the frame
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct WindowConfig {
+    pub title: String,
+    pub size: PhysicalSize<u32>, //ignored in borderless fullscreen
+    pub mode: WindowMode,
+    pub present_mode: wgpu::PresentMode, //falls back to Fifo if the surface doesn't support it
+    pub resizable: bool,
+    pub icon: Option<WindowIcon>,
+}
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: "Vertix".to_string(),
+            size: PhysicalSize::new(800, 600),
+            mode: WindowMode::BorderlessFullscreen,
+            present_mode: wgpu::PresentMode::AutoVsync,
+            resizable: true,
+            icon: None,
+        }
+    }
+}
+fn fullscreen_for(mode: WindowMode, monitor: Option<MonitorHandle>, size: PhysicalSize<u32>) -> Option<Fullscreen> {
+    match mode {
+        WindowMode::Windowed => None,
+        WindowMode::BorderlessFullscreen => Some(Fullscreen::Borderless(monitor)),
+        WindowMode::ExclusiveFullscreen => {
+            let video_mode = monitor.and_then(|monitor| {
+                monitor.video_modes().min_by_key(|video_mode| {
+                    let mode_size = video_mode.size();
+                    mode_size.width.abs_diff(size.width) + mode_size.height.abs_diff(size.height)
+                })
+            });
+            match video_mode {
+                Some(video_mode) => Some(Fullscreen::Exclusive(video_mode)),
+                None => {
+                    log::warn!("No video modes for exclusive fullscreen, using borderless");
+                    Some(Fullscreen::Borderless(None))
+                }
+            }
+        }
+    }
+}
 impl Window {
-    pub async fn new(mouse_lock: bool) -> (Self, EventLoop<()>) {
+    pub async fn new(mouse_lock: bool, config: &WindowConfig) -> Result<(Self, EventLoop<()>)> {
         cfg_if::cfg_if! {
             if #[cfg(target_arch = "wasm32")] {
                 std::panic::set_hook(Box::new(console_error_panic_hook::hook));
@@ -22,20 +97,16 @@ impl Window {
         }
 
         let event_loop = EventLoop::new();
-        let monitor = event_loop.primary_monitor().unwrap();
-        let video_mode = monitor.video_modes().next();
-        let size = video_mode
-            .clone()
-            .map_or(PhysicalSize::new(800, 600), |vm| vm.size());
+        let monitor = event_loop.primary_monitor();
         let window = WindowBuilder::new()
-            .with_title("WGPUCraft")
-            .with_fullscreen(Some(Fullscreen::Borderless(Some(monitor))))
-            .build(&event_loop)
-            .unwrap();
+            .with_title(&config.title)
+            .with_inner_size(config.size)
+            .with_resizable(config.resizable)
+            .with_window_icon(config.icon.as_ref().and_then(WindowIcon::to_winit))
+            .with_fullscreen(fullscreen_for(config.mode, monitor, config.size))
+            .build(&event_loop)?;
+        let size = window.inner_size();
 
-        if window.fullscreen().is_none() {
-            window.set_inner_size(PhysicalSize::new(512, 512));
-        }
         if mouse_lock {
             window.set_cursor_visible(false);
         }
@@ -92,7 +163,7 @@ impl Window {
         //
         // The surface needs to live as long as the window that created it.
         // State owns the window so this should be safe.
-        let surface = unsafe { instance.create_surface(&window) }.unwrap();
+        let surface = unsafe { instance.create_surface(&window) }?;
         window.set_visible(true);
         if mouse_lock {
             #[cfg(any(target_arch = "wasm32", target_os = "macos"))]
@@ -122,9 +193,9 @@ impl Window {
                 force_fallback_adapter: false,
             })
             .await
-            .unwrap();
+            .ok_or(VertixError::AdapterNotFound)?;
         log::warn!("device and queue");
-        (
+        Ok((
             Self {
                 window,
                 size,
@@ -132,7 +203,35 @@ impl Window {
                 surface,
             },
             event_loop,
-        )
+        ))
+    }
+    //applies whatever changed between two configs, called by State when the WindowConfig resource is edited
+    pub fn apply_config(&self, old: &WindowConfig, new: &WindowConfig) {
+        if old.title != new.title {
+            self.window.set_title(&new.title);
+        }
+        if old.resizable != new.resizable {
+            self.window.set_resizable(new.resizable);
+        }
+        if old.icon != new.icon {
+            self.window.set_window_icon(new.icon.as_ref().and_then(WindowIcon::to_winit));
+        }
+        if old.mode != new.mode || (old.size != new.size && new.mode == WindowMode::ExclusiveFullscreen) {
+            self.window.set_fullscreen(fullscreen_for(new.mode, self.window.current_monitor(), new.size));
+        }
+        if old.size != new.size && new.mode == WindowMode::Windowed {
+            self.window.set_inner_size(new.size);
+        }
+    }
+    pub fn supported_present_mode(&self, present_mode: wgpu::PresentMode) -> wgpu::PresentMode {
+        let supported = self.surface.get_capabilities(&self.adapter).present_modes;
+        let is_auto = matches!(present_mode, wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync);
+        if is_auto || supported.contains(&present_mode) {
+            present_mode
+        } else {
+            log::warn!("Present mode {:?} not supported, using Fifo", present_mode);
+            wgpu::PresentMode::Fifo
+        }
     }
     pub fn normalize_position(&self, pos: &PhysicalPosition<f64>) -> PhysicalPosition<f32>{ 
         let normalized_x = ((2.0 * pos.x as f32) / self.size.width as f32) - 1.0; //normalize to be between -1 and 1 instead of 0-1
@@ -0,0 +1,42 @@
+use glam::Vec3;
+use instant::Duration;
+use vertix::{app_resource::App, prelude::*, shapes::cube};
+
+fn center_pixel(state: &mut State) -> [u8; 4] {
+    state.run_frame(Duration::from_millis(16));
+    let frame = state.render_to_image();
+    frame.get_pixel(frame.width() / 2, frame.height() / 2).0
+}
+
+//one State for every mode, a second gpu device in one process isn't reliable on every driver
+#[test]
+fn materials_draw_with_the_alpha_mode_they_are_set_to() {
+    let camera = Camera::new(Vec3::new(0.0, 0.0, 5.0), f32::to_radians(-90.0), 0.0);
+    let mut state = pollster::block_on(AppBuilder::new(env!("OUT_DIR")).with_camera(camera).build_headless(32, 32, true)).unwrap();
+    let background = center_pixel(&mut state);
+    //white with no alpha, straight alpha hides it and premultiplied adds it on top
+    let mut instance = Instance::default();
+    let app = state.world.resource_mut::<App>().into_inner();
+    let material = app.asset_server.compile_color_material([255, 255, 255, 0]);
+    app.asset_server.build_mesh(cube(2.0, 2.0, 2.0), vec![&mut instance], &material, false);
+    let prefab = instance.prefab_index;
+    assert_eq!(app.asset_server.materials.get(&material).unwrap().alpha_mode(), AlphaMode::Blend);
+    state.world.spawn(instance);
+    assert_eq!(center_pixel(&mut state), background);
+
+    let asset_server = &mut state.world.resource_mut::<App>().into_inner().asset_server;
+    asset_server.set_alpha_mode(&material, AlphaMode::Premultiplied);
+    assert!(asset_server.is_transparent(&asset_server.prefab_slab[prefab]));
+    let added = center_pixel(&mut state);
+    assert!((0..3).all(|channel| added[channel] > background[channel]), "{:?} over {:?}", added, background);
+
+    //masked materials stay in the opaque pass, with the see through parts cut out
+    let asset_server = &mut state.world.resource_mut::<App>().into_inner().asset_server;
+    asset_server.set_alpha_mode(&material, AlphaMode::Mask(0.5));
+    assert!(!asset_server.is_transparent(&asset_server.prefab_slab[prefab]));
+    assert_eq!(center_pixel(&mut state), background);
+    let asset_server = &mut state.world.resource_mut::<App>().into_inner().asset_server;
+    asset_server.set_alpha_mode(&material, AlphaMode::Mask(0.0));
+    let opaque = center_pixel(&mut state);
+    assert_ne!(opaque, background);
+}
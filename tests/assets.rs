@@ -0,0 +1,63 @@
+use vertix::{app_resource::App, handle::Assets, prelude::*};
+
+#[test]
+fn handles_keep_assets_alive_until_the_last_one_drops() {
+    let mut assets = Assets::default();
+    let a = assets.add("a".to_string());
+    let b = assets.add("b".to_string());
+    assert_ne!(a, b);
+    let a_again = a.clone();
+    assert_eq!(a_again, a);
+    assert_eq!(a.ref_count(), 2);
+
+    drop(a);
+    assets.free_unused();
+    assert_eq!(assets.get(&a_again).map(String::as_str), Some("a"));
+    drop(a_again);
+    drop(b);
+    assets.free_unused();
+    assert!(assets.is_empty());
+}
+
+#[test]
+fn keyed_assets_are_only_stored_once() {
+    let mut assets = Assets::default();
+    let texture = assets.add_keyed("grass.png", 1);
+    let same = assets.get_keyed("grass.png").unwrap();
+    assert_eq!(same, texture);
+    assert_eq!(texture.ref_count(), 2);
+    assert!(assets.get_keyed("stone.png").is_none());
+    *assets.get_mut(&same).unwrap() = 2;
+    assert_eq!(assets.get(&texture), Some(&2));
+
+    //once nothing holds it the key misses, and loading it again stores a fresh copy
+    drop(texture);
+    drop(same);
+    assert!(assets.get_keyed("grass.png").is_none());
+    let reloaded = assets.add_keyed("grass.png", 3);
+    assets.free_unused();
+    assert_eq!(assets.len(), 1);
+    assert_eq!(assets.get_keyed("grass.png"), Some(reloaded.clone()));
+    assert_eq!(assets.get(&reloaded), Some(&3));
+}
+
+#[test]
+fn asset_server_dedups_and_frees_textures_and_materials() {
+    let mut state = pollster::block_on(AppBuilder::new(env!("OUT_DIR")).build_headless(64, 64, true)).unwrap();
+    let asset_server = &mut state.world.resource_mut::<App>().into_inner().asset_server;
+    let material = pollster::block_on(asset_server.compile_material("cube-diffuse.jpg", wgpu::FilterMode::Linear)).unwrap();
+    let same = pollster::block_on(asset_server.compile_material("cube-diffuse.jpg", wgpu::FilterMode::Linear)).unwrap();
+    let texture = pollster::block_on(asset_server.load_texture("cube-diffuse.jpg", wgpu::FilterMode::Linear)).unwrap();
+    assert_eq!(material, same);
+    let (materials, textures) = (asset_server.materials.len(), asset_server.textures.len());
+
+    //the material holds its texture, so both go once the material and the texture handle are gone
+    drop(material);
+    drop(same);
+    asset_server.free_unused_assets();
+    assert_eq!(asset_server.materials.len(), materials - 1);
+    assert_eq!(asset_server.textures.len(), textures);
+    drop(texture);
+    asset_server.free_unused_assets();
+    assert_eq!(asset_server.textures.len(), textures - 1);
+}
@@ -0,0 +1,246 @@
+mod common;
+
+use std::collections::HashSet;
+
+use bevy_ecs::{prelude::*, system::SystemState};
+use glam::{Vec2, Vec3};
+use vertix::{
+    collision::{
+        broadphase::{update_broadphase, Aabb, Broadphase, DynamicAabbTree, UniformGrid2D},
+        spatial_query::{QueryFilter, SpatialQuery},
+        structs_2d::{Box2D, Circle},
+        structs_3d::{Collider3D, Ray, OBB},
+    },
+    instance::Instance,
+};
+
+use common::Rng;
+
+fn random_aabb(rng: &mut Rng, range: f32) -> Aabb {
+    let center = rng.vec3(-range, range);
+    let half_size = rng.vec3(0.1, 2.1);
+    Aabb::new(center - half_size, center + half_size)
+}
+fn sorted_pair(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+#[test]
+fn tree_finds_every_overlap_while_things_move() {
+    let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+    let mut tree = DynamicAabbTree::new();
+    let mut boxes: Vec<Option<(usize, Aabb)>> = (0..300)
+        .map(|i| {
+            let aabb = random_aabb(&mut rng, 20.0);
+            Some((tree.insert(aabb, i), aabb))
+        })
+        .collect();
+    for round in 0..5 {
+        //move most, remove a few
+        for (i, entry) in boxes.iter_mut().enumerate() {
+            let Some((proxy, aabb)) = entry else { continue };
+            if (i + round) % 17 == 0 {
+                assert_eq!(tree.remove(*proxy), i);
+                *entry = None;
+            } else {
+                let offset = rng.vec3(-0.5, 0.5);
+                *aabb = Aabb::new(aabb.min + offset, aabb.max + offset);
+                tree.update(*proxy, *aabb);
+            }
+        }
+        let live: Vec<(usize, Aabb)> = boxes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| entry.map(|(_, aabb)| (i, aabb)))
+            .collect();
+        assert_eq!(tree.len(), live.len());
+        //balanced means logarithmic, a list would be hundreds deep
+        assert!(tree.height() <= 3 * (live.len() as f32).log2().ceil() as u32, "tree is {} deep", tree.height());
+
+        let tree_pairs: HashSet<(usize, usize)> = tree.pairs().into_iter().map(|(a, b)| sorted_pair(a, b)).collect();
+        for (i, (a, a_aabb)) in live.iter().enumerate() {
+            for (b, b_aabb) in &live[i + 1..] {
+                if a_aabb.intersects(b_aabb) {
+                    assert!(tree_pairs.contains(&sorted_pair(*a, *b)), "missed overlap {} {}", a, b);
+                }
+            }
+        }
+        assert_eq!(tree.pairs().len(), tree_pairs.len(), "pairs reported twice");
+
+        let query = random_aabb(&mut rng, 20.0);
+        let mut found = HashSet::new();
+        tree.query_aabb(&query, |_, data| {
+            found.insert(data);
+        });
+        for (i, aabb) in &live {
+            if aabb.intersects(&query) {
+                assert!(found.contains(i));
+            }
+        }
+    }
+}
+
+#[test]
+fn tree_ray_query_finds_every_box_on_the_ray() {
+    let mut rng = Rng(0x853C_49E6_748F_EA9B);
+    let mut tree = DynamicAabbTree::new();
+    let boxes: Vec<Aabb> = (0..500).map(|_| random_aabb(&mut rng, 30.0)).collect();
+    for (i, aabb) in boxes.iter().enumerate() {
+        tree.insert(*aabb, i);
+    }
+    for _ in 0..50 {
+        let origin = rng.vec3(-40.0, 40.0);
+        let direction = rng.vec3(-1.0, 1.0).normalize();
+        let mut found = HashSet::new();
+        tree.query_ray(origin, direction, 50.0, |_, i| {
+            found.insert(i);
+        });
+        for (i, aabb) in boxes.iter().enumerate() {
+            if aabb.ray_entry(origin, direction.recip(), 50.0).is_some() {
+                assert!(found.contains(&i), "ray missed box {}", i);
+            }
+        }
+        assert!(found.len() < boxes.len() / 2, "ray query isn't culling");
+    }
+}
+
+#[test]
+fn grid_pairs_match_brute_force() {
+    let mut rng = Rng(0xDA94_2042_E4DD_58B5);
+    let mut grid = UniformGrid2D::new(1.5);
+    let mut boxes: Vec<(Vec2, Vec2)> = vec![];
+    for i in 0..300 {
+        let center = rng.vec3(-15.0, 15.0).truncate();
+        let half_size = Vec2::new(rng.next_f32(), rng.next_f32()) * 2.0 + 0.05;
+        boxes.push((center - half_size, center + half_size));
+        grid.insert(i, center - half_size, center + half_size);
+    }
+    //move half of them
+    for (i, (min, max)) in boxes.iter_mut().enumerate().step_by(2) {
+        let offset = rng.vec3(-3.0, 3.0).truncate();
+        *min += offset;
+        *max += offset;
+        grid.insert(i, *min, *max);
+    }
+    grid.remove(7);
+    let mut expected = HashSet::new();
+    for (a, (a_min, a_max)) in boxes.iter().enumerate() {
+        for (b, (b_min, b_max)) in boxes.iter().enumerate().skip(a + 1) {
+            if a != 7 && b != 7 && a_min.cmple(*b_max).all() && b_min.cmple(*a_max).all() {
+                expected.insert((a, b));
+            }
+        }
+    }
+    let pairs = grid.pairs();
+    let found: HashSet<(usize, usize)> = pairs.iter().map(|(a, b)| sorted_pair(*a, *b)).collect();
+    assert_eq!(pairs.len(), found.len(), "pairs reported twice");
+    assert_eq!(found, expected);
+    assert_eq!(grid.len(), 299);
+}
+
+#[test]
+fn spatial_query_goes_through_the_broadphase() {
+    let mut world = World::new();
+    world.insert_resource(Broadphase::default());
+    let mut schedule = Schedule::default();
+    schedule.add_systems(update_broadphase);
+    let cube = |z: f32| (Instance { position: Vec3::new(0.0, 0.0, z), ..Default::default() }, Collider3D::OBB(OBB::new(2.0, 2.0, 2.0)));
+    let near = world.spawn(cube(0.0)).id();
+    let far = world.spawn(cube(5.0)).id();
+    let touching = world.spawn(cube(6.5)).id();
+    let circle = world.spawn((Instance::default(), Circle::new(Vec2::ZERO, 1.0, true))).id();
+    schedule.run(&mut world);
+
+    let ray = Ray { origin: Vec3::new(0.0, 0.0, -10.0), direction: Vec3::Z };
+    let mut state = SystemState::<SpatialQuery>::new(&mut world);
+    let query = state.get(&world);
+    let hits: Vec<Entity> = query.cast_ray_all(ray, 100.0, &QueryFilter::default()).iter().map(|hit| hit.entity).collect();
+    assert_eq!(hits, vec![near, far, touching]);
+    let pairs = query.contact_pairs();
+    assert_eq!(pairs.len(), 1);
+    let (a, b, _) = &pairs[0];
+    assert_eq!(HashSet::from([*a, *b]), HashSet::from([far, touching]));
+    let overlaps = query.shape_overlaps(&Collider3D::OBB(OBB::new(1.0, 1.0, 1.0)), None, &QueryFilter::default());
+    assert_eq!(overlaps.len(), 1);
+    assert_eq!(overlaps[0].0, near);
+    let broadphase = world.resource::<Broadphase>();
+    assert_eq!(broadphase.grid.query_point(Vec2::new(0.5, 0.5)), vec![circle]);
+
+    //moved and despawned colliders are picked up on the next update
+    world.get_mut::<Instance>(near).unwrap().position = Vec3::new(50.0, 0.0, 0.0);
+    world.despawn(far);
+    schedule.run(&mut world);
+    let query = state.get(&world);
+    let hits: Vec<Entity> = query.cast_ray_all(ray, 100.0, &QueryFilter::default()).iter().map(|hit| hit.entity).collect();
+    assert_eq!(hits, vec![touching]);
+    assert!(!world.resource::<Broadphase>().contains_3d(far));
+}
+
+#[test]
+fn grid_keeps_huge_and_non_finite_boxes_out_of_the_cells() {
+    let mut grid = UniformGrid2D::new(1.0);
+    grid.insert(0, Vec2::ZERO, Vec2::ONE);
+    //each of these would take billions of cells, or saturate to i32::MAX, if they were bucketed
+    grid.insert(1, Vec2::splat(-1e9), Vec2::splat(1e9));
+    grid.insert(2, Vec2::NEG_INFINITY, Vec2::INFINITY);
+    grid.insert(3, Vec2::new(f32::NAN, 0.0), Vec2::ONE);
+    grid.insert(4, Vec2::new(50.0, 50.0), Vec2::new(51.0, 51.0));
+    assert_eq!(grid.len(), 5);
+
+    let mut at_origin = grid.query_point(Vec2::splat(0.5));
+    at_origin.sort();
+    assert_eq!(at_origin, vec![0, 1, 2]);
+    let mut everything = grid.query_aabb(Vec2::splat(-1e12), Vec2::splat(1e12));
+    everything.sort();
+    assert_eq!(everything, vec![0, 1, 2, 4]);
+    let pairs: HashSet<(usize, usize)> = grid.pairs().into_iter().map(|(a, b)| sorted_pair(a, b)).collect();
+    assert_eq!(pairs, HashSet::from([(0, 1), (0, 2), (1, 2), (1, 4), (2, 4)]));
+    assert_eq!(grid.pairs().len(), pairs.len(), "pairs reported twice");
+
+    //shrinking one back down puts it in the cells
+    grid.insert(1, Vec2::new(50.5, 50.5), Vec2::new(52.0, 52.0));
+    grid.remove(2);
+    let pairs: HashSet<(usize, usize)> = grid.pairs().into_iter().map(|(a, b)| sorted_pair(a, b)).collect();
+    assert_eq!(pairs, HashSet::from([(1, 4)]));
+    assert_eq!(grid.query_point(Vec2::splat(0.5)), vec![0]);
+}
+
+#[test]
+fn spatial_query_2d_matches_with_and_without_the_grid() {
+    let mut world = World::new();
+    let square = world
+        .spawn((Instance { position: Vec3::new(2.0, 0.0, 0.0), ..Default::default() }, Box2D::new(Vec2::splat(-1.0), Vec2::ONE)))
+        .id();
+    let circle = world.spawn((Instance::default(), Circle::new(Vec2::ZERO, 1.0, true))).id();
+    //the box around this circle covers the point below, the circle doesn't
+    let scaled = world
+        .spawn((
+            Instance { position: Vec3::new(0.0, 4.0, 0.0), scale: Vec3::splat(2.0), ..Default::default() },
+            Circle::new(Vec2::ZERO, 1.0, true),
+        ))
+        .id();
+    let disabled = world.spawn((Instance::default(), Circle::new(Vec2::ZERO, 5.0, false))).id();
+
+    let check = |world: &mut World| {
+        let mut state = SystemState::<SpatialQuery>::new(world);
+        let query = state.get(world);
+        assert_eq!(query.point_overlaps_2d(Vec2::new(2.5, 0.5), &QueryFilter::default()), vec![square]);
+        assert!(query.point_overlaps_2d(Vec2::new(1.8, 5.8), &QueryFilter::default()).is_empty());
+        assert_eq!(query.point_overlaps_2d(Vec2::new(0.0, 5.0), &QueryFilter::default()), vec![scaled]);
+        let excluded = QueryFilter::default().exclude(square);
+        assert!(query.point_overlaps_2d(Vec2::new(2.5, 0.5), &excluded).is_empty());
+        let mut in_box = query.aabb_overlaps_2d(Vec2::new(-0.5, -0.5), Vec2::new(1.5, 3.0), &QueryFilter::default());
+        in_box.sort();
+        assert_eq!(in_box, vec![square, circle, scaled]);
+        let pairs = query.contact_pairs_2d();
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(HashSet::from([pairs[0].0, pairs[0].1]), HashSet::from([square, circle]));
+        assert!(!query.point_overlaps_2d(Vec2::ZERO, &QueryFilter::default()).contains(&disabled));
+    };
+    check(&mut world);
+    world.insert_resource(Broadphase::default());
+    let mut schedule = Schedule::default();
+    schedule.add_systems(update_broadphase);
+    schedule.run(&mut world);
+    check(&mut world);
+}
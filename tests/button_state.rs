@@ -0,0 +1,65 @@
+use instant::Duration;
+use vertix::{app_resource::App, prelude::*, replay::RecordedInput, resources::ButtonState};
+
+#[test]
+fn presses_last_until_released() {
+    let mut buttons = ButtonState::default();
+    assert!(buttons.press(VirtualKeyCode::W));
+    assert!(buttons.held(VirtualKeyCode::W) && buttons.just_pressed(VirtualKeyCode::W));
+    buttons.next_frame();
+    assert!(buttons.held(VirtualKeyCode::W) && !buttons.just_pressed(VirtualKeyCode::W));
+    buttons.next_frame();
+    assert!(buttons.held(VirtualKeyCode::W));
+
+    buttons.release(VirtualKeyCode::W);
+    assert!(!buttons.held(VirtualKeyCode::W) && buttons.just_released(VirtualKeyCode::W));
+    buttons.next_frame();
+    assert!(!buttons.just_released(VirtualKeyCode::W));
+    //releasing something that wasn't held isn't a release
+    buttons.release(VirtualKeyCode::S);
+    assert!(!buttons.just_released(VirtualKeyCode::S));
+}
+
+#[test]
+fn key_repeats_are_not_new_presses() {
+    let mut buttons = ButtonState::default();
+    buttons.set(VirtualKeyCode::A, ElementState::Pressed);
+    buttons.next_frame();
+    assert!(!buttons.press(VirtualKeyCode::A));
+    assert!(!buttons.just_pressed(VirtualKeyCode::A));
+    //pressed and released within one frame shows up as both
+    buttons.set(VirtualKeyCode::B, ElementState::Pressed);
+    buttons.set(VirtualKeyCode::B, ElementState::Released);
+    assert!(buttons.just_pressed(VirtualKeyCode::B) && buttons.just_released(VirtualKeyCode::B));
+    assert!(!buttons.held(VirtualKeyCode::B));
+}
+
+#[test]
+fn release_all_lets_go_of_everything() {
+    let mut buttons = ButtonState::default();
+    buttons.press(MouseButton::Left);
+    buttons.press(MouseButton::Other(7));
+    buttons.next_frame();
+    buttons.release_all();
+    assert_eq!(buttons.all_held().count(), 0);
+    assert!(buttons.just_released(MouseButton::Left) && buttons.just_released(MouseButton::Other(7)));
+}
+
+#[test]
+fn window_events_keep_held_state_until_focus_is_lost() {
+    let mut state = pollster::block_on(AppBuilder::new(env!("OUT_DIR")).build_headless(64, 64, true)).unwrap();
+    state.apply_input(&RecordedInput::Key { scancode: 17, key: VirtualKeyCode::W, state: ElementState::Pressed });
+    state.apply_input(&RecordedInput::MouseButton { button: MouseButton::Middle, state: ElementState::Pressed });
+    let window_events = &state.world.resource::<App>().window_events;
+    assert!(window_events.key_just_pressed(VirtualKeyCode::W) && window_events.mouse_just_pressed(MouseButton::Middle));
+    state.run_frame(Duration::from_millis(16));
+    state.run_frame(Duration::from_millis(16));
+    let window_events = &state.world.resource::<App>().window_events;
+    assert!(window_events.key_held(VirtualKeyCode::W) && !window_events.key_just_pressed(VirtualKeyCode::W));
+    assert!(window_events.mouse_held(MouseButton::Middle));
+
+    state.apply_input(&RecordedInput::Focused(false));
+    let window_events = &state.world.resource::<App>().window_events;
+    assert!(!window_events.key_held(VirtualKeyCode::W) && window_events.key_just_released(VirtualKeyCode::W));
+    assert!(!window_events.mouse_held(MouseButton::Middle));
+}
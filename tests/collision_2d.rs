@@ -0,0 +1,41 @@
+use glam::{Vec2, Vec3};
+use vertix::{collision::structs_2d::{Box2D, Circle}, instance::Instance, resources::WindowEvents};
+use winit::dpi::PhysicalPosition;
+
+#[test]
+fn circles_follow_their_instance() {
+    let circle = Circle::new(Vec2::new(1.0, 0.0), 1.0, true);
+    let instance = Instance { position: Vec3::new(10.0, 0.0, 0.0), scale: Vec3::new(2.0, 0.5, 1.0), ..Default::default() };
+    //center lands at 12, radius grows with the larger scale to 2
+    assert!(circle.check_collision(&PhysicalPosition::new(13.5, 0.0), &instance));
+    assert!(circle.check_collision(&PhysicalPosition::new(12.0, 1.5), &instance));
+    assert!(!circle.check_collision(&PhysicalPosition::new(1.0, 0.0), &instance));
+    assert!(!circle.check_collision(&PhysicalPosition::new(14.5, 0.0), &instance));
+    let disabled = Circle::new(Vec2::ZERO, 1.0, false);
+    assert!(!disabled.check_collision(&PhysicalPosition::new(0.0, 0.0), &Instance::default()));
+}
+
+#[test]
+fn boxes_under_the_cursor_are_where_they_are_drawn() {
+    let collider = Box2D::new(Vec2::new(-0.5, -0.5), Vec2::new(0.5, 0.5));
+    let instance = Instance { position: Vec3::new(0.6, -0.2, 0.0), scale: Vec3::new(0.4, 0.2, 1.0), is_world_space: false, ..Default::default() };
+    //covers 0.4 to 0.8 across and -0.3 to -0.1 up in screen space
+    let (min, max) = collider.bounds(&instance);
+    assert!(min.abs_diff_eq(Vec2::new(0.4, -0.3), 1e-6) && max.abs_diff_eq(Vec2::new(0.8, -0.1), 1e-6), "{:?} {:?}", min, max);
+    //twice as wide as it's tall, so the cursor's x is doubled into screen space
+    let mut window_events = WindowEvents::new(200, 100);
+    let mut hovering = |x: f32, y: f32| {
+        window_events.screen_mouse_pos = PhysicalPosition::new(x, y);
+        collider.check_collision(&instance, &window_events)
+    };
+    assert!(hovering(0.3, -0.2));
+    assert!(hovering(0.21, -0.29));
+    assert!(hovering(0.39, -0.11));
+    //left of it, right of it, above and below
+    assert!(!hovering(0.19, -0.2));
+    assert!(!hovering(0.41, -0.2));
+    assert!(!hovering(0.3, -0.05));
+    assert!(!hovering(0.3, -0.35));
+    //where the old math put it, the mouse plus the position compared against the box scaled about the origin
+    assert!(!hovering(-0.6, 0.2));
+}
@@ -0,0 +1,275 @@
+use glam::{Mat4, Quat, Vec3};
+use std::f32::consts::{FRAC_PI_4, SQRT_2};
+use vertix::{
+    collision::{
+        collision_fns_3d::{
+            oriented_bounding_box_with_oriented_bounding_box, sphere_with_oriented_bounding_box, sphere_with_sphere,
+        },
+        structs_3d::{Collider3D, ColliderResult, Contact, Ray, Sphere, OBB},
+    },
+    hierarchy::GlobalTransform,
+};
+
+const EPSILON: f32 = 1e-4;
+
+fn assert_vec_eq(actual: Vec3, expected: Vec3) {
+    assert!(actual.abs_diff_eq(expected, EPSILON), "expected {}, got {}", expected, actual);
+}
+fn assert_close(actual: f32, expected: f32) {
+    assert!((actual - expected).abs() < EPSILON, "expected {}, got {}", expected, actual);
+}
+fn transform(translation: Vec3, rotation: Quat) -> GlobalTransform {
+    GlobalTransform(Mat4::from_rotation_translation(rotation, translation))
+}
+fn matrix(translation: Vec3, rotation: Quat) -> [[f32; 4]; 4] {
+    transform(translation, rotation).0.to_cols_array_2d()
+}
+fn unit_cube_contact(b_translation: Vec3, b_rotation: Quat) -> Option<Contact> {
+    let cube = OBB::new(2.0, 2.0, 2.0);
+    oriented_bounding_box_with_oriented_bounding_box(
+        cube.aabb_min,
+        cube.aabb_max,
+        matrix(Vec3::ZERO, Quat::IDENTITY),
+        cube.aabb_min,
+        cube.aabb_max,
+        matrix(b_translation, b_rotation),
+    )
+}
+
+#[test]
+fn spheres_apart_do_not_collide() {
+    assert_eq!(sphere_with_sphere(Vec3::ZERO, 1.0, Vec3::new(2.1, 0.0, 0.0), 1.0), None);
+}
+
+#[test]
+fn overlapping_spheres() {
+    let contact = sphere_with_sphere(Vec3::ZERO, 1.0, Vec3::new(1.5, 0.0, 0.0), 1.0).unwrap();
+    assert_vec_eq(contact.normal, Vec3::X);
+    assert_close(contact.penetration, 0.5);
+    assert_eq!(contact.points.len(), 1);
+    assert_vec_eq(contact.points[0], Vec3::new(0.75, 0.0, 0.0));
+}
+
+#[test]
+fn concentric_spheres_still_get_a_normal() {
+    let contact = sphere_with_sphere(Vec3::ONE, 1.0, Vec3::ONE, 0.5).unwrap();
+    assert_vec_eq(contact.normal, Vec3::Y);
+    assert_close(contact.penetration, 1.5);
+}
+
+#[test]
+fn sphere_touching_box_face() {
+    let cube = OBB::new(2.0, 2.0, 2.0);
+    let contact = sphere_with_oriented_bounding_box(
+        Vec3::new(1.5, 0.0, 0.0),
+        1.0,
+        cube.aabb_min,
+        cube.aabb_max,
+        matrix(Vec3::ZERO, Quat::IDENTITY),
+    )
+    .unwrap();
+    assert_vec_eq(contact.normal, -Vec3::X);
+    assert_close(contact.penetration, 0.5);
+    assert_vec_eq(contact.points[0], Vec3::new(1.0, 0.0, 0.0));
+}
+
+#[test]
+fn sphere_near_box_corner_misses() {
+    let cube = OBB::new(2.0, 2.0, 2.0);
+    let contact = sphere_with_oriented_bounding_box(
+        Vec3::new(1.5, 1.5, 0.0),
+        0.6,
+        cube.aabb_min,
+        cube.aabb_max,
+        matrix(Vec3::ZERO, Quat::IDENTITY),
+    );
+    assert_eq!(contact, None);
+}
+
+#[test]
+fn sphere_center_inside_box_leaves_through_nearest_face() {
+    let cube = OBB::new(2.0, 2.0, 2.0);
+    let contact = sphere_with_oriented_bounding_box(
+        Vec3::new(0.0, -0.8, 0.0),
+        0.5,
+        cube.aabb_min,
+        cube.aabb_max,
+        matrix(Vec3::ZERO, Quat::IDENTITY),
+    )
+    .unwrap();
+    assert_vec_eq(contact.normal, Vec3::Y);
+    assert_close(contact.penetration, 0.7);
+    assert_vec_eq(contact.points[0], Vec3::new(0.0, -1.0, 0.0));
+}
+
+#[test]
+fn sphere_against_rotated_box() {
+    //a box turned 45 degrees around z has a corner pointing up at sqrt(2)
+    let cube = OBB::new(2.0, 2.0, 2.0);
+    let contact = sphere_with_oriented_bounding_box(
+        Vec3::new(0.0, SQRT_2 + 0.4, 0.0),
+        0.5,
+        cube.aabb_min,
+        cube.aabb_max,
+        matrix(Vec3::ZERO, Quat::from_rotation_z(FRAC_PI_4)),
+    )
+    .unwrap();
+    assert_vec_eq(contact.normal, -Vec3::Y);
+    assert_close(contact.penetration, 0.1);
+    assert_vec_eq(contact.points[0], Vec3::new(0.0, SQRT_2, 0.0));
+}
+
+#[test]
+fn boxes_apart_do_not_collide() {
+    assert_eq!(unit_cube_contact(Vec3::new(2.1, 0.0, 0.0), Quat::IDENTITY), None);
+    assert_eq!(unit_cube_contact(Vec3::new(1.5, 2.1, 1.5), Quat::IDENTITY), None);
+}
+
+#[test]
+fn boxes_face_to_face() {
+    let contact = unit_cube_contact(Vec3::new(1.5, 0.0, 0.0), Quat::IDENTITY).unwrap();
+    assert_vec_eq(contact.normal, Vec3::X);
+    assert_close(contact.penetration, 0.5);
+    //the whole overlapping face touches, one point per corner
+    assert_eq!(contact.points.len(), 4);
+    for point in &contact.points {
+        assert_close(point.x, 0.5);
+        assert_close(point.y.abs(), 1.0);
+        assert_close(point.z.abs(), 1.0);
+    }
+}
+
+#[test]
+fn offset_boxes_only_touch_where_faces_overlap() {
+    let contact = unit_cube_contact(Vec3::new(1.0, 1.9, 0.0), Quat::IDENTITY).unwrap();
+    assert_vec_eq(contact.normal, Vec3::Y);
+    assert_close(contact.penetration, 0.1);
+    assert_eq!(contact.points.len(), 4);
+    for point in &contact.points {
+        assert!(point.x >= -EPSILON && point.x <= 1.0 + EPSILON, "{} is outside the overlap", point);
+    }
+}
+
+#[test]
+fn rotated_box_corner_in_face() {
+    //turned 45 degrees around y its edge points at the other box, reaching sqrt(2) from its center
+    let rotation = Quat::from_rotation_y(FRAC_PI_4);
+    let contact = unit_cube_contact(Vec3::new(2.3, 0.0, 0.0), rotation).unwrap();
+    assert_vec_eq(contact.normal, Vec3::X);
+    assert_close(contact.penetration, 1.0 + SQRT_2 - 2.3);
+    for point in &contact.points {
+        assert_close(point.x, 2.3 - SQRT_2);
+        assert_close(point.z, 0.0);
+    }
+    assert_eq!(unit_cube_contact(Vec3::new(2.5, 0.0, 0.0), rotation), None);
+}
+
+#[test]
+fn crossed_edges() {
+    //both boxes stand on an edge, one along z and one along x, so only the edges touch
+    let cube = OBB::new(2.0, 2.0, 2.0);
+    let height = 2.0 * SQRT_2 - 0.1;
+    let contact = oriented_bounding_box_with_oriented_bounding_box(
+        cube.aabb_min,
+        cube.aabb_max,
+        matrix(Vec3::ZERO, Quat::from_rotation_z(FRAC_PI_4)),
+        cube.aabb_min,
+        cube.aabb_max,
+        matrix(Vec3::new(0.0, height, 0.0), Quat::from_rotation_x(FRAC_PI_4)),
+    )
+    .unwrap();
+    assert_vec_eq(contact.normal, Vec3::Y);
+    assert_close(contact.penetration, 0.1);
+    assert_eq!(contact.points.len(), 1);
+    assert_vec_eq(contact.points[0], Vec3::new(0.0, SQRT_2 - 0.05, 0.0));
+}
+
+#[test]
+fn box_mesh_not_centered_on_its_origin() {
+    let a = OBB { aabb_min: Vec3::ZERO, aabb_max: Vec3::splat(2.0) };
+    let b = OBB::new(2.0, 2.0, 2.0);
+    let contact = oriented_bounding_box_with_oriented_bounding_box(
+        a.aabb_min,
+        a.aabb_max,
+        matrix(Vec3::ZERO, Quat::IDENTITY),
+        b.aabb_min,
+        b.aabb_max,
+        matrix(Vec3::new(1.0, 1.0, 2.5), Quat::IDENTITY),
+    )
+    .unwrap();
+    assert_vec_eq(contact.normal, Vec3::Z);
+    assert_close(contact.penetration, 0.5);
+}
+
+#[test]
+fn scaled_box() {
+    let cube = OBB::new(2.0, 2.0, 2.0);
+    let scaled = Mat4::from_scale_rotation_translation(Vec3::new(3.0, 1.0, 1.0), Quat::IDENTITY, Vec3::ZERO);
+    let contact = oriented_bounding_box_with_oriented_bounding_box(
+        cube.aabb_min,
+        cube.aabb_max,
+        scaled.to_cols_array_2d(),
+        cube.aabb_min,
+        cube.aabb_max,
+        matrix(Vec3::new(3.5, 0.0, 0.0), Quat::IDENTITY),
+    )
+    .unwrap();
+    assert_vec_eq(contact.normal, Vec3::X);
+    assert_close(contact.penetration, 0.5);
+}
+
+#[test]
+fn collider_contacts_point_from_self_to_other() {
+    let obb = Collider3D::OBB(OBB::new(2.0, 2.0, 2.0));
+    let sphere = Collider3D::Sphere(Sphere { center: Vec3::ZERO, radius: 1.0 });
+    let sphere_transform = transform(Vec3::new(1.5, 0.0, 0.0), Quat::IDENTITY);
+
+    let ColliderResult::Contact(box_first) = obb.check_collision(None, &sphere, Some(&sphere_transform)) else {
+        panic!("expected a contact");
+    };
+    let ColliderResult::Contact(sphere_first) = sphere.check_collision(Some(&sphere_transform), &obb, None) else {
+        panic!("expected a contact");
+    };
+    assert_vec_eq(box_first.normal, Vec3::X);
+    assert_vec_eq(sphere_first.normal, -Vec3::X);
+    assert_close(box_first.penetration, sphere_first.penetration);
+
+    let far = transform(Vec3::new(5.0, 0.0, 0.0), Quat::IDENTITY);
+    assert_eq!(obb.check_collision(None, &sphere, Some(&far)), ColliderResult::NoCollision);
+    assert_eq!(obb.check_collision(None, &obb, Some(&far)), ColliderResult::NoCollision);
+}
+
+#[test]
+fn ray_first_uses_the_other_transform() {
+    let ray = Collider3D::Ray(Ray { origin: Vec3::new(0.0, 0.0, -10.0), direction: Vec3::Z });
+    let obb = Collider3D::OBB(OBB::new(2.0, 2.0, 2.0));
+    let moved_away = transform(Vec3::new(5.0, 0.0, 0.0), Quat::IDENTITY);
+    assert_eq!(ray.check_collision(None, &obb, Some(&moved_away)), ColliderResult::NoCollision);
+}
+
+#[test]
+fn zero_scale_boxes_are_flat_not_nan() {
+    let cube = OBB::new(2.0, 2.0, 2.0);
+    //squashed flat along y, like a scale animation passing through 0
+    let flat = Mat4::from_scale(Vec3::new(1.0, 0.0, 1.0)).to_cols_array_2d();
+    let contact = sphere_with_oriented_bounding_box(Vec3::new(0.0, 0.5, 0.0), 1.0, cube.aabb_min, cube.aabb_max, flat).unwrap();
+    assert_vec_eq(contact.normal, -Vec3::Y);
+    assert_close(contact.penetration, 0.5);
+    assert_eq!(sphere_with_oriented_bounding_box(Vec3::new(0.0, 1.5, 0.0), 1.0, cube.aabb_min, cube.aabb_max, flat), None);
+
+    let contact = oriented_bounding_box_with_oriented_bounding_box(
+        cube.aabb_min,
+        cube.aabb_max,
+        matrix(Vec3::new(0.0, 0.5, 0.0), Quat::IDENTITY),
+        cube.aabb_min,
+        cube.aabb_max,
+        flat,
+    )
+    .unwrap();
+    assert!(contact.normal.is_finite() && contact.penetration.is_finite());
+    assert_close(contact.penetration, 0.5);
+    //a box scaled to a point still doesn't give NaN
+    let point = Mat4::from_scale(Vec3::ZERO).to_cols_array_2d();
+    let contact = sphere_with_oriented_bounding_box(Vec3::new(0.5, 0.0, 0.0), 1.0, cube.aabb_min, cube.aabb_max, point).unwrap();
+    assert_vec_eq(contact.normal, -Vec3::X);
+}
@@ -0,0 +1,32 @@
+//helpers shared by the integration tests, each test binary only uses some of them
+#![allow(dead_code)]
+
+use glam::{Quat, Vec3};
+
+//xorshift, so failures reproduce without pulling in a property testing crate
+pub struct Rng(pub u64);
+impl Rng {
+    pub fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+    pub fn vec3(&mut self, min: f32, max: f32) -> Vec3 {
+        Vec3::new(self.range(min, max), self.range(min, max), self.range(min, max))
+    }
+    pub fn direction(&mut self) -> Vec3 {
+        loop {
+            let v = self.vec3(-1.0, 1.0);
+            if v.length_squared() > 0.01 && v.length_squared() <= 1.0 {
+                return v.normalize();
+            }
+        }
+    }
+    pub fn rotation(&mut self) -> Quat {
+        Quat::from_axis_angle(self.direction(), self.range(0.0, std::f32::consts::TAU))
+    }
+}
@@ -0,0 +1,139 @@
+use bevy_ecs::{event::ManualEventReader, prelude::*};
+use glam::Vec3;
+use vertix::{
+    collision::{
+        broadphase::{update_broadphase, Broadphase},
+        contacts::{
+            detect_collisions, init_collision_events, update_collision_events, CollisionEnded, CollisionStarted, Contacts, Sensor,
+            TriggerEntered, TriggerExited,
+        },
+        spatial_query::CollisionLayers,
+        structs_3d::{Collider3D, OBB},
+    },
+    instance::Instance,
+    prelude::{AppBuilder, CollisionPlugin},
+};
+
+//what CollisionPlugin sets up, with the broadphase or without it
+fn world(broadphase: bool) -> (World, Schedule) {
+    let mut world = World::new();
+    init_collision_events(&mut world);
+    let mut schedule = Schedule::default();
+    if broadphase {
+        world.insert_resource(Broadphase::default());
+        schedule.add_systems((update_collision_events, update_broadphase, detect_collisions).chain());
+    } else {
+        schedule.add_systems((update_collision_events, detect_collisions).chain());
+    }
+    (world, schedule)
+}
+fn cube(x: f32) -> (Instance, Collider3D) {
+    (Instance { position: Vec3::new(x, 0.0, 0.0), ..Default::default() }, Collider3D::OBB(OBB::new(2.0, 2.0, 2.0)))
+}
+fn move_to(world: &mut World, entity: Entity, x: f32) {
+    world.get_mut::<Instance>(entity).unwrap().position.x = x;
+}
+//events sent since the reader last looked
+fn read<T: Event + Copy>(world: &World, reader: &mut ManualEventReader<T>) -> Vec<T> {
+    reader.iter(world.resource::<Events<T>>()).copied().collect()
+}
+
+#[test]
+fn collisions_start_persist_and_end() {
+    for broadphase in [false, true] {
+        let (mut world, mut schedule) = world(broadphase);
+        let mut started = ManualEventReader::<CollisionStarted>::default();
+        let mut ended = ManualEventReader::<CollisionEnded>::default();
+        let a = world.spawn(cube(0.0)).id();
+        let b = world.spawn(cube(5.0)).id();
+        schedule.run(&mut world);
+        assert!(read(&world, &mut started).is_empty());
+        assert!(world.resource::<Contacts>().is_empty());
+
+        move_to(&mut world, b, 1.5);
+        schedule.run(&mut world);
+        assert_eq!(read(&world, &mut started), vec![CollisionStarted(a, b)]);
+        let contacts = world.resource::<Contacts>();
+        assert!(contacts.touching(b, a));
+        assert!((contacts.get(a, b).unwrap().penetration - 0.5).abs() < 1e-4);
+        assert!(contacts.get(a, b).unwrap().normal.abs_diff_eq(Vec3::X, 1e-4));
+        assert!(contacts.get(b, a).unwrap().normal.abs_diff_eq(-Vec3::X, 1e-4));
+        assert_eq!(contacts.contacts_of(b).map(|(other, _)| other).collect::<Vec<_>>(), vec![a]);
+
+        //still touching, no new events but the contact keeps up
+        move_to(&mut world, b, 1.8);
+        schedule.run(&mut world);
+        schedule.run(&mut world);
+        assert!(read(&world, &mut started).is_empty());
+        let pair = world.resource::<Contacts>().pair(a, b).unwrap();
+        assert_eq!(pair.frames, 3);
+        assert!((pair.contact.penetration - 0.2).abs() < 1e-4);
+
+        move_to(&mut world, b, 5.0);
+        schedule.run(&mut world);
+        assert_eq!(read(&world, &mut ended), vec![CollisionEnded(a, b)]);
+        assert!(!world.resource::<Contacts>().touching(a, b));
+
+        //despawning ends it too
+        move_to(&mut world, b, 1.5);
+        schedule.run(&mut world);
+        world.despawn(a);
+        schedule.run(&mut world);
+        assert_eq!(read(&world, &mut started), vec![CollisionStarted(a, b)]);
+        assert_eq!(read(&world, &mut ended), vec![CollisionEnded(a, b)]);
+        assert!(world.resource::<Contacts>().is_empty());
+    }
+}
+
+#[test]
+fn sensors_send_trigger_events() {
+    let (mut world, mut schedule) = world(true);
+    let mut started = ManualEventReader::<CollisionStarted>::default();
+    let mut entered = ManualEventReader::<TriggerEntered>::default();
+    let mut exited = ManualEventReader::<TriggerExited>::default();
+    let zone = world.spawn((cube(0.0), Sensor)).id();
+    let player = world.spawn(cube(5.0)).id();
+    schedule.run(&mut world);
+    move_to(&mut world, player, 1.0);
+    schedule.run(&mut world);
+    assert_eq!(read(&world, &mut entered), vec![TriggerEntered { sensor: zone, other: player }]);
+    assert!(read(&world, &mut started).is_empty());
+    assert!(world.resource::<Contacts>().touching(zone, player));
+
+    //the exit matches the enter even if the sensor was taken off in between
+    world.entity_mut(zone).remove::<Sensor>();
+    move_to(&mut world, player, 5.0);
+    schedule.run(&mut world);
+    assert_eq!(read(&world, &mut exited), vec![TriggerExited { sensor: zone, other: player }]);
+}
+
+#[test]
+fn layers_and_disabled_instances_dont_collide() {
+    let (mut world, mut schedule) = world(true);
+    let mut started = ManualEventReader::<CollisionStarted>::default();
+    let mut ended = ManualEventReader::<CollisionEnded>::default();
+    let a = world.spawn((cube(0.0), CollisionLayers::layer(0))).id();
+    let b = world.spawn((cube(1.0), CollisionLayers::layer(1))).id();
+    schedule.run(&mut world);
+    assert!(read(&world, &mut started).is_empty());
+
+    world.entity_mut(b).insert(CollisionLayers::layer(1).with(0));
+    schedule.run(&mut world);
+    assert_eq!(read(&world, &mut started), vec![CollisionStarted(a, b)]);
+
+    world.get_mut::<Instance>(a).unwrap().enabled = false;
+    schedule.run(&mut world);
+    assert_eq!(read(&world, &mut ended), vec![CollisionEnded(a, b)]);
+}
+
+#[test]
+fn the_collision_plugin_runs_before_every_frame() {
+    let mut state = pollster::block_on(AppBuilder::new(env!("OUT_DIR")).add_plugin(CollisionPlugin).build_headless(32, 32, true)).unwrap();
+    assert!(state.world.contains_resource::<Broadphase>());
+    let mut started = ManualEventReader::<CollisionStarted>::default();
+    let a = state.world.spawn(cube(0.0)).id();
+    let b = state.world.spawn(cube(1.5)).id();
+    state.run_frame(instant::Duration::from_millis(16));
+    assert_eq!(read(&state.world, &mut started), vec![CollisionStarted(a, b)]);
+    assert!(state.world.resource::<Contacts>().touching(a, b));
+}
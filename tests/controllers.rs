@@ -0,0 +1,90 @@
+use std::f32::consts::FRAC_PI_2;
+
+use glam::{Vec2, Vec3};
+use instant::Duration;
+use vertix::{
+    app_resource::App,
+    collision::structs_3d::{Collider3D, Sphere},
+    hierarchy::add_child,
+    prelude::*,
+    replay::RecordedInput,
+    structs::CameraController,
+};
+
+fn frame(state: &mut State) {
+    state.run_frame(Duration::from_millis(16));
+}
+fn controller(state: &mut State) -> &mut CameraController {
+    &mut state.world.resource_mut::<App>().into_inner().camera.camera_controller
+}
+fn camera(state: &State) -> &Camera {
+    &state.world.resource::<App>().camera.camera_transform
+}
+fn set_kind(state: &mut State, kind: CameraControllerKind) {
+    *state.world.resource_mut::<CameraControllerKind>() = kind;
+}
+
+//one test so only one gpu device gets made
+#[test]
+fn controllers_clamp_and_keep_up_with_their_target() {
+    let plugin = CameraControllerPlugin { kind: CameraControllerKind::None, speed: 5.0, sensitivity: 2.0 };
+    let mut state = pollster::block_on(AppBuilder::new(env!("OUT_DIR")).add_plugin(plugin).build_headless(64, 64, true)).unwrap();
+
+    //orbit: zooming stops at the distance limits and looking stops short of straight down
+    let target = Vec3::new(1.0, 2.0, 3.0);
+    set_kind(&mut state, CameraControllerKind::Orbit(OrbitController { target, distance: 5.0, min_distance: 2.0, max_distance: 8.0 }));
+    let orbit_distance = |state: &State| match state.world.resource::<CameraControllerKind>() {
+        CameraControllerKind::Orbit(orbit) => orbit.distance,
+        kind => panic!("{:?}", kind),
+    };
+    for (scroll, expected) in [(1000.0, 2.0), (-1000.0, 8.0)] {
+        controller(&mut state).scroll = scroll;
+        frame(&mut state);
+        assert_eq!(orbit_distance(&state), expected);
+        assert!((camera(&state).position.distance(target) - expected).abs() < 1e-3);
+    }
+    controller(&mut state).rotate_vertical = 10000.0;
+    frame(&mut state);
+    let pitch = camera(&state).pitch;
+    assert!(pitch > -FRAC_PI_2 && pitch < -FRAC_PI_2 + 0.001, "{}", pitch);
+    assert!((camera(&state).position.distance(target) - 8.0).abs() < 1e-3);
+
+    //follow: a parented target is followed where its parent moved it this frame, not where it was drawn last frame
+    let parent = state.world.spawn(Instance { position: Vec3::new(10.0, 0.0, 0.0), ..Default::default() }).id();
+    let followed = state.world.spawn(Instance { position: Vec3::new(0.0, 0.0, 2.0), ..Default::default() }).id();
+    add_child(&mut state.world, parent, followed);
+    let follow = FollowController { smoothing: 0.0, ..FollowController::new(followed) };
+    let (distance, margin) = (follow.distance, follow.collision_margin);
+    set_kind(&mut state, CameraControllerKind::Follow(follow));
+    let camera_transform = &mut state.world.resource_mut::<App>().into_inner().camera.camera_transform;
+    (camera_transform.yaw, camera_transform.pitch) = (-FRAC_PI_2, -0.3);
+    frame(&mut state);
+    state.world.get_mut::<Instance>(parent).unwrap().position.x = 20.0;
+    frame(&mut state);
+    let pivot = Vec3::new(20.0, 1.5, 2.0);
+    let looking_at = |state: &State, distance: f32| camera(state).position + camera(state).forward() * distance;
+    assert!(looking_at(&state, distance).abs_diff_eq(pivot, 1e-3), "{:?}", looking_at(&state, distance));
+
+    //something between the target and the camera pulls the camera in front of it
+    let midway = pivot.lerp(camera(&state).position, 0.5);
+    state.world.spawn((Instance { position: midway, ..Default::default() }, Collider3D::Sphere(Sphere { center: Vec3::ZERO, radius: 1.0 })));
+    frame(&mut state);
+    let pulled_in = distance / 2.0 - 1.0 - margin;
+    assert!((camera(&state).position.distance(pivot) - pulled_in).abs() < 1e-3, "{:?}", camera(&state).position);
+    assert!(looking_at(&state, pulled_in).abs_diff_eq(pivot, 1e-3));
+
+    //2d pan and zoom: zoom stops at its limits, panning covers the same screen distance at any zoom
+    state.world.resource_mut::<App>().camera.set_2d(Camera2D::default(), OrthographicScaling::FixedHeight(10.0));
+    set_kind(&mut state, CameraControllerKind::PanZoom2D(PanZoom2DController { min_zoom: 0.5, max_zoom: 4.0 }));
+    let camera_2d = |state: &State| state.world.resource::<App>().camera.camera_2d.unwrap();
+    for (scroll, expected) in [(1000.0, 4.0), (-1000.0, 0.5)] {
+        controller(&mut state).scroll = scroll;
+        frame(&mut state);
+        assert_eq!(camera_2d(&state).zoom, expected);
+    }
+    let start = camera_2d(&state).position;
+    state.apply_input(&RecordedInput::Key { scancode: 32, key: VirtualKeyCode::D, state: ElementState::Pressed });
+    frame(&mut state);
+    let panned = camera_2d(&state).position - start;
+    assert!(panned.abs_diff_eq(Vec2::new(5.0 * 0.016 / 0.5, 0.0), 1e-4), "{:?}", panned);
+}
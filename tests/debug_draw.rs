@@ -0,0 +1,61 @@
+use glam::{Vec2, Vec3};
+use instant::Duration;
+use vertix::{
+    app_resource::App,
+    collision::{
+        structs_2d::Box2D,
+        structs_3d::{Collider3D, OBB},
+    },
+    plugins::DebugDraw,
+    prelude::*,
+    structs::MeshType,
+};
+
+//every line is a quad
+const INDICES_PER_LINE: u32 = 6;
+
+//the debug mesh and how many prefabs there are
+fn debug_mesh(state: &State) -> (u32, u64, usize) {
+    let asset_server = &state.world.resource::<App>().asset_server;
+    let mesh = asset_server
+        .prefab_slab
+        .iter()
+        .find_map(|(_, prefab)| match &prefab.mesh_type {
+            MeshType::Mesh(mesh) => Some(mesh),
+            _ => None,
+        })
+        .unwrap();
+    (mesh.num_elements, mesh.index_buffer.size(), asset_server.prefab_slab.len())
+}
+
+#[test]
+fn collider_outlines_reuse_one_growing_buffer() {
+    let mut state = pollster::block_on(AppBuilder::new(env!("OUT_DIR")).add_plugin(CollisionDebugPlugin).build_headless(32, 32, true)).unwrap();
+    state.world.spawn((Instance::default(), Collider3D::OBB(OBB::new(1.0, 1.0, 1.0))));
+    let square = Box2D::new(Vec2::splat(-1.0), Vec2::ONE);
+    state.world.spawn((Instance { position: Vec3::new(3.0, 0.0, 0.0), ..Default::default() }, square));
+    //screen space boxes aren't outlined
+    let ui = Box2D::new(Vec2::splat(-1.0), Vec2::ONE);
+    state.world.spawn((Instance { is_world_space: false, ..Default::default() }, ui));
+    state.run_frame(Duration::from_millis(16));
+    let (elements, _, prefabs) = debug_mesh(&state);
+    assert_eq!(elements, (12 + 4) * INDICES_PER_LINE);
+
+    //more lines than fit grow the buffer, but it stays the same prefab
+    for i in 0..50 {
+        state.world.spawn((Instance { position: Vec3::new(i as f32 * 2.0, 5.0, 0.0), ..Default::default() }, Collider3D::OBB(OBB::new(1.0, 1.0, 1.0))));
+    }
+    state.run_frame(Duration::from_millis(16));
+    let (elements, size, grown_prefabs) = debug_mesh(&state);
+    assert_eq!(elements, (51 * 12 + 4) * INDICES_PER_LINE);
+    assert!(size >= elements as u64 * 4);
+    assert_eq!(grown_prefabs, prefabs);
+
+    //with nothing to draw the mesh is kept, just empty
+    state.world.clear_entities();
+    state.run_frame(Duration::from_millis(16));
+    assert_eq!(debug_mesh(&state), (0, size, prefabs));
+    state.world.resource_mut::<DebugDraw>().line(Vec3::ZERO, Vec3::X);
+    state.run_frame(Duration::from_millis(16));
+    assert_eq!(debug_mesh(&state).0, INDICES_PER_LINE);
+}
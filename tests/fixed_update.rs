@@ -0,0 +1,63 @@
+use bevy_ecs::system::{Res, ResMut, Resource};
+use instant::Duration;
+use vertix::{app_resource::App, prelude::*, state::MAX_FIXED_STEPS_PER_FRAME};
+
+#[derive(Resource, Default)]
+struct Steps(Vec<Duration>); //the dt every fixed step saw
+
+fn count_steps(app: Res<App>, mut steps: ResMut<Steps>) {
+    steps.0.push(app.dt);
+}
+fn run(state: &mut State, dt_ms: u64) -> usize {
+    state.world.resource_mut::<Steps>().0.clear();
+    state.run_frame(Duration::from_millis(dt_ms));
+    state.world.resource::<Steps>().0.len()
+}
+fn alpha(state: &State) -> f32 {
+    state.world.resource::<App>().fixed_alpha
+}
+
+//one test so only one gpu device gets made
+#[test]
+fn fixed_steps_accumulate_clamp_and_report_alpha() {
+    let mut state = pollster::block_on(AppBuilder::new(env!("OUT_DIR")).build_headless(64, 64, true)).unwrap();
+    state.world.resource_mut::<App>().fixed_timestep = Duration::from_millis(10);
+    state.world.init_resource::<Steps>();
+    state.add_systems(Stage::FixedUpdate, count_steps);
+
+    //short frames add up until a whole step fits
+    assert_eq!(run(&mut state, 4), 0);
+    assert!((alpha(&state) - 0.4).abs() < 1e-4);
+    assert_eq!(run(&mut state, 4), 0);
+    assert_eq!(run(&mut state, 4), 1);
+    assert!((alpha(&state) - 0.2).abs() < 1e-4);
+    //fixed systems see the timestep, everything after sees the frame's dt again
+    assert_eq!(state.world.resource::<Steps>().0, vec![Duration::from_millis(10)]);
+    assert_eq!(state.world.resource::<App>().dt, Duration::from_millis(4));
+
+    //a long frame catches up with several steps
+    assert_eq!(run(&mut state, 38), 4);
+    assert!(alpha(&state).abs() < 1e-4);
+
+    //a huge hitch runs at most MAX_FIXED_STEPS_PER_FRAME and drops the rest
+    assert_eq!(run(&mut state, 1000), MAX_FIXED_STEPS_PER_FRAME as usize);
+    assert_eq!(alpha(&state), 0.0);
+    assert_eq!(run(&mut state, 5), 0);
+    assert!((alpha(&state) - 0.5).abs() < 1e-4);
+
+    //a recording started part way to a step replays the same steps, whatever has built up when the replay starts
+    state.start_recording();
+    assert_eq!(run(&mut state, 6), 1);
+    assert_eq!(run(&mut state, 2), 0);
+    let recording = state.stop_recording().unwrap();
+    assert_eq!(recording.fixed_accumulator, Duration::from_millis(5));
+    assert_eq!(run(&mut state, 4), 0);
+    state.world.resource_mut::<Steps>().0.clear();
+    state.run_replay(recording);
+    assert_eq!(state.world.resource::<Steps>().0.len(), 1);
+    assert!((alpha(&state) - 0.3).abs() < 1e-4);
+
+    //a zero timestep turns FixedUpdate off
+    state.world.resource_mut::<App>().fixed_timestep = Duration::ZERO;
+    assert_eq!(run(&mut state, 100), 0);
+}
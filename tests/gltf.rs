@@ -0,0 +1,67 @@
+use glam::Vec3;
+use vertix::{
+    app_resource::App,
+    error::VertixError,
+    loader::{load_gltf_primitives, GltfPrimitive},
+    prelude::*,
+};
+
+//the fixtures live in tests/res/gltf, the loader looks in <build path>/res
+const BUILD_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests");
+//the same scene with the buffer inline, next to the file and in a glb's binary chunk
+const FIXTURES: [&str; 3] = ["gltf/embedded.gltf", "gltf/external.gltf", "gltf/binary.glb"];
+
+fn positions(primitive: &GltfPrimitive) -> Vec<Vec3> {
+    primitive.vertices.iter().map(|vertex| Vec3::from(vertex.position)).collect()
+}
+fn assert_positions(actual: Vec<Vec3>, expected: &[Vec3]) {
+    assert_eq!(actual.len(), expected.len());
+    for (actual, expected) in actual.iter().zip(expected) {
+        assert!(actual.abs_diff_eq(*expected, 1e-5), "expected {}, got {}", expected, actual);
+    }
+}
+
+#[test]
+fn node_transforms_are_baked_into_every_mesh() {
+    for fixture in FIXTURES {
+        let primitives = pollster::block_on(load_gltf_primitives(fixture, BUILD_PATH)).unwrap();
+        assert_eq!(primitives.len(), 2, "{}", fixture);
+        //moved 2 along x
+        let triangle = primitives.iter().find(|primitive| primitive.material == Some(0)).unwrap();
+        assert_positions(positions(triangle), &[Vec3::new(2.0, 0.0, 0.0), Vec3::new(3.0, 0.0, 0.0), Vec3::new(2.0, 1.0, 0.0)]);
+        assert_eq!(triangle.indices, vec![0, 1, 2]);
+        assert!(triangle.vertices.iter().all(|vertex| Vec3::from(vertex.normal).abs_diff_eq(Vec3::Z, 1e-5)));
+        //turned 90 degrees around z and moved up 1 by the child, then doubled by its parent
+        let quad = primitives.iter().find(|primitive| primitive.material.is_none()).unwrap();
+        assert_positions(
+            positions(quad),
+            &[Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 4.0, 0.0), Vec3::new(-2.0, 4.0, 0.0), Vec3::new(-2.0, 2.0, 0.0)],
+        );
+        assert_eq!(quad.indices, vec![0, 1, 2, 0, 2, 3]);
+    }
+}
+
+#[test]
+fn missing_files_are_io_errors() {
+    let result = pollster::block_on(load_gltf_primitives("gltf/missing.gltf", BUILD_PATH));
+    assert!(matches!(result, Err(VertixError::Io { .. })));
+}
+
+#[test]
+fn models_load_their_materials() {
+    let mut state = pollster::block_on(AppBuilder::new(BUILD_PATH).build_headless(32, 32, true)).unwrap();
+    let asset_server = &mut state.world.resource_mut::<App>().into_inner().asset_server;
+    for fixture in FIXTURES {
+        let model = pollster::block_on(asset_server.load_model(fixture)).unwrap();
+        let model = asset_server.models.get(&model).unwrap();
+        assert_eq!(model.meshes.len(), 2);
+        assert!(model.meshes.iter().any(|mesh| mesh.num_elements == 6 && mesh.material == 1));
+        //the material from the file, then the default one for meshes without
+        assert_eq!(model.materials.len(), 2);
+        let material = &model.materials[0];
+        assert_eq!(material.alpha_mode(), AlphaMode::Mask(0.3));
+        assert_eq!((material.metallic_factor, material.roughness_factor), (0.5, 0.25));
+        assert!(material.metallic_roughness_texture.is_some());
+        assert!(model.materials[1].metallic_roughness_texture.is_none());
+    }
+}
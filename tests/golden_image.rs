@@ -0,0 +1,61 @@
+use glam::{Quat, Vec3};
+use instant::Duration;
+use vertix::{app_resource::App, camera::Camera, prelude::*, shapes::cube};
+
+//set to write the current render over the golden image instead of comparing, after an intended change to how things look
+const UPDATE_VAR: &str = "VERTIX_UPDATE_GOLDEN";
+//rasterizers round differently, a channel off by this much still matches
+const CHANNEL_TOLERANCE: u8 = 8;
+const MAX_MISMATCHED_FRACTION: f32 = 0.005;
+
+fn render_scene() -> image::RgbaImage {
+    let camera = Camera::new(Vec3::new(0.0, 3.0, 7.0), f32::to_radians(-90.0), f32::to_radians(-20.0));
+    let mut state = pollster::block_on(AppBuilder::new(env!("OUT_DIR")).with_camera(camera).build_headless(128, 128, true)).unwrap();
+    state.world.insert_resource(ShadowSettings { enabled: true, ..Default::default() });
+    let mut ground = Instance { position: Vec3::new(0.0, -1.1, 0.0), ..Default::default() };
+    let mut textured = Instance { rotation: Quat::from_rotation_y(0.5), ..Default::default() };
+    let mut glass = Instance { position: Vec3::new(1.5, 0.0, 1.5), color: [0.2, 0.4, 1.0, 0.5], ..Default::default() };
+    let asset_server = &mut state.world.resource_mut::<App>().into_inner().asset_server;
+    let white = asset_server.compile_color_material([255, 255, 255, 255]);
+    let texture = pollster::block_on(asset_server.compile_material("cube-diffuse.jpg", wgpu::FilterMode::Linear)).unwrap();
+    asset_server.build_mesh(cube(10.0, 0.2, 10.0), vec![&mut ground], &white, false);
+    asset_server.build_mesh(cube(2.0, 2.0, 2.0), vec![&mut textured], &texture, true);
+    asset_server.build_mesh(cube(1.0, 1.0, 1.0), vec![&mut glass], &white, true);
+    state.world.spawn(ground);
+    state.world.spawn(textured);
+    state.world.spawn(glass);
+    state.run_frame(Duration::from_millis(16));
+    state.render_to_image()
+}
+
+//a lit, shadowed, textured and blended scene rendered headlessly, so changes to any of those show up as a diff
+#[test]
+fn scene_matches_golden_image() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/lit_scene.png");
+    let frame = render_scene();
+    if std::env::var_os(UPDATE_VAR).is_some() {
+        frame.save(path).unwrap();
+        return;
+    }
+    let golden = image::open(path)
+        .unwrap_or_else(|err| panic!("couldn't open {}: {}, run with {}=1 to create it", path, err, UPDATE_VAR))
+        .to_rgba8();
+    assert_eq!(frame.dimensions(), golden.dimensions());
+    let mismatched = frame
+        .pixels()
+        .zip(golden.pixels())
+        .filter(|(a, b)| a.0.iter().zip(b.0.iter()).any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE))
+        .count();
+    let fraction = mismatched as f32 / (frame.width() * frame.height()) as f32;
+    if fraction > MAX_MISMATCHED_FRACTION {
+        let actual = std::env::temp_dir().join("vertix_lit_scene_actual.png");
+        frame.save(&actual).unwrap();
+        panic!(
+            "{} of {} pixels differ from {}, the render was saved to {}",
+            mismatched,
+            frame.width() * frame.height(),
+            path,
+            actual.display()
+        );
+    }
+}
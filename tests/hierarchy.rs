@@ -0,0 +1,93 @@
+use bevy_ecs::prelude::*;
+use glam::{Quat, Vec3};
+use vertix::{
+    hierarchy::{add_child, insert_global_transforms, propagate_transforms, remove_parent, Children, GlobalTransform},
+    instance::Instance,
+};
+
+//what State runs in RenderPrep before the upload
+fn world() -> (World, Schedule) {
+    let mut schedule = Schedule::default();
+    schedule.add_systems((insert_global_transforms, apply_deferred, propagate_transforms).chain());
+    (World::new(), schedule)
+}
+fn at(position: Vec3) -> Instance {
+    Instance { position, ..Default::default() }
+}
+fn translation(world: &World, entity: Entity) -> Vec3 {
+    world.get::<GlobalTransform>(entity).unwrap().translation()
+}
+
+#[test]
+fn children_follow_their_parents() {
+    let (mut world, mut schedule) = world();
+    let root = world
+        .spawn(Instance { position: Vec3::X, rotation: Quat::from_rotation_y(f32::to_radians(90.0)), scale: Vec3::splat(2.0), ..Default::default() })
+        .id();
+    let child = world.spawn(at(Vec3::X)).id();
+    let grandchild = world.spawn(at(Vec3::Y)).id();
+    add_child(&mut world, root, child);
+    add_child(&mut world, child, grandchild);
+    schedule.run(&mut world);
+    //scaled by 2 and turned so +x points along -z
+    assert!(translation(&world, child).abs_diff_eq(Vec3::new(1.0, 0.0, -2.0), 1e-5));
+    assert!(translation(&world, grandchild).abs_diff_eq(Vec3::new(1.0, 2.0, -2.0), 1e-5));
+    assert!(world.get::<GlobalTransform>(grandchild).unwrap().scale().abs_diff_eq(Vec3::splat(2.0), 1e-5));
+
+    world.get_mut::<Instance>(root).unwrap().position = Vec3::ZERO;
+    schedule.run(&mut world);
+    assert!(translation(&world, grandchild).abs_diff_eq(Vec3::new(0.0, 2.0, -2.0), 1e-5));
+}
+
+#[test]
+fn reparenting_moves_the_subtree() {
+    let (mut world, mut schedule) = world();
+    let a = world.spawn(at(Vec3::new(10.0, 0.0, 0.0))).id();
+    let b = world.spawn(at(Vec3::new(0.0, 0.0, 10.0))).id();
+    let child = world.spawn(at(Vec3::Y)).id();
+    let grandchild = world.spawn(at(Vec3::Y)).id();
+    add_child(&mut world, a, child);
+    add_child(&mut world, child, grandchild);
+    schedule.run(&mut world);
+    assert!(translation(&world, grandchild).abs_diff_eq(Vec3::new(10.0, 2.0, 0.0), 1e-5));
+
+    remove_parent(&mut world, child);
+    add_child(&mut world, b, child);
+    schedule.run(&mut world);
+    assert!(translation(&world, grandchild).abs_diff_eq(Vec3::new(0.0, 2.0, 10.0), 1e-5));
+
+    //add_child alone moves it too, without leaving it in the old parent's Children
+    add_child(&mut world, a, child);
+    add_child(&mut world, b, child);
+    assert!(!world.get::<Children>(a).unwrap().0.contains(&child));
+    assert_eq!(world.get::<Children>(b).unwrap().0, vec![child]);
+    //a runs after b or before it depending on storage order, either way only b moves the child
+    world.get_mut::<Instance>(a).unwrap().position = Vec3::new(-10.0, 0.0, 0.0);
+    schedule.run(&mut world);
+    assert!(translation(&world, grandchild).abs_diff_eq(Vec3::new(0.0, 2.0, 10.0), 1e-5));
+
+    //without a parent the instance is the global transform again
+    remove_parent(&mut world, child);
+    schedule.run(&mut world);
+    assert!(translation(&world, child).abs_diff_eq(Vec3::Y, 1e-5));
+    assert!(translation(&world, grandchild).abs_diff_eq(Vec3::new(0.0, 2.0, 0.0), 1e-5));
+}
+
+#[test]
+fn orphans_keep_updating_after_their_parent_is_despawned() {
+    let (mut world, mut schedule) = world();
+    let parent = world.spawn(at(Vec3::new(10.0, 0.0, 0.0))).id();
+    let child = world.spawn(at(Vec3::Y)).id();
+    let grandchild = world.spawn(at(Vec3::Y)).id();
+    add_child(&mut world, parent, child);
+    add_child(&mut world, child, grandchild);
+    schedule.run(&mut world);
+    world.despawn(parent);
+    schedule.run(&mut world);
+    assert!(translation(&world, child).abs_diff_eq(Vec3::Y, 1e-5));
+    assert!(translation(&world, grandchild).abs_diff_eq(Vec3::new(0.0, 2.0, 0.0), 1e-5));
+
+    world.get_mut::<Instance>(child).unwrap().position = Vec3::Z;
+    schedule.run(&mut world);
+    assert!(translation(&world, grandchild).abs_diff_eq(Vec3::new(0.0, 1.0, 1.0), 1e-5));
+}
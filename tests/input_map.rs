@@ -0,0 +1,111 @@
+use vertix::{
+    controllers::{bind_camera_axes, CAMERA_FORWARD, CAMERA_RIGHT, CAMERA_UP},
+    error::VertixError,
+    input::{AxisBinding, InputBinding, InputMap},
+    replay::RecordedInput,
+};
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, VirtualKeyCode};
+
+fn key(map: &mut InputMap, key: VirtualKeyCode, state: ElementState) {
+    let input = RecordedInput::Key { scancode: 0, key, state };
+    map.process_event(&input.to_window_event().unwrap());
+}
+fn scroll(map: &mut InputMap, lines: f32) {
+    map.process_event(&RecordedInput::MouseWheel(MouseScrollDelta::LineDelta(0.0, lines)).to_window_event().unwrap());
+}
+fn movement_map() -> InputMap {
+    InputMap::new()
+        .with_action("jump", InputBinding::Key(VirtualKeyCode::Space))
+        .with_action("jump", InputBinding::Mouse(MouseButton::Right))
+        .with_axis("move_x", AxisBinding::new(InputBinding::Key(VirtualKeyCode::A), InputBinding::Key(VirtualKeyCode::D)))
+        .with_axis("zoom", AxisBinding::new(InputBinding::ScrollDown, InputBinding::ScrollUp))
+}
+
+#[test]
+fn actions_follow_their_bindings_across_frames() {
+    let mut map = movement_map();
+    key(&mut map, VirtualKeyCode::Space, ElementState::Pressed);
+    assert!(map.pressed("jump") && map.just_pressed("jump"));
+    assert_eq!(map.value("jump"), 1.0);
+    map.next_frame();
+    assert!(map.pressed("jump") && !map.just_pressed("jump"));
+    key(&mut map, VirtualKeyCode::Space, ElementState::Released);
+    assert!(!map.pressed("jump") && map.just_released("jump"));
+    map.next_frame();
+    assert!(!map.just_released("jump"));
+    assert!(!map.pressed("unbound"));
+}
+
+#[test]
+fn axes_combine_both_directions_and_scroll() {
+    let mut map = movement_map();
+    key(&mut map, VirtualKeyCode::D, ElementState::Pressed);
+    assert_eq!(map.value("move_x"), 1.0);
+    key(&mut map, VirtualKeyCode::A, ElementState::Pressed);
+    assert_eq!(map.value("move_x"), 0.0);
+    assert!(map.pressed("move_x"));
+    key(&mut map, VirtualKeyCode::D, ElementState::Released);
+    assert_eq!(map.value("move_x"), -1.0);
+
+    //scroll is how far the wheel moved, and only for the frame it moved
+    scroll(&mut map, 1.0);
+    scroll(&mut map, 2.0);
+    assert_eq!(map.value("zoom"), 3.0);
+    assert!(map.just_pressed("zoom"));
+    map.next_frame();
+    assert_eq!(map.value("zoom"), 0.0);
+    assert!(!map.pressed("zoom"));
+    scroll(&mut map, -0.5);
+    assert_eq!(map.value("zoom"), -0.5);
+}
+
+#[test]
+fn rebinding_and_conflicts() {
+    let mut map = movement_map();
+    map.rebind_action("jump", vec![InputBinding::Key(VirtualKeyCode::W)]);
+    key(&mut map, VirtualKeyCode::Space, ElementState::Pressed);
+    assert!(!map.pressed("jump"));
+    key(&mut map, VirtualKeyCode::W, ElementState::Pressed);
+    assert!(map.pressed("jump"));
+    //"press a key to bind" menus get whatever was pressed last, once
+    assert_eq!(map.take_last_pressed(), Some(InputBinding::Key(VirtualKeyCode::W)));
+    assert_eq!(map.take_last_pressed(), None);
+
+    map.bind_action("dash", InputBinding::Key(VirtualKeyCode::D));
+    assert_eq!(map.bound_to(InputBinding::Key(VirtualKeyCode::D)), vec!["dash", "move_x"]);
+    map.unbind("move_x");
+    assert_eq!(map.bound_to(InputBinding::Key(VirtualKeyCode::D)), vec!["dash"]);
+
+    map.release_all();
+    assert!(!map.pressed("jump"));
+}
+
+#[test]
+fn bindings_round_trip_through_a_file() {
+    let path = std::env::temp_dir().join(format!("vertix_input_map_{}.json", std::process::id()));
+    let path = path.to_str().unwrap();
+    let map = movement_map();
+    map.save_to_file(path).unwrap();
+    let mut loaded = InputMap::new();
+    loaded.load_from_file(path).unwrap();
+    assert_eq!(loaded.bindings, map.bindings);
+
+    std::fs::write(path, "{ not json").unwrap();
+    assert!(matches!(loaded.load_from_file(path), Err(VertixError::InputBindings { .. })));
+    assert_eq!(loaded.bindings, map.bindings);
+    std::fs::remove_file(path).unwrap();
+    assert!(matches!(loaded.load_from_file(path), Err(VertixError::Io { .. })));
+}
+
+#[test]
+fn camera_axes_keep_bindings_the_game_already_made() {
+    let mut map = InputMap::new().with_axis(CAMERA_UP, AxisBinding::new(InputBinding::Key(VirtualKeyCode::Q), InputBinding::Key(VirtualKeyCode::E)));
+    bind_camera_axes(&mut map);
+    key(&mut map, VirtualKeyCode::Up, ElementState::Pressed);
+    key(&mut map, VirtualKeyCode::A, ElementState::Pressed);
+    key(&mut map, VirtualKeyCode::E, ElementState::Pressed);
+    assert_eq!((map.value(CAMERA_FORWARD), map.value(CAMERA_RIGHT), map.value(CAMERA_UP)), (1.0, -1.0, 1.0));
+    key(&mut map, VirtualKeyCode::E, ElementState::Released);
+    key(&mut map, VirtualKeyCode::Space, ElementState::Pressed);
+    assert_eq!(map.value(CAMERA_UP), 0.0);
+}
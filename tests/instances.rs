@@ -0,0 +1,36 @@
+use glam::Vec3;
+use instant::Duration;
+use vertix::{app_resource::App, prelude::*, shapes::cube};
+
+fn prefab_positions(state: &State, prefab_index: usize) -> (u32, Vec<Vec3>) {
+    let prefab = &state.world.resource::<App>().asset_server.prefab_slab[prefab_index];
+    (prefab.length, prefab.instances.iter().map(|instance| instance.position()).collect())
+}
+
+#[test]
+fn despawned_and_moved_instances_leave_their_prefab() {
+    let mut state = pollster::block_on(AppBuilder::new(env!("OUT_DIR")).build_headless(64, 64, true)).unwrap();
+    let mut a = Instance { position: Vec3::X, ..Default::default() };
+    let mut b = Instance { position: Vec3::Y, ..Default::default() };
+    let mut other = Instance::default();
+    let asset_server = &mut state.world.resource_mut::<App>().into_inner().asset_server;
+    let material = asset_server.compile_color_material([255, 255, 255, 255]);
+    asset_server.build_mesh(cube(1.0, 1.0, 1.0), vec![&mut a, &mut b], &material, true);
+    asset_server.build_mesh(cube(1.0, 1.0, 1.0), vec![&mut other], &material, true);
+    let prefab = a.prefab_index;
+    let other_prefab = other.prefab_index;
+    let a = state.world.spawn(a).id();
+    let b = state.world.spawn(b).id();
+    state.world.spawn(other);
+    state.run_frame(Duration::from_millis(16));
+    assert_eq!(prefab_positions(&state, prefab), (2, vec![Vec3::X, Vec3::Y]));
+
+    state.world.despawn(a);
+    state.run_frame(Duration::from_millis(16));
+    assert_eq!(prefab_positions(&state, prefab), (1, vec![Vec3::Y]));
+
+    state.world.get_mut::<Instance>(b).unwrap().prefab_index = other_prefab;
+    state.run_frame(Duration::from_millis(16));
+    assert_eq!(prefab_positions(&state, prefab), (0, vec![]));
+    assert_eq!(prefab_positions(&state, other_prefab).0, 2);
+}
@@ -0,0 +1,57 @@
+use glam::{Vec2, Vec3};
+use vertix::camera::{screen_projection, Camera, Camera2D, OrthographicScaling, PerspectiveSettings, Projection, ORTHOGRAPHIC_DEPTH};
+
+const CURSOR: Vec2 = Vec2::new(0.2, 0.1);
+
+#[test]
+fn perspective_rays_point_through_the_cursor_in_every_depth_mode() {
+    //looking down -z, so the view space direction is the world space one
+    let camera = Camera::new(Vec3::new(1.0, 2.0, 3.0), f32::to_radians(-90.0), 0.0);
+    let fovy = f32::to_radians(60.0);
+    let half_height = (fovy / 2.0).tan();
+    //200x100 is twice as wide as it is tall
+    let expected = Vec3::new(CURSOR.x * half_height * 2.0, CURSOR.y * half_height, -1.0).normalize();
+    for zfar in [100.0, f32::INFINITY] {
+        for reverse_z in [false, true] {
+            let projection = Projection::perspective(200, 100, PerspectiveSettings { fovy, znear: 0.1, zfar, reverse_z });
+            let view_proj = projection.calc_matrix() * camera.calc_matrix();
+            let (near_point, direction) = projection.unproject(view_proj, CURSOR);
+            assert!(
+                direction.abs_diff_eq(expected, 1e-4),
+                "zfar {} reverse_z {}: {:?} instead of {:?}",
+                zfar,
+                reverse_z,
+                direction,
+                expected
+            );
+            //on the near plane, along the same ray from the camera
+            assert!((near_point - camera.position).normalize().abs_diff_eq(expected, 1e-3));
+            assert!(((near_point - camera.position).dot(-Vec3::Z) - 0.1).abs() < 1e-3);
+        }
+    }
+}
+
+#[test]
+fn orthographic_rays_start_under_the_cursor() {
+    let camera_2d = Camera2D::new(Vec2::new(3.0, 4.0), 1.0, 0.0);
+    for reverse_z in [false, true] {
+        let mut projection = Projection::orthographic(200, 100, OrthographicScaling::FixedHeight(10.0));
+        projection.set_reverse_z(reverse_z);
+        let view_proj = projection.calc_matrix_zoomed(camera_2d.zoom) * camera_2d.calc_matrix(&projection);
+        let (origin, direction) = projection.unproject(view_proj, CURSOR);
+        //10 units tall and 20 wide
+        assert!(origin.abs_diff_eq(Vec3::new(3.0 + CURSOR.x * 10.0, 4.0 + CURSOR.y * 5.0, ORTHOGRAPHIC_DEPTH), 1e-3), "{:?}", origin);
+        assert!(direction.abs_diff_eq(-Vec3::Z, 1e-5), "{:?}", direction);
+    }
+}
+
+#[test]
+fn screen_space_is_as_wide_as_the_aspect_ratio() {
+    let projection = screen_projection(200, 100);
+    //the cursor's x scaled by the aspect ratio, what WindowEvents::screen_space_mouse_pos gives
+    let under_cursor = projection.project_point3(Vec3::new(CURSOR.x * 2.0, CURSOR.y, 0.25));
+    assert!(under_cursor.abs_diff_eq(CURSOR.extend(0.25), 1e-6), "{:?}", under_cursor);
+    //a unit square covers as many pixels across as it does up
+    let corner = projection.project_point3(Vec3::new(1.0, 1.0, 0.0));
+    assert_eq!((corner.x * 200.0, corner.y * 100.0), (100.0, 100.0));
+}
@@ -0,0 +1,150 @@
+//ray casts checked against marching along the ray in small steps, over randomly placed shapes
+mod common;
+
+use glam::{Mat4, Vec3};
+use vertix::collision::{
+    collision_fns_3d::{oriented_bounding_box_with_ray, sphere_with_ray_collision},
+    structs_3d::RayHit,
+};
+
+use common::Rng;
+
+const CASES: usize = 500;
+const STEP: f32 = 0.005;
+const MAX_DISTANCE: f32 = 30.0;
+const EPSILON: f32 = 1e-3;
+
+//the distance of the first marched sample inside the shape
+fn march(origin: Vec3, direction: Vec3, inside: impl Fn(Vec3) -> bool) -> Option<f32> {
+    let steps = (MAX_DISTANCE / STEP) as usize;
+    (0..=steps).map(|i| i as f32 * STEP).find(|t| inside(origin + direction * *t))
+}
+
+//a shape grown and shrunk by a margin, so samples right on the surface don't decide the test
+struct Shape<F: Fn(Vec3, f32) -> bool> {
+    inside_with_margin: F,
+}
+impl<F: Fn(Vec3, f32) -> bool> Shape<F> {
+    fn check(&self, origin: Vec3, direction: Vec3, hit: Option<RayHit>) {
+        let shrunk = |point: Vec3| (self.inside_with_margin)(point, -EPSILON);
+        let grown = |point: Vec3| (self.inside_with_margin)(point, EPSILON);
+        match hit {
+            None => assert_eq!(
+                march(origin, direction, shrunk),
+                None,
+                "ray from {} along {} missed but goes through the shape",
+                origin,
+                direction
+            ),
+            Some(hit) => {
+                assert!(hit.distance >= 0.0, "hit behind the ray: {:?}", hit);
+                assert!(hit.point.abs_diff_eq(origin + direction * hit.distance, EPSILON));
+                assert!((hit.normal.length() - 1.0).abs() < EPSILON, "normal isn't unit length: {:?}", hit);
+                assert!(hit.normal.dot(direction) <= EPSILON, "normal faces away from the ray: {:?}", hit);
+                assert!(grown(hit.point), "hit point {} isn't on the shape", hit.point);
+                if hit.distance > 0.0 {
+                    //nothing of the shape before the hit, and the surface really is there
+                    if let Some(first) = march(origin, direction, shrunk) {
+                        assert!(first >= hit.distance - STEP, "marched into the shape at {} before the hit at {}", first, hit.distance);
+                    }
+                    assert!(!shrunk(hit.point + hit.normal * EPSILON * 2.0), "outside of the normal is inside: {:?}", hit);
+                } else {
+                    assert!(grown(origin), "hit at 0 but the ray starts outside: {:?}", hit);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn sphere_ray_matches_marching() {
+    let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+    for _ in 0..CASES {
+        let center = rng.vec3(-5.0, 5.0);
+        let radius = rng.range(0.2, 4.0);
+        //aim near the shape most of the time so hits and grazes both come up
+        let origin = rng.vec3(-10.0, 10.0);
+        let direction = if rng.next_f32() < 0.7 {
+            (center + rng.vec3(-radius * 1.2, radius * 1.2) - origin).normalize()
+        } else {
+            rng.direction()
+        };
+        let shape = Shape {
+            inside_with_margin: |point: Vec3, margin: f32| point.distance(center) <= radius + margin,
+        };
+        shape.check(origin, direction, sphere_with_ray_collision(origin, direction, radius, center));
+    }
+}
+
+#[test]
+fn oriented_box_ray_matches_marching() {
+    let mut rng = Rng(0xD1B5_4A32_D192_ED03);
+    for _ in 0..CASES {
+        let aabb_min = rng.vec3(-2.0, -0.1);
+        let aabb_max = rng.vec3(0.1, 2.0);
+        let scale = rng.vec3(0.5, 2.0);
+        let rotation = rng.rotation();
+        let translation = rng.vec3(-5.0, 5.0);
+        let model_matrix = Mat4::from_scale_rotation_translation(scale, rotation, translation);
+        let origin = rng.vec3(-10.0, 10.0);
+        let direction = if rng.next_f32() < 0.7 {
+            (translation + rng.vec3(-2.0, 2.0) - origin).normalize()
+        } else {
+            rng.direction()
+        };
+        let world_to_local = Mat4::from_rotation_translation(rotation, translation).inverse();
+        let shape = Shape {
+            inside_with_margin: |point: Vec3, margin: f32| {
+                let local = world_to_local.transform_point3(point);
+                local.cmpge(aabb_min * scale - margin).all() && local.cmple(aabb_max * scale + margin).all()
+            },
+        };
+        let hit = oriented_bounding_box_with_ray(origin, direction, aabb_min, aabb_max, model_matrix.to_cols_array_2d());
+        shape.check(origin, direction, hit);
+    }
+}
+
+#[test]
+fn rays_starting_inside_hit_at_their_origin() {
+    let sphere_hit = sphere_with_ray_collision(Vec3::new(0.5, 0.0, 0.0), Vec3::X, 1.0, Vec3::ZERO).unwrap();
+    assert_eq!(sphere_hit.distance, 0.0);
+    assert_eq!(sphere_hit.point, Vec3::new(0.5, 0.0, 0.0));
+
+    let box_hit =
+        oriented_bounding_box_with_ray(Vec3::ZERO, Vec3::Z, Vec3::splat(-1.0), Vec3::splat(1.0), Mat4::IDENTITY.to_cols_array_2d())
+            .unwrap();
+    assert_eq!(box_hit.distance, 0.0);
+}
+
+#[test]
+fn shapes_behind_the_ray_are_missed() {
+    assert_eq!(sphere_with_ray_collision(Vec3::new(0.0, 0.0, 5.0), Vec3::Z, 1.0, Vec3::ZERO), None);
+    let matrix = Mat4::IDENTITY.to_cols_array_2d();
+    assert_eq!(oriented_bounding_box_with_ray(Vec3::new(0.0, 0.0, 5.0), Vec3::Z, Vec3::splat(-1.0), Vec3::splat(1.0), matrix), None);
+}
+
+#[test]
+fn z_slab_uses_the_boxes_depth() {
+    //thin in z and tall in y, the old code used the y extents for z and hit it at 9 instead of 9.9
+    let matrix = Mat4::IDENTITY.to_cols_array_2d();
+    let hit = oriented_bounding_box_with_ray(
+        Vec3::new(0.0, 0.0, -10.0),
+        Vec3::Z,
+        Vec3::new(-1.0, -1.0, -0.1),
+        Vec3::new(1.0, 1.0, 0.1),
+        matrix,
+    )
+    .unwrap();
+    assert!((hit.distance - 9.9).abs() < 1e-5, "{:?}", hit);
+    assert_eq!(hit.normal, -Vec3::Z);
+    //and a ray passing beside it in z misses, instead of hitting a box 2 deep
+    let miss = oriented_bounding_box_with_ray(Vec3::new(-10.0, 0.0, 0.5), Vec3::X, Vec3::new(-1.0, -1.0, -0.1), Vec3::new(1.0, 1.0, 0.1), matrix);
+    assert_eq!(miss, None);
+}
+
+#[test]
+fn sphere_hit_distance_is_not_zero() {
+    let hit = sphere_with_ray_collision(Vec3::new(0.0, 0.0, -10.0), Vec3::Z, 2.0, Vec3::ZERO).unwrap();
+    assert!((hit.distance - 8.0).abs() < 1e-5, "{:?}", hit);
+    assert!(hit.normal.abs_diff_eq(-Vec3::Z, 1e-5));
+}
@@ -0,0 +1,90 @@
+use glam::Vec3;
+use instant::Duration;
+use vertix::{
+    app_resource::App,
+    controllers::CAMERA_FORWARD,
+    error::VertixError,
+    plugins::FlyCameraPlugin,
+    prelude::*,
+    replay::{InputRecording, RecordedInput},
+};
+
+fn headless() -> State {
+    pollster::block_on(AppBuilder::new(env!("OUT_DIR")).add_plugin(FlyCameraPlugin::default()).build_headless(64, 64, true)).unwrap()
+}
+fn camera(state: &State) -> (Vec3, f32, f32) {
+    let camera = &state.world.resource::<App>().camera.camera_transform;
+    (camera.position, camera.yaw, camera.pitch)
+}
+
+#[test]
+fn recordings_round_trip_through_a_file() {
+    let mut recording = InputRecording::new(64, 32);
+    recording.frames.push(Default::default());
+    recording.frames[0].dt = Duration::from_millis(16);
+    recording.frames[0].inputs = vec![
+        RecordedInput::Key { scancode: 17, key: VirtualKeyCode::W, state: ElementState::Pressed },
+        RecordedInput::MouseMotion(3.0, -1.5),
+        RecordedInput::Resized(32, 16),
+    ];
+    let path = std::env::temp_dir().join(format!("vertix_recording_{}.json", std::process::id()));
+    let path = path.to_str().unwrap();
+    recording.save_to_file(path).unwrap();
+    assert_eq!(InputRecording::load_from_file(path).unwrap(), recording);
+    assert_eq!(recording.duration(), Duration::from_millis(16));
+
+    std::fs::write(path, "[]").unwrap();
+    assert!(matches!(InputRecording::load_from_file(path), Err(VertixError::InvalidRecording { .. })));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn replaying_a_recording_ends_up_in_the_same_place() {
+    let mut state = headless();
+    let start = camera(&state);
+    state.start_recording();
+    let inputs = [
+        RecordedInput::Key { scancode: 17, key: VirtualKeyCode::W, state: ElementState::Pressed },
+        RecordedInput::MouseButton { button: MouseButton::Left, state: ElementState::Pressed },
+        RecordedInput::MouseMotion(12.0, -4.0),
+        RecordedInput::Key { scancode: 30, key: VirtualKeyCode::A, state: ElementState::Pressed },
+        RecordedInput::MouseMotion(-3.0, 1.0),
+        RecordedInput::Key { scancode: 17, key: VirtualKeyCode::W, state: ElementState::Released },
+        //let go of everything so the replay starts from the same input state the recording did
+        RecordedInput::Key { scancode: 30, key: VirtualKeyCode::A, state: ElementState::Released },
+        RecordedInput::MouseButton { button: MouseButton::Left, state: ElementState::Released },
+    ];
+    //uneven frame times, a replay has to use the recorded ones to land in the same place
+    for (i, input) in inputs.iter().enumerate() {
+        state.apply_input(input);
+        state.run_frame(Duration::from_millis(10 + 7 * i as u64));
+    }
+    state.run_frame(Duration::from_millis(33));
+    let recording = state.stop_recording().unwrap();
+    assert_eq!(recording.frames.len(), inputs.len() + 1);
+    assert_eq!(recording.duration(), Duration::from_millis(10 * 8 + 7 * 28 + 33));
+    let recorded = camera(&state);
+    assert_ne!(recorded.0, Vec3::ZERO);
+
+    //back to the start on the same State, a second gpu device in one process isn't reliable on every driver
+    let camera_transform = &mut state.world.resource_mut::<App>().into_inner().camera.camera_transform;
+    (camera_transform.position, camera_transform.yaw, camera_transform.pitch) = start;
+    state.run_replay(recording);
+    assert!(!state.is_replaying());
+    assert_eq!(camera(&state), recorded);
+
+    //the fly camera moves on the InputMap's camera axes, so rebinding one takes the old keys off it
+    state.world.resource_mut::<InputMap>().bind_axis(
+        CAMERA_FORWARD,
+        AxisBinding::new(InputBinding::Key(VirtualKeyCode::K), InputBinding::Key(VirtualKeyCode::I)),
+    );
+    let press = |key| RecordedInput::Key { scancode: 0, key, state: ElementState::Pressed };
+    state.apply_input(&press(VirtualKeyCode::W));
+    state.run_frame(Duration::from_millis(100));
+    assert_eq!(camera(&state), recorded);
+    state.apply_input(&press(VirtualKeyCode::I));
+    state.run_frame(Duration::from_millis(100));
+    let forward = state.world.resource::<App>().camera.camera_transform.forward();
+    let moved = camera(&state).0 - recorded.0;
+    assert!(moved.dot(forward) > 0.0, "moved {:?} while facing {:?}", moved, forward);
+}
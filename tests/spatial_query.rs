@@ -0,0 +1,83 @@
+use bevy_ecs::{prelude::*, system::SystemState};
+use glam::Vec3;
+use vertix::{
+    collision::{
+        spatial_query::{CollisionLayers, QueryFilter, SpatialQuery},
+        structs_3d::{Collider3D, Ray, Sphere, OBB},
+    },
+    instance::Instance,
+};
+
+fn cube_at(world: &mut World, z: f32) -> Entity {
+    world
+        .spawn((
+            Instance { position: Vec3::new(0.0, 0.0, z), ..Default::default() },
+            Collider3D::OBB(OBB::new(2.0, 2.0, 2.0)),
+        ))
+        .id()
+}
+fn forward_ray() -> Ray {
+    Ray { origin: Vec3::new(0.0, 0.0, -10.0), direction: Vec3::Z }
+}
+
+#[test]
+fn nearest_and_all_hits() {
+    let mut world = World::new();
+    let far = cube_at(&mut world, 5.0);
+    let near = cube_at(&mut world, 0.0);
+    let sphere = world
+        .spawn(Collider3D::Sphere(Sphere { center: Vec3::new(0.0, 0.0, 10.0), radius: 1.0 }))
+        .id();
+    let mut state = SystemState::<SpatialQuery>::new(&mut world);
+    let query = state.get(&world);
+
+    let hit = query.cast_ray(forward_ray(), 100.0, &QueryFilter::default()).unwrap();
+    assert_eq!(hit.entity, near);
+    assert!((hit.distance - 9.0).abs() < 1e-5);
+    assert_eq!(hit.normal, -Vec3::Z);
+    assert!(hit.point.abs_diff_eq(Vec3::new(0.0, 0.0, -1.0), 1e-5));
+
+    let hits = query.cast_ray_all(forward_ray(), 100.0, &QueryFilter::default());
+    assert_eq!(hits.iter().map(|hit| hit.entity).collect::<Vec<_>>(), vec![near, far, sphere]);
+
+    let in_range = query.cast_ray_all(forward_ray(), 15.0, &QueryFilter::default());
+    assert_eq!(in_range.len(), 2);
+}
+
+#[test]
+fn excluded_and_disabled_colliders_are_skipped() {
+    let mut world = World::new();
+    let far = cube_at(&mut world, 5.0);
+    let near = cube_at(&mut world, 0.0);
+    let disabled = world
+        .spawn((
+            Instance { position: Vec3::new(0.0, 0.0, -5.0), enabled: false, ..Default::default() },
+            Collider3D::OBB(OBB::new(2.0, 2.0, 2.0)),
+        ))
+        .id();
+    let mut state = SystemState::<SpatialQuery>::new(&mut world);
+    let query = state.get(&world);
+
+    assert_eq!(query.cast_ray(forward_ray(), 100.0, &QueryFilter::default()).unwrap().entity, near);
+    let excluded = QueryFilter::default().exclude(near);
+    assert_eq!(query.cast_ray(forward_ray(), 100.0, &excluded).unwrap().entity, far);
+    let with_disabled = QueryFilter { include_disabled: true, ..Default::default() };
+    assert_eq!(query.cast_ray(forward_ray(), 100.0, &with_disabled).unwrap().entity, disabled);
+}
+
+#[test]
+fn layer_masks() {
+    let mut world = World::new();
+    let near = cube_at(&mut world, 0.0);
+    let far = cube_at(&mut world, 5.0);
+    world.entity_mut(near).insert(CollisionLayers::layer(1));
+    world.entity_mut(far).insert(CollisionLayers::layer(2).with(3));
+    let mut state = SystemState::<SpatialQuery>::new(&mut world);
+    let query = state.get(&world);
+
+    let on = |layers| QueryFilter::default().with_layers(layers);
+    assert_eq!(query.cast_ray(forward_ray(), 100.0, &on(CollisionLayers::layer(1))).unwrap().entity, near);
+    assert_eq!(query.cast_ray(forward_ray(), 100.0, &on(CollisionLayers::layer(3))).unwrap().entity, far);
+    assert_eq!(query.cast_ray(forward_ray(), 100.0, &on(CollisionLayers::layer(4))), None);
+    assert_eq!(query.cast_ray(forward_ray(), 100.0, &on(CollisionLayers::NONE)), None);
+}
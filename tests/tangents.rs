@@ -0,0 +1,52 @@
+use glam::Vec3;
+use vertix::structs::{compute_tangents, Vertex};
+
+//a unit quad facing +z, tex coords go down in y like images do
+fn quad(tex_coords: [[f32; 2]; 4], normal: [f32; 3]) -> (Vec<Vertex>, Vec<u32>) {
+    let positions = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]];
+    let vertices = positions.iter().zip(tex_coords).map(|(position, uv)| Vertex::with_normal(*position, uv, normal)).collect();
+    (vertices, vec![0, 1, 2, 0, 2, 3])
+}
+fn assert_frame(vertices: &[Vertex], tangent: Vec3, bitangent: Vec3) {
+    for vertex in vertices {
+        assert!(Vec3::from(vertex.tangent).abs_diff_eq(tangent, 1e-5), "tangent {:?}", vertex.tangent);
+        assert!(Vec3::from(vertex.bitangent).abs_diff_eq(bitangent, 1e-5), "bitangent {:?}", vertex.bitangent);
+    }
+}
+
+#[test]
+fn quad_tangents_follow_the_texture() {
+    let (mut vertices, indices) = quad([[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]], [0.0, 0.0, 1.0]);
+    compute_tangents(&mut vertices, &indices);
+    assert_frame(&vertices, Vec3::X, Vec3::Y);
+}
+
+#[test]
+fn mirrored_tex_coords_flip_the_tangent_but_keep_the_bitangent_up() {
+    let (mut vertices, indices) = quad([[1.0, 1.0], [0.0, 1.0], [0.0, 0.0], [1.0, 0.0]], [0.0, 0.0, 1.0]);
+    compute_tangents(&mut vertices, &indices);
+    assert_frame(&vertices, -Vec3::X, Vec3::Y);
+}
+
+#[test]
+fn tangents_stay_perpendicular_to_tilted_normals() {
+    let normal = Vec3::new(0.3, 0.0, 1.0).normalize();
+    let (mut vertices, indices) = quad([[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]], normal.into());
+    compute_tangents(&mut vertices, &indices);
+    for vertex in &vertices {
+        let tangent = Vec3::from(vertex.tangent);
+        assert!(tangent.dot(normal).abs() < 1e-5);
+        assert!((tangent.length() - 1.0).abs() < 1e-5);
+        assert!(tangent.x > 0.9);
+    }
+}
+
+#[test]
+fn unlit_and_untextured_vertices_are_left_alone() {
+    let (mut unlit, indices) = quad([[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]], [0.0; 3]);
+    compute_tangents(&mut unlit, &indices);
+    assert_frame(&unlit, Vec3::ZERO, Vec3::ZERO);
+    let (mut untextured, indices) = quad([[0.5, 0.5]; 4], [0.0, 0.0, 1.0]);
+    compute_tangents(&mut untextured, &indices);
+    assert_frame(&untextured, Vec3::ZERO, Vec3::ZERO);
+}